@@ -0,0 +1,222 @@
+use std::{future::Future, marker::PhantomData};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use anyhow::{bail, Result};
+
+use crate::{
+    config::ContractAddresses,
+    uniswapv2_math::get_amount_out,
+    uniswapv2_pair::get_reserves_with_provider,
+    uniswapv3_quoter::{
+        self, QuoteExactInputSingleParams, UniswapV3QuoterCommand, UniswapV3QuoterResult,
+    },
+    unswapv3_pool::UniswapPoolFee,
+};
+
+/// Anything that can quote an exact input swap, so V2 and V3 liquidity can be
+/// compared without caring where the quote comes from
+pub trait AmmQuote {
+    /// The amount of `token_out` received for `amount_in` of `token_in`
+    fn quote(
+        &self,
+        amount_in: U256,
+        token_in: Address,
+        token_out: Address,
+    ) -> impl Future<Output = Result<U256>> + Send;
+}
+
+/// A Uniswap V2 pair, quoted from its current reserves
+#[derive(Debug, Clone)]
+pub struct V2Pair<T, P> {
+    pub address: Address,
+    client: P,
+    _transport: PhantomData<T>,
+}
+
+impl<T, P> V2Pair<T, P> {
+    pub fn new(address: Address, client: P) -> Self {
+        V2Pair {
+            address,
+            client,
+            _transport: PhantomData,
+        }
+    }
+}
+
+impl<T, P> AmmQuote for V2Pair<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    async fn quote(&self, amount_in: U256, token_in: Address, token_out: Address) -> Result<U256> {
+        let reserves = get_reserves_with_provider(self.address, &self.client).await?;
+        // 确认 pair 的两个代币正好是 token_in 和 token_out
+        let mut tokens = [reserves.token0, reserves.token1];
+        let mut expected = [token_in, token_out];
+        tokens.sort();
+        expected.sort();
+        if tokens != expected {
+            bail!("pair {} 不包含 {} 和 {}", self.address, token_in, token_out);
+        }
+        let (reserve_in, reserve_out) = reserves.reserves_for(token_in);
+        Ok(get_amount_out(amount_in, reserve_in, reserve_out)?)
+    }
+}
+
+/// A Uniswap V3 pool of one fee tier, quoted through the on-chain quoter
+#[derive(Debug, Clone)]
+pub struct V3Pool<T, P> {
+    pub fee: UniswapPoolFee,
+    pub addresses: ContractAddresses,
+    client: P,
+    _transport: PhantomData<T>,
+}
+
+impl<T, P> V3Pool<T, P> {
+    /// A pool of the mainnet deployment
+    pub fn new(fee: UniswapPoolFee, client: P) -> Self {
+        V3Pool {
+            fee,
+            addresses: ContractAddresses::default(),
+            client,
+            _transport: PhantomData,
+        }
+    }
+
+    pub fn with_addresses(mut self, addresses: ContractAddresses) -> Self {
+        self.addresses = addresses;
+        self
+    }
+}
+
+impl<T, P> AmmQuote for V3Pool<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    async fn quote(&self, amount_in: U256, token_in: Address, token_out: Address) -> Result<U256> {
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in,
+            token_out,
+            fee: self.fee,
+            amount_in,
+            sqrt_price_limit_x96: U256::ZERO,
+        });
+        match uniswapv3_quoter::execute_with_provider(command, &self.client, &self.addresses)
+            .await?
+        {
+            UniswapV3QuoterResult::QuoteExactInputSingle(amount_out) => Ok(amount_out),
+            _ => bail!("报价结果类型不正确"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy::{
+        primitives::{aliases::U112, Bytes, B256},
+        sol_types::SolCall,
+    };
+
+    use crate::{test_utils::MockTransport, uniswapv2_pair::UNIV2_PAIR};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pick_better_of_v2_and_v3() {
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let pair = Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap();
+        let amount_in = U256::from(10u128.pow(18));
+
+        let transport = MockTransport::default();
+        // V2: 30m USDC / 10k WETH
+        let reserves = UNIV2_PAIR::getReservesCall::abi_encode_returns(&(
+            U112::from(30_000_000_000_000u64),
+            U112::from(10u128.pow(22)),
+            1_700_000_000u32,
+        ));
+        transport.push_response(serde_json::json!(Bytes::from(reserves)));
+        transport.push_response(serde_json::json!(usdc.into_word()));
+        transport.push_response(serde_json::json!(weth.into_word()));
+        // V3 报价 3000 USDC
+        transport.push_response(serde_json::json!(B256::from(U256::from(3_000_000_000u64))));
+
+        let v2 = V2Pair::new(pair, transport.provider());
+        let v3 = V3Pool::new(UniswapPoolFee::Fee500, transport.provider());
+        let v2_out = v2.quote(amount_in, weth, usdc).await.unwrap();
+        let v3_out = v3.quote(amount_in, weth, usdc).await.unwrap();
+        assert_eq!(
+            get_amount_out(
+                amount_in,
+                U256::from(10u128.pow(22)),
+                U256::from(30_000_000_000_000u64)
+            )
+            .unwrap(),
+            v2_out
+        );
+        assert_eq!(U256::from(3_000_000_000u64), v3_out);
+        // V2 扣除 0.3% 手续费和价格影响后少于 3000 USDC
+        assert!(v3_out > v2_out);
+        assert_eq!(v3_out, v2_out.max(v3_out));
+    }
+
+    #[tokio::test]
+    async fn test_v2_pair_rejects_wrong_tokens() {
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let reserves = UNIV2_PAIR::getReservesCall::abi_encode_returns(&(
+            U112::from(1u64),
+            U112::from(1u64),
+            0u32,
+        ));
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(Bytes::from(reserves)));
+        transport.push_response(serde_json::json!(usdc.into_word()));
+        transport.push_response(serde_json::json!(weth.into_word()));
+
+        let v2 = V2Pair::new(Address::ZERO, transport.provider());
+        assert!(v2.quote(U256::from(1), usdt, usdc).await.is_err());
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use std::str::FromStr;
+
+    use alloy::providers::ProviderBuilder;
+
+    use crate::test_utils::spawn_mainnet_fork;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compare_v2_and_v3_on_fork() {
+        let fork = spawn_mainnet_fork();
+        let provider = ProviderBuilder::new()
+            .on_builtin(&fork.endpoint())
+            .await
+            .unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let amount_in = U256::from(10u128.pow(18));
+
+        let v2 = V2Pair::new(
+            Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap(),
+            &provider,
+        );
+        let v3 = V3Pool::new(UniswapPoolFee::Fee500, &provider);
+        let v2_out = v2.quote(amount_in, weth, usdc).await.unwrap();
+        let v3_out = v3.quote(amount_in, weth, usdc).await.unwrap();
+        assert!(v2_out > U256::ZERO);
+        // 0.05% 池子的手续费更低、流动性更深
+        assert!(v3_out > v2_out);
+    }
+}
@@ -1,25 +1,61 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
 use alloy::{
-    network::TransactionBuilder,
-    primitives::{Address, U160, U256},
-    providers::ProviderBuilder,
+    consensus::{TxType, TypedTransaction},
+    eips::BlockNumberOrTag,
+    network::{Ethereum, EthereumWallet, NetworkWallet, TransactionBuilder},
+    primitives::{Address, TxHash, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    transports::Transport,
 };
 // send a swap transaction
-use anyhow::{Ok, Result};
+use anyhow::{bail, Ok, Result};
+use futures::future::join_all;
 
 use crate::{
-    uniswapv3_router::{self, ExactInputSingleParams, UniswapV3RouterCommand},
-    unswapv3_pool::UniswapPoolFee,
+    config::{ContractAddresses, NetworkError},
+    logging,
+    uniswapv2_router::{self, SwapExactInputParams},
+    uniswapv3_factory::{
+        self, GetPoolParam, UniswapV3FactoryCommand, UniswapV3FactoryError, UniswapV3FactoryResult,
+    },
+    uniswapv3_math::{
+        min_amount_with_slippage, sort_tokens, validate_sqrt_price_limit, zero_for_one,
+    },
+    uniswapv3_quoter::{
+        self, QuoteExactInputSingleParams, QuoteExactOutputSingleParams, UniswapV3QuoterCommand,
+        UniswapV3QuoterError, UniswapV3QuoterResult,
+    },
+    uniswapv3_router::{
+        self, exact_input_single_and_unwrap, ExactInputSingleParams, UniswapV3RouterCommand,
+        UniswapV3RouterResult,
+    },
+    unswapv3_pool::{UniswapPoolFee, UNIV3_POOL},
+    utils::{to_readable_amount, Token},
+    weth::weth_address,
 };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UniswapSupportChain {
     Ethereum,
     Base,
+    /// Ethereum Sepolia testnet
+    Sepolia,
 }
 
 impl UniswapSupportChain {
     pub fn get_rpc_url(&self) -> String {
         match self {
-            UniswapSupportChain::Ethereum => "https://mainnet.base.org".to_string(),
+            UniswapSupportChain::Ethereum => "https://eth.llamarpc.com".to_string(),
             UniswapSupportChain::Base => "https://mainnet.base.org".to_string(),
+            UniswapSupportChain::Sepolia => {
+                "https://ethereum-sepolia-rpc.publicnode.com".to_string()
+            }
         }
     }
 
@@ -27,15 +63,211 @@ impl UniswapSupportChain {
         match self {
             UniswapSupportChain::Ethereum => 1,
             UniswapSupportChain::Base => 8453,
+            UniswapSupportChain::Sepolia => 11155111,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ClientError {
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("RPC 的链 ID {actual} 与选择的链 {expected} 不一致")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("RPC chain id {actual} does not match the selected chain {expected}")
+    )]
+    ChainMismatch { expected: u64, actual: u64 },
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("deadline {deadline} 早于最新区块时间 {block_timestamp}，交易一定会失败")
+    )]
+    #[cfg_attr(feature = "locale-en", error("deadline {deadline} is before the latest block timestamp {block_timestamp}, the transaction would always fail"))]
+    DeadlineInPast {
+        deadline: U256,
+        block_timestamp: u64,
+    },
+    #[cfg_attr(not(feature = "locale-en"), error("无法获取最新区块"))]
+    #[cfg_attr(feature = "locale-en", error("latest block not found"))]
+    LatestBlockNotFound,
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("缺少参数 recipient，且没有设置钱包，无法确定接收地址")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("missing recipient and no wallet set, cannot determine who receives the output")
+    )]
+    MissingRecipient,
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("gas limit 倍数不能小于 1.0，当前{0}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("gas limit multiplier must be at least 1.0, got {0}")
+    )]
+    InvalidGasLimitMultiplier(String),
+    #[cfg_attr(not(feature = "locale-en"), error("交易执行失败 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("transaction reverted {0}"))]
+    TransactionReverted(TxHash),
+    #[cfg_attr(not(feature = "locale-en"), error("交易缺少字段{0}"))]
+    #[cfg_attr(feature = "locale-en", error("transaction is missing fields: {0}"))]
+    IncompleteTransaction(String),
+    #[cfg_attr(not(feature = "locale-en"), error("交易 {0} 的回执中没有 Swap 事件"))]
+    #[cfg_attr(feature = "locale-en", error("no Swap event in the receipt of {0}"))]
+    MissingSwapEvent(TxHash),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("第 {index} 个兑换构建失败: {reason}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("failed to build swap #{index}: {reason}")
+    )]
+    SwapFailed { index: usize, reason: String },
+}
+
+impl ClientError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            ClientError::ChainMismatch { .. } => "CHAIN_MISMATCH",
+            ClientError::DeadlineInPast { .. } => "DEADLINE_IN_PAST",
+            ClientError::LatestBlockNotFound => "LATEST_BLOCK_NOT_FOUND",
+            ClientError::MissingRecipient => "MISSING_RECIPIENT",
+            ClientError::InvalidGasLimitMultiplier(_) => "INVALID_GAS_LIMIT_MULTIPLIER",
+            ClientError::TransactionReverted(_) => "TRANSACTION_REVERTED",
+            ClientError::IncompleteTransaction(_) => "INCOMPLETE_TRANSACTION",
+            ClientError::MissingSwapEvent(_) => "MISSING_SWAP_EVENT",
+            ClientError::SwapFailed { .. } => "SWAP_FAILED",
+        }
+    }
+}
+
+/// Make sure `rpc_url` serves `chain`, so a transaction is never built for
+/// one network while the RPC points at another
+pub async fn check_chain_id(chain: &UniswapSupportChain, rpc_url: &str) -> Result<()> {
+    let provider = ProviderBuilder::new().on_builtin(rpc_url).await?;
+    check_chain_id_with_provider(chain, &provider).await
+}
+
+/// Same as `check_chain_id` but reuses an already constructed provider
+pub async fn check_chain_id_with_provider<T, P>(
+    chain: &UniswapSupportChain,
+    client: P,
+) -> Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let actual = client.get_chain_id().await?;
+    let expected = chain.as_chain_id();
+    if actual != expected {
+        return Err(ClientError::ChainMismatch { expected, actual }.into());
+    }
+    Ok(())
+}
+
+/// Check `deadline` against the latest block of `rpc_url`. A deadline that is
+/// not after the latest block fails with `DeadlineInPast`, zero is replaced by
+/// `DEFAULT_DEADLINE_SECS` after the latest block.
+pub async fn resolve_deadline(deadline: U256, rpc_url: &str) -> Result<U256> {
+    let provider = ProviderBuilder::new().on_builtin(rpc_url).await?;
+    resolve_deadline_with_provider(deadline, &provider).await
+}
+
+/// Same as `resolve_deadline` but reuses an already constructed provider
+pub async fn resolve_deadline_with_provider<T, P>(deadline: U256, client: P) -> Result<U256>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let block = client
+        .get_block_by_number(BlockNumberOrTag::Latest, false)
+        .await?
+        .ok_or(ClientError::LatestBlockNotFound)?;
+    let block_timestamp = block.header.timestamp;
+    if deadline.is_zero() {
+        return Ok(U256::from(block_timestamp + DEFAULT_DEADLINE_SECS as u64));
+    }
+    // 交易最早被打包进下一个区块，时间戳一定大于最新区块
+    if deadline <= U256::from(block_timestamp) {
+        return Err(ClientError::DeadlineInPast {
+            deadline,
+            block_timestamp,
         }
+        .into());
+    }
+    Ok(deadline)
+}
+
+/// Safety margin applied on top of `eth_estimateGas` by default
+pub const DEFAULT_GAS_LIMIT_MULTIPLIER: f64 = 1.2;
+
+fn validate_gas_limit_multiplier(gas_limit_multiplier: f64) -> Result<(), ClientError> {
+    // NaN 也会被拒绝
+    if !(1.0..=f64::MAX).contains(&gas_limit_multiplier) {
+        return Err(ClientError::InvalidGasLimitMultiplier(
+            gas_limit_multiplier.to_string(),
+        ));
+    }
+    Result::Ok(())
+}
+
+/// `estimate * gas_limit_multiplier`, rounded up. The multiplier is applied in
+/// bps so that e.g. 1.2 does not round up an exact estimate because of `f64` error.
+fn scale_gas_limit(estimate: u64, gas_limit_multiplier: f64) -> u64 {
+    let bps = (gas_limit_multiplier * 10_000.0).round() as u128;
+    let scaled = (estimate as u128 * bps).div_ceil(10_000);
+    u64::try_from(scaled).unwrap_or(u64::MAX)
+}
+
+/// Estimate `tx` with `eth_estimateGas` and set its gas limit to the estimate
+/// times `gas_limit_multiplier`, so a swap whose cost grows a little between
+/// estimation and inclusion does not run out of gas
+pub async fn fill_gas_limit<T, P>(
+    tx: TransactionRequest,
+    gas_limit_multiplier: f64,
+    client: P,
+) -> Result<TransactionRequest>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    validate_gas_limit_multiplier(gas_limit_multiplier)?;
+    let estimate = client.estimate_gas(&tx).await?;
+    Ok(tx.with_gas_limit(scale_gas_limit(estimate, gas_limit_multiplier)))
+}
+
+/// Stamp `tx` with the chain id of `chain` and build it as an EIP-1559 transaction,
+/// e.g. for a request from `uniswapv3_router::build_tx` signed offline. A legacy
+/// `gas_price` is used as both the max fee and the priority fee. Fails with
+/// `IncompleteTransaction` listing what is missing, such as the nonce or gas limit.
+pub fn finalize_tx(
+    mut tx: TransactionRequest,
+    chain: &UniswapSupportChain,
+) -> Result<TypedTransaction, ClientError> {
+    if let Some(gas_price) = tx.gas_price.take() {
+        tx.max_fee_per_gas.get_or_insert(gas_price);
+        tx.max_priority_fee_per_gas.get_or_insert(gas_price);
     }
+    tx.chain_id = Some(chain.as_chain_id());
+    tx.transaction_type = Some(TxType::Eip1559 as u8);
+    tx.complete_1559()
+        .map_err(|missing| ClientError::IncompleteTransaction(missing.join(", ")))?;
+    tx.build_typed_tx()
+        .map_err(|_| ClientError::IncompleteTransaction(String::new()))
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SwapDirection {
     ExactInput,
     ExactOutput,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UniswapVersion {
     V2,
     V3,
@@ -47,8 +279,187 @@ pub struct SwapParams {
     pub amount_in: U256,
     pub amount_out_min: U256,
     pub pool_fee: UniswapPoolFee,
-    pub recipient: Address,
+    /// None 时使用 sender
+    pub recipient: Option<Address>,
+    /// 签名钱包的地址，设置后作为交易的 from
+    pub sender: Option<Address>,
     pub deadline: U256,
+    /// 使用原生 ETH 支付，token_in 会被替换为 WETH 并设置交易的 value
+    pub is_native_in: bool,
+    /// 接收原生 ETH，token_out 会被替换为 WETH 并追加 unwrapWETH9
+    pub is_native_out: bool,
+    /// 0 表示不限制价格
+    pub sqrt_price_limit_x96: U256,
+    /// Set for tokens that take a fee on every transfer (e.g. reflection tokens
+    /// like SAFEMOON or taxed meme tokens). On V2 this selects the
+    /// `*SupportingFeeOnTransferTokens` router methods. V3's SwapRouter has no
+    /// such variants: `amount_out_min` is checked against the pool output so a
+    /// fee on `token_out` only reduces what arrives, while a fee on `token_in`
+    /// makes the pool revert, so V3 swaps are encoded unchanged.
+    pub supports_fee_on_transfer: bool,
+    /// 允许 V3 兑换的 amount_out_min 为 0，即接受任意输出
+    pub allow_zero_min: bool,
+}
+
+impl SwapParams {
+    pub fn builder() -> SwapParamsBuilder {
+        SwapParamsBuilder::default()
+    }
+
+    /// `token_in` and `token_out` with native ETH replaced by WETH
+    fn resolved_tokens(&self, chain: &UniswapSupportChain) -> (Address, Address) {
+        let weth = weth_address(chain);
+        let token_in = if self.is_native_in {
+            weth
+        } else {
+            self.token_in
+        };
+        let token_out = if self.is_native_out {
+            weth
+        } else {
+            self.token_out
+        };
+        (token_in, token_out)
+    }
+
+    /// The address receiving the output, `recipient` if set, otherwise the sender
+    pub fn resolved_recipient(&self) -> Result<Address, ClientError> {
+        self.recipient
+            .or(self.sender)
+            .ok_or(ClientError::MissingRecipient)
+    }
+}
+
+/// Deadline used when none is set, in seconds from now
+pub const DEFAULT_DEADLINE_SECS: i64 = 20 * 60;
+
+/// Builder for `SwapParams`. `token_in`, `token_out`, `amount_in` and either
+/// `recipient` or a wallet are required; the recipient defaults to the wallet's
+/// address, the fee to 0.3%, the deadline to 20 minutes from `build()`, and
+/// there is no price limit. A V3 swap without `amount_out_min` is rejected when
+/// it is built unless `allow_zero_min(true)` is set.
+#[derive(Default)]
+pub struct SwapParamsBuilder {
+    token_in: Option<Address>,
+    token_out: Option<Address>,
+    amount_in: Option<U256>,
+    amount_out_min: U256,
+    pool_fee: Option<UniswapPoolFee>,
+    recipient: Option<Address>,
+    sender: Option<Address>,
+    deadline: Option<U256>,
+    is_native_in: bool,
+    is_native_out: bool,
+    sqrt_price_limit_x96: U256,
+    supports_fee_on_transfer: bool,
+    allow_zero_min: bool,
+}
+
+impl SwapParamsBuilder {
+    pub fn token_in(mut self, token_in: Address) -> Self {
+        self.token_in = Some(token_in);
+        self
+    }
+
+    pub fn token_out(mut self, token_out: Address) -> Self {
+        self.token_out = Some(token_out);
+        self
+    }
+
+    pub fn amount_in(mut self, amount_in: U256) -> Self {
+        self.amount_in = Some(amount_in);
+        self
+    }
+
+    pub fn amount_out_min(mut self, amount_out_min: U256) -> Self {
+        self.amount_out_min = amount_out_min;
+        self
+    }
+
+    pub fn pool_fee(mut self, pool_fee: UniswapPoolFee) -> Self {
+        self.pool_fee = Some(pool_fee);
+        self
+    }
+
+    pub fn recipient(mut self, recipient: Address) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    /// Send from `sender`, which also receives the output unless `recipient` is set
+    pub fn sender(mut self, sender: Address) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// Send from the wallet's default signer
+    pub fn wallet(self, wallet: &EthereumWallet) -> Self {
+        self.sender(NetworkWallet::<Ethereum>::default_signer_address(wallet))
+    }
+
+    pub fn deadline(mut self, deadline: U256) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn native_in(mut self, is_native_in: bool) -> Self {
+        self.is_native_in = is_native_in;
+        self
+    }
+
+    pub fn native_out(mut self, is_native_out: bool) -> Self {
+        self.is_native_out = is_native_out;
+        self
+    }
+
+    pub fn sqrt_price_limit_x96(mut self, sqrt_price_limit_x96: U256) -> Self {
+        self.sqrt_price_limit_x96 = sqrt_price_limit_x96;
+        self
+    }
+
+    pub fn supports_fee_on_transfer(mut self, supports_fee_on_transfer: bool) -> Self {
+        self.supports_fee_on_transfer = supports_fee_on_transfer;
+        self
+    }
+
+    /// Opt in to a V3 swap without `amount_out_min`, accepting any output
+    pub fn allow_zero_min(mut self, allow_zero_min: bool) -> Self {
+        self.allow_zero_min = allow_zero_min;
+        self
+    }
+
+    pub fn build(self) -> Result<SwapParams> {
+        let Some(token_in) = self.token_in else {
+            bail!("缺少参数 token_in");
+        };
+        let Some(token_out) = self.token_out else {
+            bail!("缺少参数 token_out");
+        };
+        let Some(amount_in) = self.amount_in else {
+            bail!("缺少参数 amount_in");
+        };
+        if self.recipient.is_none() && self.sender.is_none() {
+            return Err(ClientError::MissingRecipient.into());
+        }
+        let deadline = self.deadline.unwrap_or_else(|| {
+            U256::from((chrono::Utc::now().timestamp() + DEFAULT_DEADLINE_SECS) as u64)
+        });
+        Ok(SwapParams {
+            token_in,
+            token_out,
+            amount_in,
+            amount_out_min: self.amount_out_min,
+            pool_fee: self.pool_fee.unwrap_or(UniswapPoolFee::Fee3000),
+            recipient: self.recipient,
+            sender: self.sender,
+            deadline,
+            is_native_in: self.is_native_in,
+            is_native_out: self.is_native_out,
+            sqrt_price_limit_x96: self.sqrt_price_limit_x96,
+            supports_fee_on_transfer: self.supports_fee_on_transfer,
+            allow_zero_min: self.allow_zero_min,
+        })
+    }
 }
 
 pub async fn swap(
@@ -57,49 +468,1530 @@ pub async fn swap(
     uniswap_version: UniswapVersion,
     params: SwapParams,
     rpc_url: String,
-) -> Result<()> {
-    match uniswap_version {
-        UniswapVersion::V2 => {
-            // V2 逻辑
-            Ok(())
+) -> Result<TransactionRequest> {
+    let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
+    swap_with_provider(chain, direction, uniswap_version, params, &provider).await
+}
+
+/// Same as `swap` but reuses an already constructed provider
+pub async fn swap_with_provider<T, P>(
+    chain: UniswapSupportChain,
+    direction: SwapDirection,
+    uniswap_version: UniswapVersion,
+    mut params: SwapParams,
+    client: P,
+) -> Result<TransactionRequest>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    params.resolved_recipient()?;
+    if matches!(uniswap_version, UniswapVersion::V3) {
+        // 在访问 RPC 之前拒绝没有原版 SwapRouter 的链
+        ContractAddresses::periphery_for_chain(&chain)?;
+    }
+    check_chain_id_with_provider(&chain, &client).await?;
+    params.deadline = resolve_deadline_with_provider(params.deadline, &client).await?;
+    if matches!(uniswap_version, UniswapVersion::V3)
+        && matches!(direction, SwapDirection::ExactInput)
+        && !params.sqrt_price_limit_x96.is_zero()
+    {
+        // 价格限制方向错误时合约只会回滚 SPL，这里提前检查
+        let (token_in, token_out) = params.resolved_tokens(&chain);
+        let pool = uniswapv3_factory::execute_with_provider(
+            UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+                token_a: token_in,
+                token_b: token_out,
+                fee: params.pool_fee.as_u32(),
+            }),
+            &client,
+            &ContractAddresses::for_chain(&chain),
+        )
+        .await?;
+        if let UniswapV3FactoryResult::GetPoolWithState(pool) = pool {
+            if !pool.exists {
+                return Err(UniswapV3FactoryError::PoolNotFound(
+                    token_in,
+                    token_out,
+                    params.pool_fee.as_u32(),
+                )
+                .into());
+            }
+            validate_sqrt_price_limit(
+                params.sqrt_price_limit_x96,
+                pool.sqrt_price_x96,
+                zero_for_one(token_in, token_out),
+            )?;
         }
+    }
+    let tx = build_swap_tx(chain, direction, uniswap_version, params)?;
+    logging::log_tx("client", &tx);
+    Ok(tx)
+}
+
+/// Build several independent swaps at once, e.g. to rebalance a portfolio. Each
+/// swap goes through the same checks as `swap`, concurrently, and the transactions
+/// come back in the order of `params`. Fails with `ClientError::SwapFailed` naming
+/// the first swap that could not be built.
+pub async fn build_swaps(
+    chain: UniswapSupportChain,
+    direction: SwapDirection,
+    uniswap_version: UniswapVersion,
+    params: Vec<SwapParams>,
+    rpc_url: String,
+) -> Result<Vec<TransactionRequest>> {
+    let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
+    let txs =
+        join_all(params.into_iter().map(|params| {
+            swap_with_provider(chain, direction, uniswap_version, params, &provider)
+        }))
+        .await;
+    txs.into_iter()
+        .enumerate()
+        .map(|(index, tx)| {
+            tx.map_err(|e| {
+                ClientError::SwapFailed {
+                    index,
+                    reason: e.to_string(),
+                }
+                .into()
+            })
+        })
+        .collect()
+}
+
+/// Dry-run variant of `swap`: build the same transaction purely from the ABI,
+/// without connecting to a RPC, e.g. to hand the calldata to a hardware wallet.
+/// Nothing is checked against the chain, and a zero deadline defaults to
+/// `DEFAULT_DEADLINE_SECS` from the local clock. V3 swaps are sent to the router
+/// of `ContractAddresses::periphery_for_chain`.
+pub fn build_swap_tx(
+    chain: UniswapSupportChain,
+    direction: SwapDirection,
+    uniswap_version: UniswapVersion,
+    params: SwapParams,
+) -> Result<TransactionRequest> {
+    let recipient = params.resolved_recipient()?;
+    let (token_in, token_out) = params.resolved_tokens(&chain);
+    let deadline = if params.deadline.is_zero() {
+        U256::from((chrono::Utc::now().timestamp() + DEFAULT_DEADLINE_SECS) as u64)
+    } else {
+        params.deadline
+    };
+    let tx = match uniswap_version {
+        UniswapVersion::V2 => match direction {
+            SwapDirection::ExactInput => {
+                uniswapv2_router::swap_exact_input_tx(SwapExactInputParams {
+                    amount_in: params.amount_in,
+                    amount_out_min: params.amount_out_min,
+                    path: vec![token_in, token_out],
+                    to: recipient,
+                    deadline,
+                    is_native_in: params.is_native_in,
+                    is_native_out: params.is_native_out,
+                    supports_fee_on_transfer: params.supports_fee_on_transfer,
+                })?
+            }
+            SwapDirection::ExactOutput => bail!("暂不支持 Uniswap V2 ExactOutput 兑换"),
+        },
         UniswapVersion::V3 => {
+            let addresses = ContractAddresses::periphery_for_chain(&chain)?;
             // 判断是 ExactInput 还是 ExactOutput
             match direction {
                 SwapDirection::ExactInput => {
-                    let params = ExactInputSingleParams {
-                        token_in: params.token_in,
-                        token_out: params.token_out,
+                    let router_params = ExactInputSingleParams {
+                        token_in,
+                        token_out,
                         fee: params.pool_fee,
-                        recipient: params.recipient,
-                        deadline: params.deadline,
+                        recipient,
+                        deadline,
                         amount_in: params.amount_in,
                         amount_out_minimum: params.amount_out_min,
-                        sqrt_price_limit_x96: U256::from(0),
+                        sqrt_price_limit_x96: params.sqrt_price_limit_x96,
+                        native_value: params.is_native_in,
+                        allow_zero_min: params.allow_zero_min,
                     };
-                    let tx = crate::uniswapv3_router::execute(
-                        UniswapV3RouterCommand::ExactInputSingle(params),
-                        rpc_url,
-                    )
-                    .await?;
-                    let tx = tx.with_chain_id(chain.as_chain_id());
-                    println!("tx: {:?}", tx);
-                    Ok(())
+                    let command = if params.is_native_out {
+                        exact_input_single_and_unwrap(router_params, &addresses)?
+                    } else {
+                        UniswapV3RouterCommand::ExactInputSingle(router_params)
+                    };
+                    uniswapv3_router::build_tx(command, &addresses)?
                 }
-                SwapDirection::ExactOutput => Ok(()),
+                SwapDirection::ExactOutput => bail!("暂不支持 ExactOutput 兑换"),
             }
         }
+    };
+    let tx = tx.with_chain_id(chain.as_chain_id());
+    Ok(match params.sender {
+        Some(sender) => tx.with_from(sender),
+        None => tx,
+    })
+}
+
+pub struct QuoteParams {
+    pub token_in: Address,
+    pub token_out: Address,
+    /// ExactInput 时为输入数量，ExactOutput 时为期望的输出数量
+    pub amount: U256,
+    pub pool_fee: UniswapPoolFee,
+}
+
+/// Quote a swap on the given chain through the quoter of
+/// `ContractAddresses::periphery_for_chain`, returning the amount out for
+/// `ExactInput` or the amount in required for `ExactOutput`. Fails with
+/// `ChainMismatch` when `rpc_url` serves another chain.
+pub async fn quote(
+    chain: UniswapSupportChain,
+    uniswap_version: UniswapVersion,
+    direction: SwapDirection,
+    params: QuoteParams,
+    rpc_url: String,
+) -> Result<U256> {
+    let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
+    quote_with_provider(chain, uniswap_version, direction, params, &provider).await
+}
+
+/// Same as `quote` but reuses an already constructed provider
+pub async fn quote_with_provider<T, P>(
+    chain: UniswapSupportChain,
+    uniswap_version: UniswapVersion,
+    direction: SwapDirection,
+    params: QuoteParams,
+    client: P,
+) -> Result<U256>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    match uniswap_version {
+        UniswapVersion::V2 => bail!("暂不支持 Uniswap V2 报价"),
+        UniswapVersion::V3 => {
+            let addresses = ContractAddresses::periphery_for_chain(&chain)?;
+            check_chain_id_with_provider(&chain, &client).await?;
+            let command = match direction {
+                SwapDirection::ExactInput => {
+                    UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+                        token_in: params.token_in,
+                        token_out: params.token_out,
+                        fee: params.pool_fee,
+                        amount_in: params.amount,
+                        sqrt_price_limit_x96: U256::ZERO,
+                    })
+                }
+                SwapDirection::ExactOutput => {
+                    UniswapV3QuoterCommand::QuoteExactOutputSingle(QuoteExactOutputSingleParams {
+                        token_in: params.token_in,
+                        token_out: params.token_out,
+                        fee: params.pool_fee,
+                        amount_out: params.amount,
+                        sqrt_price_limit_x96: U256::ZERO,
+                    })
+                }
+            };
+            let res = uniswapv3_quoter::execute_with_provider(command, &client, &addresses).await?;
+            Ok(res.amount())
+        }
+    }
+}
+
+pub struct SwapAndSendParams {
+    pub token_in: Token,
+    pub token_out: Token,
+    pub amount_in: U256,
+    pub pool_fee: UniswapPoolFee,
+    /// 相对报价可接受的滑点
+    pub slippage_bps: u32,
+}
+
+/// Run the whole V3 exact input flow for `wallet`: approve the router when the
+/// current allowance is not enough, quote, apply `slippage_bps`, send the swap
+/// and wait for its receipt. Both legs must be ERC20s, wrap ETH beforehand.
+pub async fn swap_and_send(
+    chain: UniswapSupportChain,
+    wallet: EthereumWallet,
+    params: SwapAndSendParams,
+    rpc_url: String,
+) -> Result<TransactionReceipt> {
+    let addresses = ContractAddresses::periphery_for_chain(&chain)?;
+    let sender = NetworkWallet::<Ethereum>::default_signer_address(&wallet);
+    let provider = ProviderBuilder::new()
+        .with_recommended_fillers()
+        .wallet(wallet)
+        .on_builtin(&rpc_url)
+        .await?;
+    check_chain_id_with_provider(&chain, &provider).await?;
+
+    let token_in = &params.token_in;
+    let allowance = token_in
+        .allowance(sender, addresses.router, &provider)
+        .await?;
+    if allowance < params.amount_in {
+        let mut approvals = Vec::new();
+        // USDT 等 token 要求先把非零额度清零才能重新授权
+        if !allowance.is_zero() {
+            approvals.push(token_in.approve_tx(addresses.router, U256::ZERO));
+        }
+        approvals.push(token_in.approve_tx(addresses.router, params.amount_in));
+        for tx in approvals {
+            send_and_confirm(&provider, tx.with_from(sender)).await?;
+        }
+    }
+
+    let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+        token_in: token_in.address,
+        token_out: params.token_out.address,
+        fee: params.pool_fee,
+        amount_in: params.amount_in,
+        sqrt_price_limit_x96: U256::ZERO,
+    });
+    let quoted =
+        match uniswapv3_quoter::execute_with_provider(command, &provider, &addresses).await? {
+            UniswapV3QuoterResult::QuoteExactInputSingle(amount_out) => amount_out,
+            _ => bail!("报价结果类型不正确"),
+        };
+    let swap_params = SwapParams::builder()
+        .token_in(token_in.address)
+        .token_out(params.token_out.address)
+        .amount_in(params.amount_in)
+        .amount_out_min(min_amount_with_slippage(quoted, params.slippage_bps)?)
+        .pool_fee(params.pool_fee)
+        .sender(sender)
+        .build()?;
+    let tx = swap_with_provider(
+        chain,
+        SwapDirection::ExactInput,
+        UniswapVersion::V3,
+        swap_params,
+        &provider,
+    )
+    .await?;
+    send_and_confirm(&provider, tx).await
+}
+
+async fn send_and_confirm<T, P>(client: &P, tx: TransactionRequest) -> Result<TransactionReceipt>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let receipt = client.send_transaction(tx).await?.get_receipt().await?;
+    if !receipt.status() {
+        return Err(ClientError::TransactionReverted(receipt.transaction_hash).into());
+    }
+    Ok(receipt)
+}
+
+/// Summary of an exact input single swap, either sent or simulated
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapResult {
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// token_out per token_in，已按两个 token 的 decimals 换算
+    pub effective_price: f64,
+    /// 按池子费率从 amount_in 中扣除的手续费，单位为 token_in
+    pub fee_paid: U256,
+    /// 模拟兑换时为 None
+    pub gas_used: Option<u128>,
+}
+
+impl SwapResult {
+    pub fn new(
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: U256,
+        amount_out: U256,
+        pool_fee: UniswapPoolFee,
+        gas_used: Option<u128>,
+    ) -> Self {
+        let readable_in = to_readable_amount(amount_in, token_in.decimals);
+        let effective_price = if readable_in > 0.0 {
+            to_readable_amount(amount_out, token_out.decimals) / readable_in
+        } else {
+            0.0
+        };
+        SwapResult {
+            amount_in,
+            amount_out,
+            effective_price,
+            fee_paid: amount_in * U256::from(pool_fee.as_u32()) / U256::from(1_000_000),
+            gas_used,
+        }
+    }
+
+    /// From the output of `uniswapv3_router::simulate_swap` for the swap `params` describes
+    pub fn from_simulation(
+        params: &SwapAndSendParams,
+        simulated: &UniswapV3RouterResult,
+    ) -> Result<Self> {
+        let UniswapV3RouterResult::ExactInputSingle(amount_out) = simulated else {
+            bail!("模拟结果不是 exact input single 兑换: {:?}", simulated);
+        };
+        Ok(SwapResult::new(
+            &params.token_in,
+            &params.token_out,
+            params.amount_in,
+            *amount_out,
+            params.pool_fee,
+            None,
+        ))
+    }
+
+    /// From the receipt returned by `swap_and_send`, reading the amounts from the
+    /// pool's `Swap` event rather than trusting the requested amount
+    pub fn from_receipt(params: &SwapAndSendParams, receipt: &TransactionReceipt) -> Result<Self> {
+        let swap = receipt
+            .inner
+            .logs()
+            .iter()
+            .find_map(|log| log.log_decode::<UNIV3_POOL::Swap>().ok())
+            .ok_or(ClientError::MissingSwapEvent(receipt.transaction_hash))?
+            .inner
+            .data;
+        // 正数是转入池子的数量，负数是池子转出的数量
+        let (delta_in, delta_out) =
+            if zero_for_one(params.token_in.address, params.token_out.address) {
+                (swap.amount0, swap.amount1)
+            } else {
+                (swap.amount1, swap.amount0)
+            };
+        Ok(SwapResult::new(
+            &params.token_in,
+            &params.token_out,
+            delta_in.unsigned_abs(),
+            delta_out.unsigned_abs(),
+            params.pool_fee,
+            Some(receipt.gas_used),
+        ))
+    }
+}
+
+/// `(token0, token1, fee)` with the pair sorted, so both orders share an entry
+type PoolKey = (Address, Address, u32);
+
+/// `(token_in, token_out, fee, amount_in)`, the direction matters for a quote
+type QuoteKey = (Address, Address, u32, U256);
+
+/// Quotes are only reused for `ttl` since the pool price keeps moving
+struct QuoteCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<QuoteKey, (Instant, U256)>>,
+}
+
+/// Long-lived entry point holding a provider and the contract addresses, for
+/// apps that make many calls against the same RPC
+pub struct UniswapClient<T, P> {
+    provider: P,
+    addresses: ContractAddresses,
+    /// 池子地址不会变化，可以一直缓存
+    pool_cache: Option<Mutex<HashMap<PoolKey, Address>>>,
+    quote_cache: Option<QuoteCache>,
+    gas_limit_multiplier: f64,
+    _transport: PhantomData<T>,
+}
+
+impl<T, P> UniswapClient<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    /// A client for the mainnet deployment without any caching
+    pub fn new(provider: P) -> Self {
+        UniswapClient {
+            provider,
+            addresses: ContractAddresses::default(),
+            pool_cache: None,
+            quote_cache: None,
+            gas_limit_multiplier: DEFAULT_GAS_LIMIT_MULTIPLIER,
+            _transport: PhantomData,
+        }
+    }
+
+    /// A client for the deployment of `chain`, see `ContractAddresses::periphery_for_chain`
+    pub fn for_chain(provider: P, chain: &UniswapSupportChain) -> Result<Self, NetworkError> {
+        Result::Ok(
+            Self::new(provider).with_addresses(ContractAddresses::periphery_for_chain(chain)?),
+        )
+    }
+
+    /// Margin used by `fill_gas_limit`, at least 1.0
+    pub fn with_gas_limit_multiplier(
+        mut self,
+        gas_limit_multiplier: f64,
+    ) -> Result<Self, ClientError> {
+        validate_gas_limit_multiplier(gas_limit_multiplier)?;
+        self.gas_limit_multiplier = gas_limit_multiplier;
+        Result::Ok(self)
+    }
+
+    pub fn with_addresses(mut self, addresses: ContractAddresses) -> Self {
+        self.addresses = addresses;
+        self
+    }
+
+    /// Memoize `get_pool` lookups in memory
+    pub fn with_pool_cache(mut self) -> Self {
+        self.pool_cache = Some(Mutex::new(HashMap::new()));
+        self
+    }
+
+    /// Reuse `quote_exact_input_single` results for `ttl`, e.g. 12 seconds for
+    /// one mainnet block
+    pub fn with_quote_cache(mut self, ttl: Duration) -> Self {
+        self.quote_cache = Some(QuoteCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    pub fn addresses(&self) -> &ContractAddresses {
+        &self.addresses
+    }
+
+    /// Resolve the pool address through the factory, from the cache when enabled.
+    /// Missing pools are not cached since they may be created later.
+    pub async fn get_pool(
+        &self,
+        token_a: Address,
+        token_b: Address,
+        fee: UniswapPoolFee,
+    ) -> Result<Address, UniswapV3FactoryError> {
+        let (token0, token1, _) = sort_tokens(token_a, token_b);
+        let key = (token0, token1, fee.as_u32());
+        if let Some(pool) = self
+            .pool_cache
+            .as_ref()
+            .and_then(|cache| cache.lock().unwrap().get(&key).copied())
+        {
+            return Result::Ok(pool);
+        }
+        let command = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a,
+            token_b,
+            fee: fee.as_u32(),
+        });
+        let pool =
+            uniswapv3_factory::execute_with_provider(command, &self.provider, &self.addresses)
+                .await?
+                .pool_address();
+        if let Some(cache) = &self.pool_cache {
+            cache.lock().unwrap().insert(key, pool);
+        }
+        Result::Ok(pool)
+    }
+
+    /// Quote `amount_in` of `token_in` into `token_out`, from the quote cache when
+    /// enabled and the cached quote is younger than its TTL. `bypass_cache`
+    /// always asks the quoter, the fresh quote still refreshes the cache.
+    pub async fn quote_exact_input_single(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        fee: UniswapPoolFee,
+        amount_in: U256,
+        bypass_cache: bool,
+    ) -> Result<U256, UniswapV3QuoterError> {
+        let key = (token_in, token_out, fee.as_u32(), amount_in);
+        if let Some(cache) = self.quote_cache.as_ref().filter(|_| !bypass_cache) {
+            let entries = cache.entries.lock().unwrap();
+            if let Some((quoted_at, amount_out)) = entries.get(&key) {
+                if quoted_at.elapsed() < cache.ttl {
+                    return Result::Ok(*amount_out);
+                }
+            }
+        }
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in,
+            token_out,
+            fee,
+            amount_in,
+            sqrt_price_limit_x96: U256::ZERO,
+        });
+        let amount_out =
+            match uniswapv3_quoter::execute_with_provider(command, &self.provider, &self.addresses)
+                .await?
+            {
+                UniswapV3QuoterResult::QuoteExactInputSingle(amount_out) => amount_out,
+                _ => return Err(UniswapV3QuoterError::InvalidCommand),
+            };
+        if let Some(cache) = &self.quote_cache {
+            cache
+                .entries
+                .lock()
+                .unwrap()
+                .insert(key, (Instant::now(), amount_out));
+        }
+        Result::Ok(amount_out)
+    }
+
+    /// Set the gas limit of `tx` from `eth_estimateGas` with the configured margin
+    pub async fn fill_gas_limit(&self, tx: TransactionRequest) -> Result<TransactionRequest> {
+        fill_gas_limit(tx, self.gas_limit_multiplier, &self.provider).await
+    }
+
+    /// Drop every cached lookup
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.pool_cache {
+            cache.lock().unwrap().clear();
+        }
+        if let Some(cache) = &self.quote_cache {
+            cache.entries.lock().unwrap().clear();
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use alloy::providers::ProviderBuilder;
+    use std::str::FromStr;
+
+    use alloy::{
+        primitives::{aliases::I24, B256, I256, U160},
+        signers::local::PrivateKeySigner,
+        sol_types::{SolCall, SolEvent},
+    };
+
+    use crate::{
+        swap_math::compute_swap_step_exact_in,
+        test_utils::{spawn_stub_rpc, MockTransport},
+        uniswapv3_math::MAX_SQRT_RATIO,
+        uniswapv3_router::{UNIV3_ROUTER, UNIV3_ROUTER_CONTRACT_ADDR},
+        utils::from_readable_amount,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(
+            "CHAIN_MISMATCH",
+            ClientError::ChainMismatch {
+                expected: 1,
+                actual: 8453
+            }
+            .code()
+        );
+        assert_eq!("MISSING_RECIPIENT", ClientError::MissingRecipient.code());
+        assert_eq!(
+            "TRANSACTION_REVERTED",
+            ClientError::TransactionReverted(TxHash::ZERO).code()
+        );
+    }
+
+    #[test]
+    fn test_finalize_tx() {
+        use alloy::consensus::Transaction;
+
+        let router = Address::from_str(UNIV3_ROUTER_CONTRACT_ADDR).unwrap();
+        let tx = TransactionRequest::default()
+            .with_to(router)
+            .with_nonce(7)
+            .with_gas_limit(200_000)
+            .with_max_fee_per_gas(30_000_000_000)
+            .with_max_priority_fee_per_gas(1_000_000_000);
+        let finalized = finalize_tx(tx.clone(), &UniswapSupportChain::Base).unwrap();
+        assert_eq!(TxType::Eip1559, finalized.tx_type());
+        assert_eq!(Some(8453), finalized.chain_id());
+        assert_eq!(7, finalized.nonce());
+
+        // legacy gas price 被转换为 EIP-1559 的两个费用字段
+        let legacy = TransactionRequest::default()
+            .with_to(router)
+            .with_nonce(0)
+            .with_gas_limit(21_000)
+            .with_gas_price(20_000_000_000);
+        let finalized = finalize_tx(legacy, &UniswapSupportChain::Sepolia).unwrap();
+        assert_eq!(TxType::Eip1559, finalized.tx_type());
+        assert_eq!(Some(11155111), finalized.chain_id());
+        assert_eq!(20_000_000_000, finalized.max_fee_per_gas());
+
+        let err = finalize_tx(
+            TransactionRequest::default().with_to(router),
+            &UniswapSupportChain::Ethereum,
+        )
+        .unwrap_err();
+        assert_eq!("INCOMPLETE_TRANSACTION", err.code());
+        let ClientError::IncompleteTransaction(missing) = err else {
+            unreachable!()
+        };
+        assert!(missing.contains("nonce"));
+        assert!(missing.contains("gas_limit"));
+    }
+
+    #[test]
+    fn test_chain_id_and_rpc_url() {
+        assert_eq!(1, UniswapSupportChain::Ethereum.as_chain_id());
+        assert_eq!(8453, UniswapSupportChain::Base.as_chain_id());
+        assert_eq!(11155111, UniswapSupportChain::Sepolia.as_chain_id());
+        assert_eq!(
+            "https://eth.llamarpc.com",
+            UniswapSupportChain::Ethereum.get_rpc_url()
+        );
+    }
+
+    #[test]
+    fn test_swap_params_builder() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let now = chrono::Utc::now().timestamp() as u64;
+        let params = SwapParams::builder()
+            .token_in(weth_address(&UniswapSupportChain::Ethereum))
+            .token_out(ethc)
+            .amount_in(from_readable_amount(0.01, 18))
+            .recipient(receiver)
+            .build()
+            .unwrap();
+        assert_eq!(ethc, params.token_out);
+        assert_eq!(UniswapPoolFee::Fee3000, params.pool_fee);
+        assert_eq!(U256::ZERO, params.amount_out_min);
+        assert_eq!(U256::ZERO, params.sqrt_price_limit_x96);
+        assert!(params.deadline >= U256::from(now + DEFAULT_DEADLINE_SECS as u64));
+        assert!(!params.is_native_in && !params.is_native_out);
+
+        let err = SwapParams::builder()
+            .token_in(ethc)
+            .amount_in(U256::from(1))
+            .recipient(receiver)
+            .build();
+        assert!(err.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_swap_v2_fee_on_transfer() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let rpc_url = spawn_stub_rpc(1).await;
+        for (supports_fee_on_transfer, selector) in [(false, "38ed1739"), (true, "5c11d795")] {
+            let params = SwapParams::builder()
+                .token_in(ethc)
+                .token_out(weth_address(&UniswapSupportChain::Ethereum))
+                .amount_in(from_readable_amount(200.0, 18))
+                .recipient(receiver)
+                .supports_fee_on_transfer(supports_fee_on_transfer)
+                .build()
+                .unwrap();
+            let tx = swap(
+                UniswapSupportChain::Ethereum,
+                SwapDirection::ExactInput,
+                UniswapVersion::V2,
+                params,
+                rpc_url.clone(),
+            )
+            .await
+            .unwrap();
+            let data = tx.input.input().unwrap();
+            assert_eq!(selector, alloy::hex::encode(&data[..4]));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_swap_native_eth_in() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let amount_in = from_readable_amount(0.01, 18);
+        let params = SwapParams {
+            token_in: Address::ZERO,
+            token_out: ethc,
+            amount_in,
+            amount_out_min: U256::from(1),
+            pool_fee: UniswapPoolFee::Fee10000,
+            recipient: Some(receiver),
+            sender: None,
+            deadline: U256::ZERO,
+            is_native_in: true,
+            is_native_out: false,
+            sqrt_price_limit_x96: U256::ZERO,
+            supports_fee_on_transfer: false,
+            allow_zero_min: false,
+        };
+        let tx = swap(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            spawn_stub_rpc(1).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some(amount_in), tx.value);
+        let data = tx.input.input().unwrap();
+        let call = UNIV3_ROUTER::exactInputSingleCall::abi_decode(data, true).unwrap();
+        assert_eq!(
+            weth_address(&UniswapSupportChain::Ethereum),
+            call.params.tokenIn
+        );
+        assert_eq!(receiver, call.params.recipient);
+        // deadline 为 0 时自动设置
+        assert!(call.params.deadline > U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_swap_native_eth_out() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let params = SwapParams {
+            token_in: ethc,
+            token_out: Address::ZERO,
+            amount_in: from_readable_amount(200.0, 18),
+            amount_out_min: U256::from(1),
+            pool_fee: UniswapPoolFee::Fee10000,
+            recipient: Some(receiver),
+            sender: None,
+            deadline: U256::ZERO,
+            is_native_in: false,
+            is_native_out: true,
+            sqrt_price_limit_x96: U256::ZERO,
+            supports_fee_on_transfer: false,
+            allow_zero_min: false,
+        };
+        let tx = swap(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            spawn_stub_rpc(1).await,
+        )
+        .await
+        .unwrap();
+        assert_eq!(None, tx.value);
+        let data = tx.input.input().unwrap();
+        let call = UNIV3_ROUTER::multicallCall::abi_decode(data, true).unwrap();
+        assert_eq!(2, call.data.len());
+        let swap_call =
+            UNIV3_ROUTER::exactInputSingleCall::abi_decode(&call.data[0], true).unwrap();
+        assert_eq!(
+            Address::from_str(UNIV3_ROUTER_CONTRACT_ADDR).unwrap(),
+            swap_call.params.recipient
+        );
+        let unwrap_call = UNIV3_ROUTER::unwrapWETH9Call::abi_decode(&call.data[1], true).unwrap();
+        assert_eq!(receiver, unwrap_call.recipient);
+    }
+
+    #[tokio::test]
+    async fn test_get_pool_cache() {
+        let weth = weth_address(&UniswapSupportChain::Ethereum);
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        let pool: Address = "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"
+            .parse()
+            .unwrap();
+        let transport = MockTransport::default();
+        let client = UniswapClient::new(transport.provider()).with_pool_cache();
+
+        transport.push_response(serde_json::json!(pool.into_word()));
+        assert_eq!(
+            pool,
+            client
+                .get_pool(usdc, weth, UniswapPoolFee::Fee500)
+                .await
+                .unwrap()
+        );
+        // 第二次查询（交换 token 顺序）命中缓存，不再发送 RPC 请求
+        assert_eq!(
+            pool,
+            client
+                .get_pool(weth, usdc, UniswapPoolFee::Fee500)
+                .await
+                .unwrap()
+        );
+        assert_eq!(1, transport.requests().len());
+
+        client.clear_cache();
+        transport.push_response(serde_json::json!(pool.into_word()));
+        client
+            .get_pool(usdc, weth, UniswapPoolFee::Fee500)
+            .await
+            .unwrap();
+        assert_eq!(2, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_quote_cache_ttl() {
+        let weth = weth_address(&UniswapSupportChain::Ethereum);
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        let amount_in = U256::from(10u128.pow(18));
+        let transport = MockTransport::default();
+        let client =
+            UniswapClient::new(transport.provider()).with_quote_cache(Duration::from_millis(200));
+        let quote = |bypass_cache| {
+            client.quote_exact_input_single(
+                weth,
+                usdc,
+                UniswapPoolFee::Fee500,
+                amount_in,
+                bypass_cache,
+            )
+        };
+
+        transport.push_response(serde_json::json!(U256::from(3000).to_be_bytes::<32>()));
+        assert_eq!(U256::from(3000), quote(false).await.unwrap());
+        // TTL 内命中缓存，不再发送 RPC 请求
+        assert_eq!(U256::from(3000), quote(false).await.unwrap());
+        assert_eq!(1, transport.requests().len());
+
+        // bypass 时总是重新报价
+        transport.push_response(serde_json::json!(U256::from(3001).to_be_bytes::<32>()));
+        assert_eq!(U256::from(3001), quote(true).await.unwrap());
+        assert_eq!(2, transport.requests().len());
+
+        // 过期后重新报价
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        transport.push_response(serde_json::json!(U256::from(3002).to_be_bytes::<32>()));
+        assert_eq!(U256::from(3002), quote(false).await.unwrap());
+        assert_eq!(3, transport.requests().len());
+        assert_eq!(U256::from(3002), quote(false).await.unwrap());
+        assert_eq!(3, transport.requests().len());
+    }
+
+    fn usdc_weth_params() -> SwapAndSendParams {
+        SwapAndSendParams {
+            token_in: Token::new(
+                "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+                18,
+                "WETH".to_string(),
+            )
+            .unwrap(),
+            token_out: Token::new(
+                "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                6,
+                "USDC".to_string(),
+            )
+            .unwrap(),
+            amount_in: U256::from(10u128.pow(18)),
+            pool_fee: UniswapPoolFee::Fee3000,
+            slippage_bps: 50,
+        }
+    }
+
+    #[test]
+    fn test_swap_result_price_between_spot_and_fee() {
+        let params = usdc_weth_params();
+        // USDC 是 token0，按 1 WETH = 3000 USDC 设置价格：token1/token0 = 1e18 / 3000e6
+        let sqrt_price_x96 = U256::from(((1e12 / 3000.0_f64).sqrt() * 2_f64.powi(96)) as u128);
+        let step = compute_swap_step_exact_in(
+            sqrt_price_x96,
+            MAX_SQRT_RATIO - U256::from(1),
+            10u128.pow(24),
+            params.amount_in,
+            params.pool_fee.as_u32(),
+        )
+        .unwrap();
+        let simulated = UniswapV3RouterResult::ExactInputSingle(step.amount_out);
+        let result = SwapResult::from_simulation(&params, &simulated).unwrap();
+
+        let sqrt_price = sqrt_price_x96.to_string().parse::<f64>().unwrap() / 2_f64.powi(96);
+        let spot = 1e12 / (sqrt_price * sqrt_price);
+        let spot_after_fee = spot * (1.0 - params.pool_fee.as_percent() / 100.0);
+        assert!((spot - 3000.0).abs() < 1e-6);
+        assert!(result.effective_price < spot);
+        // 流动性足够深，价格影响小于 1bp
+        assert!(result.effective_price > spot_after_fee * 0.9999);
+        assert!(result.effective_price < spot_after_fee);
+        assert_eq!(U256::from(3 * 10u128.pow(15)), result.fee_paid);
+        assert_eq!(step.fee_amount, result.fee_paid);
+        assert_eq!(None, result.gas_used);
+
+        let quote_only = UniswapV3RouterResult::ExactOutputSingle(step.amount_out);
+        assert!(SwapResult::from_simulation(&params, &quote_only).is_err());
+    }
+
+    #[test]
+    fn test_swap_result_from_receipt() {
+        let params = usdc_weth_params();
+        let swap = UNIV3_POOL::Swap {
+            sender: UNIV3_ROUTER_CONTRACT_ADDR.parse().unwrap(),
+            recipient: Address::repeat_byte(1),
+            amount0: I256::try_from(-2_990_000_000i64).unwrap(),
+            amount1: I256::try_from(10i128.pow(18)).unwrap(),
+            sqrtPriceX96: U160::from(1u64 << 32),
+            liquidity: 1,
+            tick: I24::ZERO,
+        };
+        let log = alloy::rpc::types::Log {
+            inner: alloy::primitives::Log {
+                address: Address::repeat_byte(2),
+                data: swap.encode_log_data(),
+            },
+            ..Default::default()
+        };
+        let receipt: TransactionReceipt = serde_json::from_value(serde_json::json!({
+            "type": "0x2",
+            "status": "0x1",
+            "cumulativeGasUsed": "0x1d4c0",
+            "logs": [log],
+            "logsBloom": alloy::primitives::Bloom::ZERO,
+            "transactionHash": TxHash::repeat_byte(3),
+            "transactionIndex": "0x0",
+            "blockHash": TxHash::repeat_byte(4),
+            "blockNumber": "0x1",
+            "gasUsed": "0x1d4c0",
+            "effectiveGasPrice": "0x1",
+            "from": Address::repeat_byte(1),
+            "to": UNIV3_ROUTER_CONTRACT_ADDR,
+            "contractAddress": null,
+        }))
+        .unwrap();
+
+        let result = SwapResult::from_receipt(&params, &receipt).unwrap();
+        assert_eq!(params.amount_in, result.amount_in);
+        assert_eq!(U256::from(2_990_000_000u64), result.amount_out);
+        assert!((result.effective_price - 2990.0).abs() < 1e-9);
+        assert_eq!(Some(120_000), result.gas_used);
+
+        let mut without_swap = receipt.clone();
+        if let alloy::consensus::ReceiptEnvelope::Eip1559(inner) = &mut without_swap.inner {
+            inner.receipt.logs.clear();
+        }
+        assert_eq!(
+            Some(&ClientError::MissingSwapEvent(TxHash::repeat_byte(3))),
+            SwapResult::from_receipt(&params, &without_swap)
+                .unwrap_err()
+                .downcast_ref()
+        );
+    }
 
-    use crate::utils::{from_readable_amount, Token};
+    #[tokio::test]
+    async fn test_swap_with_provider_reuses_provider() {
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        let params = SwapParams::builder()
+            .token_in(weth_address(&UniswapSupportChain::Ethereum))
+            .token_out(usdc)
+            .amount_in(from_readable_amount(1.0, 18))
+            .amount_out_min(U256::from(1))
+            .recipient(Address::repeat_byte(1))
+            .build()
+            .unwrap();
+        let mut block = alloy::rpc::types::Block::<alloy::rpc::types::Transaction>::default();
+        block.header.timestamp = chrono::Utc::now().timestamp() as u64;
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!("0x1"));
+        transport.push_response(serde_json::to_value(block).unwrap());
+
+        let tx = swap_with_provider(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            transport.provider(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some(1), tx.chain_id);
+        // 链 ID 和 deadline 都通过同一个 provider 查询
+        let methods: Vec<_> = transport
+            .requests()
+            .into_iter()
+            .map(|request| request.method)
+            .collect();
+        assert_eq!(vec!["eth_chainId", "eth_getBlockByNumber"], methods);
+    }
+
+    #[tokio::test]
+    async fn test_build_swaps() {
+        let weth = weth_address(&UniswapSupportChain::Ethereum);
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        let receiver = Address::repeat_byte(1);
+        let swap_params = |token_in, token_out, amount_in| {
+            SwapParams::builder()
+                .token_in(token_in)
+                .token_out(token_out)
+                .amount_in(amount_in)
+                .amount_out_min(U256::from(1))
+                .pool_fee(UniswapPoolFee::Fee500)
+                .recipient(receiver)
+                .build()
+                .unwrap()
+        };
+        let rpc_url = spawn_stub_rpc(1).await;
+        let txs = build_swaps(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            vec![
+                swap_params(weth, usdc, from_readable_amount(1.0, 18)),
+                swap_params(usdc, weth, from_readable_amount(3000.0, 6)),
+            ],
+            rpc_url.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, txs.len());
+        let router = UNIV3_ROUTER_CONTRACT_ADDR.parse::<Address>().unwrap();
+        for tx in &txs {
+            assert_eq!(Some(alloy::primitives::TxKind::Call(router)), tx.to);
+        }
+        // 与输入顺序一致
+        let calls: Vec<_> = txs
+            .iter()
+            .map(|tx| {
+                UNIV3_ROUTER::exactInputSingleCall::abi_decode(tx.input.input().unwrap(), true)
+                    .unwrap()
+                    .params
+            })
+            .collect();
+        assert_eq!((weth, usdc), (calls[0].tokenIn, calls[0].tokenOut));
+        assert_eq!((usdc, weth), (calls[1].tokenIn, calls[1].tokenOut));
+
+        // 第二个兑换的 deadline 已过期
+        let mut expired = swap_params(usdc, weth, U256::from(1));
+        expired.deadline = U256::from(1);
+        let err = build_swaps(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            vec![swap_params(weth, usdc, U256::from(1)), expired],
+            rpc_url,
+        )
+        .await
+        .unwrap_err();
+        let Some(ClientError::SwapFailed { index, .. }) = err.downcast_ref::<ClientError>() else {
+            panic!("应当返回 SwapFailed: {:?}", err);
+        };
+        assert_eq!(1, *index);
+    }
+
+    #[tokio::test]
+    async fn test_swap_recipient_defaults_to_wallet() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let signer: PrivateKeySigner =
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+                .parse()
+                .unwrap();
+        let wallet = EthereumWallet::from(signer.clone());
+        let params = SwapParams::builder()
+            .token_in(weth_address(&UniswapSupportChain::Ethereum))
+            .token_out(ethc)
+            .amount_in(from_readable_amount(0.01, 18))
+            .amount_out_min(U256::from(1))
+            .wallet(&wallet)
+            .build()
+            .unwrap();
+        assert_eq!(None, params.recipient);
+        let tx = swap(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            spawn_stub_rpc(1).await,
+        )
+        .await
+        .unwrap();
+        let call = UNIV3_ROUTER::exactInputSingleCall::abi_decode(tx.input.input().unwrap(), true)
+            .unwrap();
+        assert_eq!(signer.address(), call.params.recipient);
+        assert_eq!(Some(signer.address()), tx.from);
+
+        // 既没有 recipient 也没有钱包
+        let Err(err) = SwapParams::builder()
+            .token_in(weth_address(&UniswapSupportChain::Ethereum))
+            .token_out(ethc)
+            .amount_in(from_readable_amount(0.01, 18))
+            .build()
+        else {
+            panic!("没有接收地址时应当失败");
+        };
+        assert_eq!(
+            Some(&ClientError::MissingRecipient),
+            err.downcast_ref::<ClientError>()
+        );
+    }
+
+    #[test]
+    fn test_build_swap_tx_offline() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let params = || {
+            SwapParams::builder()
+                .token_in(weth_address(&UniswapSupportChain::Ethereum))
+                .token_out(ethc)
+                .amount_in(from_readable_amount(0.01, 18))
+                .amount_out_min(U256::from(1))
+                .recipient(receiver)
+                .deadline(U256::from(1735689600u64))
+        };
+        // 不需要 RPC
+        let tx = build_swap_tx(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params().build().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(Address::from_str(UNIV3_ROUTER_CONTRACT_ADDR).unwrap()),
+            tx.to.and_then(|to| to.to().copied())
+        );
+        assert_eq!(Some(1), tx.chain_id);
+        let call = UNIV3_ROUTER::exactInputSingleCall::abi_decode(tx.input.input().unwrap(), true)
+            .unwrap();
+        assert_eq!(receiver, call.params.recipient);
+        assert_eq!(U256::from(1735689600u64), call.params.deadline);
+
+        let tx = build_swap_tx(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V2,
+            params().build().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            "38ed1739",
+            alloy::hex::encode(&tx.input.input().unwrap()[..4])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_swap_tx_per_chain() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let params = |chain: &UniswapSupportChain| {
+            SwapParams::builder()
+                .token_in(weth_address(chain))
+                .token_out(ethc)
+                .amount_in(from_readable_amount(0.01, 18))
+                .amount_out_min(U256::from(1))
+                .recipient(receiver)
+                .deadline(U256::from(1735689600u64))
+                .build()
+                .unwrap()
+        };
+        let chain = UniswapSupportChain::Ethereum;
+        let tx = build_swap_tx(
+            chain,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params(&chain),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(ContractAddresses::for_chain(&chain).router),
+            tx.to.and_then(|to| to.to().copied())
+        );
+        assert_eq!(Some(1), tx.chain_id);
+        // 原版 SwapRouter 的 exactInputSingle 带 deadline
+        let input = tx.input.input().unwrap();
+        assert_eq!("414bf389", alloy::hex::encode(&input[..4]));
+        let call = UNIV3_ROUTER::exactInputSingleCall::abi_decode(input, true).unwrap();
+        assert_eq!(weth_address(&chain), call.params.tokenIn);
+        assert_eq!(U256::from(1735689600u64), call.params.deadline);
+
+        // SwapRouter02 的 ABI 不同，不能把原版 calldata 发给它
+        for chain in [UniswapSupportChain::Base, UniswapSupportChain::Sepolia] {
+            let err = build_swap_tx(
+                chain,
+                SwapDirection::ExactInput,
+                UniswapVersion::V3,
+                params(&chain),
+            )
+            .unwrap_err();
+            assert_eq!(
+                Some(&NetworkError::UnsupportedPeriphery(chain.as_chain_id())),
+                err.downcast_ref::<NetworkError>()
+            );
+            // 在连接 RPC 之前就失败
+            let err = swap(
+                chain,
+                SwapDirection::ExactInput,
+                UniswapVersion::V3,
+                params(&chain),
+                "http://127.0.0.1:1".to_string(),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(
+                Some(&NetworkError::UnsupportedPeriphery(chain.as_chain_id())),
+                err.downcast_ref::<NetworkError>()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_with_provider() {
+        let params = || QuoteParams {
+            token_in: weth_address(&UniswapSupportChain::Ethereum),
+            token_out: "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+                .parse()
+                .unwrap(),
+            amount: from_readable_amount(1.0, 18),
+            pool_fee: UniswapPoolFee::Fee500,
+        };
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!("0x1"));
+        transport.push_response(serde_json::json!(B256::from(U256::from(3_000_000_000u64))));
+        let amount_out = quote_with_provider(
+            UniswapSupportChain::Ethereum,
+            UniswapVersion::V3,
+            SwapDirection::ExactInput,
+            params(),
+            transport.provider(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(U256::from(3_000_000_000u64), amount_out);
+        let requests = transport.requests();
+        assert_eq!("eth_chainId", requests[0].method);
+        assert_eq!(
+            serde_json::json!(ContractAddresses::default().quoter),
+            requests[1].params[0]["to"]
+        );
+
+        // Base 只有 QuoterV2，不发送任何请求
+        let transport = MockTransport::default();
+        let err = quote_with_provider(
+            UniswapSupportChain::Base,
+            UniswapVersion::V3,
+            SwapDirection::ExactInput,
+            params(),
+            transport.provider(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            Some(&NetworkError::UnsupportedPeriphery(8453)),
+            err.downcast_ref::<NetworkError>()
+        );
+        assert!(transport.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_swap_deadline_in_past() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let params = SwapParams::builder()
+            .token_in(weth_address(&UniswapSupportChain::Ethereum))
+            .token_out(ethc)
+            .amount_in(from_readable_amount(0.01, 18))
+            .recipient(receiver)
+            .deadline(U256::from(1))
+            .build()
+            .unwrap();
+        let err = swap(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            spawn_stub_rpc(1).await,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ClientError>(),
+            Some(ClientError::DeadlineInPast { deadline, .. }) if *deadline == U256::from(1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_deadline() {
+        let rpc_url = spawn_stub_rpc(1).await;
+        let now = chrono::Utc::now().timestamp() as u64;
+        // 0 使用默认的 20 分钟
+        let deadline = resolve_deadline(U256::ZERO, &rpc_url).await.unwrap();
+        assert!(deadline >= U256::from(now + DEFAULT_DEADLINE_SECS as u64));
+        let future = U256::from(now + 60);
+        assert_eq!(future, resolve_deadline(future, &rpc_url).await.unwrap());
+        assert!(resolve_deadline(U256::from(now - 60), &rpc_url)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fill_gas_limit() {
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!("0x186a0"));
+        let client = UniswapClient::new(transport.provider())
+            .with_gas_limit_multiplier(1.25)
+            .unwrap();
+        let tx = client
+            .fill_gas_limit(TransactionRequest::default())
+            .await
+            .unwrap();
+        // 100000 * 1.25
+        assert_eq!(Some(125_000), tx.gas);
+        assert_eq!("eth_estimateGas", transport.requests()[0].method);
+
+        assert_eq!(120_000, scale_gas_limit(100_000, 1.2));
+        assert_eq!(120_002, scale_gas_limit(100_001, 1.2));
+        for multiplier in [0.9, f64::NAN] {
+            assert!(matches!(
+                UniswapClient::new(transport.provider()).with_gas_limit_multiplier(multiplier),
+                Err(ClientError::InvalidGasLimitMultiplier(_))
+            ));
+        }
+        assert!(
+            fill_gas_limit(TransactionRequest::default(), 0.5, transport.provider())
+                .await
+                .is_err()
+        );
+        // 倍数不合法时不会发送请求
+        assert_eq!(1, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_swap_chain_mismatch() {
+        let ethc: Address = "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4"
+            .parse()
+            .unwrap();
+        let receiver: Address = "0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69"
+            .parse()
+            .unwrap();
+        let params = SwapParams::builder()
+            .token_in(weth_address(&UniswapSupportChain::Ethereum))
+            .token_out(ethc)
+            .amount_in(from_readable_amount(0.01, 18))
+            .recipient(receiver)
+            .build()
+            .unwrap();
+        // 选择 Ethereum，但 RPC 是 Base
+        let err = swap(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            spawn_stub_rpc(8453).await,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            Some(&ClientError::ChainMismatch {
+                expected: 1,
+                actual: 8453
+            }),
+            err.downcast_ref::<ClientError>()
+        );
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use crate::{
+        test_utils::spawn_mainnet_fork,
+        utils::{from_readable_amount, Token, ERC20},
+        weth::wrap_tx,
+    };
 
     use super::*;
 
+    #[tokio::test]
+    async fn test_swap_and_send_approves_then_swaps() {
+        let fork = spawn_mainnet_fork();
+        let chain = UniswapSupportChain::Ethereum;
+        let signer = PrivateKeySigner::from(fork.keys()[0].clone());
+        let from = signer.address();
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .on_http(fork.endpoint_url());
+        let weth = Token::new(&weth_address(&chain).to_string(), 18, "WETH".to_string()).unwrap();
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USDC".to_string(),
+        )
+        .unwrap();
+        let amount_in = from_readable_amount(1.0, 18);
+        provider
+            .send_transaction(wrap_tx(&chain, amount_in * U256::from(2)).with_from(from))
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+        let router = ContractAddresses::default().router;
+        let params = || SwapAndSendParams {
+            token_in: weth.clone(),
+            token_out: usdc.clone(),
+            amount_in,
+            pool_fee: UniswapPoolFee::Fee500,
+            slippage_bps: 100,
+        };
+
+        // 没有授权：先 approve 再兑换
+        let nonce_before = provider.get_transaction_count(from).await.unwrap();
+        let receipt = swap_and_send(
+            UniswapSupportChain::Ethereum,
+            EthereumWallet::from(signer.clone()),
+            params(),
+            fork.endpoint(),
+        )
+        .await
+        .unwrap();
+        assert!(receipt.status());
+        assert_eq!(
+            nonce_before + 2,
+            provider.get_transaction_count(from).await.unwrap()
+        );
+        let usdc_contract = ERC20::new(usdc.address, &provider);
+        assert!(usdc_contract.balanceOf(from).call().await.unwrap().balance > U256::ZERO);
+        // 授权额度正好被兑换用完
+        assert_eq!(
+            U256::ZERO,
+            weth.allowance(from, router, &provider).await.unwrap()
+        );
+
+        // 额度已足够时跳过 approve，只发送兑换交易
+        provider
+            .send_transaction(weth.approve_tx(router, U256::MAX).with_from(from))
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+        let nonce_before = provider.get_transaction_count(from).await.unwrap();
+        swap_and_send(
+            UniswapSupportChain::Ethereum,
+            EthereumWallet::from(signer),
+            params(),
+            fork.endpoint(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            nonce_before + 1,
+            provider.get_transaction_count(from).await.unwrap()
+        );
+    }
+
     #[tokio::test]
     async fn test_swap() {
         let mainet_rpc = "https://eth.llamarpc.com";
@@ -120,10 +2012,16 @@ mod tests {
             amount_in: from_readable_amount(0.01, weth.decimals),
             amount_out_min: U256::ZERO,
             pool_fee: UniswapPoolFee::Fee10000,
-            recipient: receiver,
+            recipient: Some(receiver),
+            sender: None,
             deadline: U256::ZERO,
+            is_native_in: false,
+            is_native_out: false,
+            sqrt_price_limit_x96: U256::ZERO,
+            supports_fee_on_transfer: false,
+            allow_zero_min: true,
         };
-        let tx = swap(
+        swap(
             UniswapSupportChain::Ethereum,
             SwapDirection::ExactInput,
             UniswapVersion::V3,
@@ -133,4 +2031,54 @@ mod tests {
         .await
         .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_check_chain_id_base_rpc() {
+        let err = check_chain_id(
+            &UniswapSupportChain::Ethereum,
+            &UniswapSupportChain::Base.get_rpc_url(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ClientError>(),
+            Some(ClientError::ChainMismatch {
+                expected: 1,
+                actual: 8453
+            })
+        ));
+        check_chain_id(
+            &UniswapSupportChain::Base,
+            &UniswapSupportChain::Base.get_rpc_url(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quote_weth_to_usdc() {
+        let fork = spawn_mainnet_fork();
+        let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+            .parse()
+            .unwrap();
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .unwrap();
+        let params = QuoteParams {
+            token_in: weth,
+            token_out: usdc,
+            amount: from_readable_amount(1.0, 18),
+            pool_fee: UniswapPoolFee::Fee500,
+        };
+        let amount_out = quote(
+            UniswapSupportChain::Ethereum,
+            UniswapVersion::V3,
+            SwapDirection::ExactInput,
+            params,
+            fork.endpoint(),
+        )
+        .await
+        .unwrap();
+        assert!(amount_out > U256::ZERO);
+    }
 }
@@ -0,0 +1,282 @@
+use std::str::FromStr;
+
+use alloy::{
+    primitives::{address, Address},
+    providers::{Provider, ProviderBuilder, RootProvider},
+    transports::BoxTransport,
+};
+
+use crate::{
+    client::{UniswapClient, UniswapSupportChain},
+    position_manager::POSITION_MANAGER_CONTRACT_ADDR,
+    tokens,
+    uniswapv3_factory::UNIV3_FACTORY_CONTRACT_ADDR,
+    uniswapv3_quoter::UNIV3_QUOTER_CONTRACT_ADDR,
+    uniswapv3_router::UNIV3_ROUTER_CONTRACT_ADDR,
+};
+
+/// Contract addresses used by the `execute` functions. Override them to talk to
+/// a Uniswap V3 fork with the same ABI, e.g. PancakeSwap V3 or SushiSwap V3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractAddresses {
+    pub factory: Address,
+    pub quoter: Address,
+    pub router: Address,
+    pub position_manager: Address,
+    /// WETH9 the router wraps native ETH into
+    pub weth: Address,
+}
+
+impl Default for ContractAddresses {
+    /// Uniswap V3 deployment on Ethereum mainnet
+    fn default() -> Self {
+        ContractAddresses {
+            factory: Address::from_str(UNIV3_FACTORY_CONTRACT_ADDR).unwrap(),
+            quoter: Address::from_str(UNIV3_QUOTER_CONTRACT_ADDR).unwrap(),
+            router: Address::from_str(UNIV3_ROUTER_CONTRACT_ADDR).unwrap(),
+            position_manager: Address::from_str(POSITION_MANAGER_CONTRACT_ADDR).unwrap(),
+            weth: tokens::weth(&UniswapSupportChain::Ethereum),
+        }
+    }
+}
+
+impl ContractAddresses {
+    /// The Uniswap V3 deployment of `chain`. Except on mainnet Uniswap deploys
+    /// `QuoterV2` and `SwapRouter02` instead of the original quoter and router.
+    pub fn for_chain(chain: &UniswapSupportChain) -> Self {
+        match chain {
+            UniswapSupportChain::Ethereum => ContractAddresses::default(),
+            UniswapSupportChain::Base => ContractAddresses {
+                factory: address!("33128a8fC17869897dcE68Ed026d694621f6FDfD"),
+                quoter: address!("3d4e44Eb1374240CE5F1B871ab261CD16335B76a"),
+                router: address!("2626664c2603336E57B271c5C0b26F421741e481"),
+                position_manager: address!("03a520b32C04BF3bEEf7BEb72E919cf822Ed34f1"),
+                weth: tokens::weth(chain),
+            },
+            UniswapSupportChain::Sepolia => ContractAddresses {
+                factory: address!("0227628f3F023bb0B980b67D528571c95c6DaC1c"),
+                quoter: address!("Ed1f6473345F45b75F8179591dd5bA1888cf2FB3"),
+                router: address!("3bFA4769FB09eefC5a80d6E87c3B9C650f7Ae48E"),
+                position_manager: address!("1238536071E1c677A632429e3655c799b22cDA52"),
+                weth: tokens::weth(chain),
+            },
+        }
+    }
+
+    /// The deployment of `chain` used to quote and swap. Only the original `Quoter`
+    /// and `SwapRouter` ABI is encoded, so chains that only have `QuoterV2` and
+    /// `SwapRouter02` fail with `UnsupportedPeriphery` instead of sending calls
+    /// those contracts would revert.
+    pub fn periphery_for_chain(chain: &UniswapSupportChain) -> Result<Self, NetworkError> {
+        match chain {
+            UniswapSupportChain::Ethereum => Ok(ContractAddresses::for_chain(chain)),
+            UniswapSupportChain::Base | UniswapSupportChain::Sepolia => {
+                Err(NetworkError::UnsupportedPeriphery(chain.as_chain_id()))
+            }
+        }
+    }
+}
+
+/// Environment variable holding the RPC url read by `Network::from_env`
+pub const RPC_URL_ENV: &str = "UNISWAP_RPC_URL";
+/// Environment variable holding the chain read by `Network::from_env`, e.g. `ethereum` or `8453`
+pub const CHAIN_ENV: &str = "UNISWAP_CHAIN";
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum NetworkError {
+    #[cfg_attr(not(feature = "locale-en"), error("缺少环境变量{0}"))]
+    #[cfg_attr(feature = "locale-en", error("missing environment variable {0}"))]
+    MissingEnv(&'static str),
+    #[cfg_attr(not(feature = "locale-en"), error("不支持的链{0}"))]
+    #[cfg_attr(feature = "locale-en", error("unsupported chain: {0}"))]
+    InvalidChain(String),
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("RPC 的链 ID {actual} 与选择的链 {expected} 不一致")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("RPC chain id {actual} does not match the selected chain {expected}")
+    )]
+    ChainMismatch { expected: u64, actual: u64 },
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("链 {0} 只部署了 QuoterV2 和 SwapRouter02，暂不支持报价和兑换")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error(
+            "chain {0} only has QuoterV2 and SwapRouter02, quoting and swapping are not supported"
+        )
+    )]
+    UnsupportedPeriphery(u64),
+}
+
+impl NetworkError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            NetworkError::MissingEnv(_) => "MISSING_ENV",
+            NetworkError::InvalidChain(_) => "INVALID_CHAIN",
+            NetworkError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            NetworkError::ChainMismatch { .. } => "CHAIN_MISMATCH",
+            NetworkError::UnsupportedPeriphery(_) => "UNSUPPORTED_PERIPHERY",
+        }
+    }
+}
+
+/// A chain together with the RPC serving it, checked to agree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    pub chain: UniswapSupportChain,
+    pub rpc_url: String,
+    pub addresses: ContractAddresses,
+}
+
+impl Network {
+    /// Read `UNISWAP_RPC_URL` and `UNISWAP_CHAIN` and make sure the RPC's chain id
+    /// matches the chain. `UNISWAP_CHAIN` is a name (`ethereum`, `mainnet`, `base`,
+    /// `sepolia`) or a chain id.
+    pub async fn from_env() -> Result<Self, NetworkError> {
+        let rpc_url =
+            std::env::var(RPC_URL_ENV).map_err(|_| NetworkError::MissingEnv(RPC_URL_ENV))?;
+        let chain = std::env::var(CHAIN_ENV).map_err(|_| NetworkError::MissingEnv(CHAIN_ENV))?;
+        Self::new(parse_chain(&chain)?, rpc_url).await
+    }
+
+    /// Connect to `rpc_url` and check that it serves `chain`, using the chain's deployment
+    pub async fn new(chain: UniswapSupportChain, rpc_url: String) -> Result<Self, NetworkError> {
+        let provider = connect(&rpc_url).await?;
+        let actual = provider
+            .get_chain_id()
+            .await
+            .map_err(|e| NetworkError::InvalidRpcUrl(e.to_string()))?;
+        let expected = chain.as_chain_id();
+        if actual != expected {
+            return Err(NetworkError::ChainMismatch { expected, actual });
+        }
+        Ok(Network {
+            chain,
+            rpc_url,
+            addresses: ContractAddresses::for_chain(&chain),
+        })
+    }
+
+    /// A `UniswapClient` connected to `rpc_url`, see `UniswapClient::for_chain`
+    pub async fn client(
+        &self,
+    ) -> Result<UniswapClient<BoxTransport, RootProvider<BoxTransport>>, NetworkError> {
+        let provider = connect(&self.rpc_url).await?;
+        UniswapClient::for_chain(provider, &self.chain)
+    }
+}
+
+async fn connect(rpc_url: &str) -> Result<RootProvider<BoxTransport>, NetworkError> {
+    ProviderBuilder::new()
+        .on_builtin(rpc_url)
+        .await
+        .map_err(|e| NetworkError::InvalidRpcUrl(e.to_string()))
+}
+
+fn parse_chain(chain: &str) -> Result<UniswapSupportChain, NetworkError> {
+    match chain.trim().to_ascii_lowercase().as_str() {
+        "ethereum" | "mainnet" | "1" => Ok(UniswapSupportChain::Ethereum),
+        "base" | "8453" => Ok(UniswapSupportChain::Base),
+        "sepolia" | "11155111" => Ok(UniswapSupportChain::Sepolia),
+        _ => Err(NetworkError::InvalidChain(chain.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::spawn_stub_rpc;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_chain() {
+        assert_eq!(
+            UniswapSupportChain::Ethereum,
+            parse_chain("Ethereum").unwrap()
+        );
+        assert_eq!(UniswapSupportChain::Base, parse_chain("8453").unwrap());
+        assert_eq!(
+            UniswapSupportChain::Sepolia,
+            parse_chain("11155111").unwrap()
+        );
+        assert_eq!(
+            NetworkError::InvalidChain("solana".to_string()),
+            parse_chain("solana").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_periphery_for_chain() {
+        assert_eq!(
+            ContractAddresses::default(),
+            ContractAddresses::periphery_for_chain(&UniswapSupportChain::Ethereum).unwrap()
+        );
+        for chain in [UniswapSupportChain::Base, UniswapSupportChain::Sepolia] {
+            let err = ContractAddresses::periphery_for_chain(&chain).unwrap_err();
+            assert_eq!(NetworkError::UnsupportedPeriphery(chain.as_chain_id()), err);
+            assert_eq!("UNSUPPORTED_PERIPHERY", err.code());
+        }
+        for chain in [
+            UniswapSupportChain::Ethereum,
+            UniswapSupportChain::Base,
+            UniswapSupportChain::Sepolia,
+        ] {
+            assert_eq!(
+                tokens::weth(&chain),
+                ContractAddresses::for_chain(&chain).weth
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_network_from_env() {
+        // 环境变量是进程级的，只有这个测试会修改它们
+        std::env::remove_var(RPC_URL_ENV);
+        std::env::set_var(CHAIN_ENV, "base");
+        assert_eq!(
+            NetworkError::MissingEnv(RPC_URL_ENV),
+            Network::from_env().await.unwrap_err()
+        );
+
+        let rpc_url = spawn_stub_rpc(8453).await;
+        std::env::set_var(RPC_URL_ENV, &rpc_url);
+        let network = Network::from_env().await.unwrap();
+        assert_eq!(
+            Network {
+                chain: UniswapSupportChain::Base,
+                rpc_url: rpc_url.clone(),
+                addresses: ContractAddresses::for_chain(&UniswapSupportChain::Base),
+            },
+            network
+        );
+        // Base 只有 QuoterV2 和 SwapRouter02
+        assert_eq!(
+            Some(NetworkError::UnsupportedPeriphery(8453)),
+            network.client().await.err()
+        );
+
+        // RPC 是 Base，但选择了以太坊主网
+        std::env::set_var(CHAIN_ENV, "ethereum");
+        assert_eq!(
+            NetworkError::ChainMismatch {
+                expected: 1,
+                actual: 8453
+            },
+            Network::from_env().await.unwrap_err()
+        );
+        std::env::set_var(RPC_URL_ENV, spawn_stub_rpc(1).await);
+        let network = Network::from_env().await.unwrap();
+        let client = network.client().await.unwrap();
+        assert_eq!(&ContractAddresses::default(), client.addresses());
+        std::env::remove_var(RPC_URL_ENV);
+        std::env::remove_var(CHAIN_ENV);
+    }
+}
@@ -1,6 +1,28 @@
+pub mod amm;
 pub mod client;
+pub mod config;
+pub mod liquidity_math;
+pub mod local_quote;
+mod logging;
+pub mod multicall;
+pub mod permit2;
+pub mod position_manager;
+pub mod price_oracle;
+pub mod routing;
+pub mod swap_math;
+pub mod tick_math;
+pub mod tokens;
+pub mod uniswapv2_factory;
+pub mod uniswapv2_math;
+pub mod uniswapv2_pair;
+pub mod uniswapv2_router;
 pub mod uniswapv3_factory;
+pub mod uniswapv3_math;
 pub mod uniswapv3_quoter;
 pub mod uniswapv3_router;
 pub mod unswapv3_pool;
 pub mod utils;
+pub mod weth;
+
+#[cfg(test)]
+mod test_utils;
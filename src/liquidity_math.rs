@@ -0,0 +1,205 @@
+//! Port of the periphery `LiquidityAmounts` library. All prices are `sqrtPriceX96`
+//! values and the two bounds may be passed in either order.
+
+use alloy::primitives::U256;
+
+use crate::uniswapv3_math::{mul_div, Q96};
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum LiquidityMathError {
+    #[cfg_attr(not(feature = "locale-en"), error("价格区间的上下限不能相同"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("the lower and upper bound of the price range must differ")
+    )]
+    EmptyRange,
+    #[cfg_attr(not(feature = "locale-en"), error("计算结果溢出"))]
+    #[cfg_attr(feature = "locale-en", error("arithmetic overflow"))]
+    Overflow,
+}
+
+impl LiquidityMathError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            LiquidityMathError::EmptyRange => "EMPTY_RANGE",
+            LiquidityMathError::Overflow => "OVERFLOW",
+        }
+    }
+}
+
+fn sorted(sqrt_ratio_a_x96: U256, sqrt_ratio_b_x96: U256) -> (U256, U256) {
+    if sqrt_ratio_a_x96 > sqrt_ratio_b_x96 {
+        (sqrt_ratio_b_x96, sqrt_ratio_a_x96)
+    } else {
+        (sqrt_ratio_a_x96, sqrt_ratio_b_x96)
+    }
+}
+
+fn to_u128(x: U256) -> Result<u128, LiquidityMathError> {
+    u128::try_from(x).map_err(|_| LiquidityMathError::Overflow)
+}
+
+/// Liquidity received for `amount0` of token0 over the price range
+pub fn get_liquidity_for_amount0(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    amount0: U256,
+) -> Result<u128, LiquidityMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    if a == b {
+        return Err(LiquidityMathError::EmptyRange);
+    }
+    let intermediate = mul_div(a, b, Q96).ok_or(LiquidityMathError::Overflow)?;
+    to_u128(mul_div(amount0, intermediate, b - a).ok_or(LiquidityMathError::Overflow)?)
+}
+
+/// Liquidity received for `amount1` of token1 over the price range
+pub fn get_liquidity_for_amount1(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    amount1: U256,
+) -> Result<u128, LiquidityMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    if a == b {
+        return Err(LiquidityMathError::EmptyRange);
+    }
+    to_u128(mul_div(amount1, Q96, b - a).ok_or(LiquidityMathError::Overflow)?)
+}
+
+/// The maximum liquidity that `amount0` and `amount1` can provide at the current
+/// price `sqrt_ratio_x96`
+pub fn get_liquidity_for_amounts(
+    sqrt_ratio_x96: U256,
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    amount0: U256,
+    amount1: U256,
+) -> Result<u128, LiquidityMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    if sqrt_ratio_x96 <= a {
+        get_liquidity_for_amount0(a, b, amount0)
+    } else if sqrt_ratio_x96 < b {
+        let liquidity0 = get_liquidity_for_amount0(sqrt_ratio_x96, b, amount0)?;
+        let liquidity1 = get_liquidity_for_amount1(a, sqrt_ratio_x96, amount1)?;
+        Ok(liquidity0.min(liquidity1))
+    } else {
+        get_liquidity_for_amount1(a, b, amount1)
+    }
+}
+
+/// Amount of token0 held by `liquidity` over the price range
+pub fn get_amount0_for_liquidity(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+) -> Result<U256, LiquidityMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    if a.is_zero() {
+        return Err(LiquidityMathError::Overflow);
+    }
+    let amount =
+        mul_div(U256::from(liquidity) << 96, b - a, b).ok_or(LiquidityMathError::Overflow)?;
+    Ok(amount / a)
+}
+
+/// Amount of token1 held by `liquidity` over the price range
+pub fn get_amount1_for_liquidity(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+) -> Result<U256, LiquidityMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    mul_div(U256::from(liquidity), b - a, Q96).ok_or(LiquidityMathError::Overflow)
+}
+
+/// Token amounts held by `liquidity` at the current price `sqrt_ratio_x96`
+pub fn get_amounts_for_liquidity(
+    sqrt_ratio_x96: U256,
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+) -> Result<(U256, U256), LiquidityMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    if sqrt_ratio_x96 <= a {
+        Ok((get_amount0_for_liquidity(a, b, liquidity)?, U256::ZERO))
+    } else if sqrt_ratio_x96 < b {
+        Ok((
+            get_amount0_for_liquidity(sqrt_ratio_x96, b, liquidity)?,
+            get_amount1_for_liquidity(a, sqrt_ratio_x96, liquidity)?,
+        ))
+    } else {
+        Ok((U256::ZERO, get_amount1_for_liquidity(a, b, liquidity)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::uint;
+
+    use super::*;
+
+    // 与 v3-periphery LiquidityAmounts.spec.ts 相同的价格，encodePriceSqrt(reserve1, reserve0)
+    const PRICE_1_1: U256 = uint!(79228162514264337593543950336_U256);
+    const PRICE_100_110: U256 = uint!(75541088972021052632782079082_U256);
+    const PRICE_110_100: U256 = uint!(83095197869223157896060286990_U256);
+    const PRICE_99_110: U256 = uint!(75162434512514379355924140470_U256);
+    const PRICE_111_100: U256 = uint!(83472048772503575395058907992_U256);
+
+    fn liquidity(sqrt_price: U256) -> u128 {
+        get_liquidity_for_amounts(
+            sqrt_price,
+            PRICE_100_110,
+            PRICE_110_100,
+            U256::from(100),
+            U256::from(200),
+        )
+        .unwrap()
+    }
+
+    fn amounts(sqrt_price: U256, liquidity: u128) -> (U256, U256) {
+        get_amounts_for_liquidity(sqrt_price, PRICE_100_110, PRICE_110_100, liquidity).unwrap()
+    }
+
+    #[test]
+    fn test_get_liquidity_for_amounts() {
+        assert_eq!(2148, liquidity(PRICE_1_1));
+        assert_eq!(1048, liquidity(PRICE_99_110));
+        assert_eq!(2097, liquidity(PRICE_111_100));
+        assert_eq!(1048, liquidity(PRICE_100_110));
+        assert_eq!(2097, liquidity(PRICE_110_100));
+        // 上下限顺序不影响结果
+        assert_eq!(
+            2148,
+            get_liquidity_for_amounts(
+                PRICE_1_1,
+                PRICE_110_100,
+                PRICE_100_110,
+                U256::from(100),
+                U256::from(200),
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_amounts_for_liquidity() {
+        assert_eq!((U256::from(99), U256::from(99)), amounts(PRICE_1_1, 2148));
+        assert_eq!((U256::from(99), U256::ZERO), amounts(PRICE_99_110, 1048));
+        assert_eq!((U256::ZERO, U256::from(199)), amounts(PRICE_111_100, 2097));
+        assert_eq!((U256::from(99), U256::ZERO), amounts(PRICE_100_110, 1048));
+        assert_eq!((U256::ZERO, U256::from(199)), amounts(PRICE_110_100, 2097));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Err(LiquidityMathError::EmptyRange),
+            get_liquidity_for_amount1(PRICE_1_1, PRICE_1_1, U256::from(1))
+        );
+        assert_eq!(
+            Err(LiquidityMathError::Overflow),
+            get_liquidity_for_amount1(PRICE_100_110, PRICE_110_100, U256::MAX >> 64)
+        );
+    }
+}
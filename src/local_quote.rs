@@ -0,0 +1,540 @@
+//! Exact input quotes computed from pool state instead of calling the quoter,
+//! either inside the current tick range or by walking the initialized ticks
+
+use std::sync::Arc;
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    transports::Transport,
+};
+
+use crate::{
+    config::ContractAddresses,
+    swap_math::compute_swap_step_exact_in,
+    tick_math::{get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio, MAX_TICK, MIN_TICK},
+    uniswapv3_factory::{
+        self, GetPoolParam, PoolWithState, UniswapV3FactoryCommand, UniswapV3FactoryResult,
+    },
+    uniswapv3_math::{full_range_sqrt_price_limit, zero_for_one},
+    uniswapv3_quoter::{
+        self, QuoteExactInputSingleParams, UniswapV3QuoterCommand, UniswapV3QuoterResult,
+    },
+    unswapv3_pool::{
+        bitmap_word_pos, get_tick_bitmap_with_provider, get_tick_info_with_provider,
+        next_initialized_tick_within_one_word, UniswapPoolFee,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum LocalQuoteError {
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取池子状态{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read pool state: {0}"))]
+    GetPoolStateError(String),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("池子不存在 token_in: {0}, token_out: {1}, fee: {2}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("pool not found token_in: {0}, token_out: {1}, fee: {2}")
+    )]
+    PoolNotFound(Address, Address, u32),
+    #[cfg_attr(not(feature = "locale-en"), error("本地报价计算失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("local quote failed: {0}"))]
+    MathError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("链上报价失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("on-chain quote failed: {0}"))]
+    QuoterError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+}
+
+impl LocalQuoteError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            LocalQuoteError::GetPoolStateError(_) => "GET_POOL_STATE_ERROR",
+            LocalQuoteError::PoolNotFound(..) => "POOL_NOT_FOUND",
+            LocalQuoteError::MathError(_) => "MATH_ERROR",
+            LocalQuoteError::QuoterError(_) => "QUOTER_ERROR",
+            LocalQuoteError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+        }
+    }
+}
+
+/// The price of the nearest tick in the swap direction that could be initialized.
+/// Ticks are only initialized on multiples of the tick spacing, so no liquidity
+/// changes between the current price and this one.
+fn next_tick_boundary_price(
+    tick: i32,
+    tick_spacing: i32,
+    zero_for_one: bool,
+) -> Result<U256, LocalQuoteError> {
+    let compressed = tick.div_euclid(tick_spacing) * tick_spacing;
+    let boundary = if zero_for_one {
+        compressed
+    } else {
+        compressed + tick_spacing
+    };
+    get_sqrt_ratio_at_tick(boundary.clamp(MIN_TICK, MAX_TICK))
+        .map_err(|e| LocalQuoteError::MathError(e.to_string()))
+}
+
+/// Amount out for swapping `amount_in` through `pool`, or `None` when the swap
+/// would reach the next tick boundary and has to go through the quoter instead
+pub fn quote_exact_input_in_range(
+    pool: &PoolWithState,
+    fee: UniswapPoolFee,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<Option<U256>, LocalQuoteError> {
+    if pool.liquidity == 0 {
+        return Ok(None);
+    }
+    let target = next_tick_boundary_price(pool.tick, fee.tick_spacing(), zero_for_one)?;
+    let step = compute_swap_step_exact_in(
+        pool.sqrt_price_x96,
+        target,
+        pool.liquidity,
+        amount_in,
+        fee.as_u32(),
+    )
+    .map_err(|e| LocalQuoteError::MathError(e.to_string()))?;
+    if step.sqrt_price_next_x96 == target {
+        return Ok(None);
+    }
+    Ok(Some(step.amount_out))
+}
+
+/// Quote `amount_in` of `token_in` into `token_out` from the pool's `slot0` and
+/// `liquidity`, falling back to the on-chain quoter when the swap crosses a tick
+pub async fn quote_exact_input_single(
+    token_in: Address,
+    token_out: Address,
+    fee: UniswapPoolFee,
+    amount_in: U256,
+    rpc_url: String,
+) -> Result<U256, LocalQuoteError> {
+    let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+        token_a: token_in,
+        token_b: token_out,
+        fee: fee.as_u32(),
+    });
+    let pool = match uniswapv3_factory::execute(command, rpc_url.clone())
+        .await
+        .map_err(|e| LocalQuoteError::GetPoolStateError(e.to_string()))?
+    {
+        UniswapV3FactoryResult::GetPoolWithState(pool) => pool,
+        UniswapV3FactoryResult::GetPool(_) => unreachable!("GetPoolWithState 只返回池子状态"),
+    };
+    if !pool.exists {
+        return Err(LocalQuoteError::PoolNotFound(
+            token_in,
+            token_out,
+            fee.as_u32(),
+        ));
+    }
+    if let Some(amount_out) =
+        quote_exact_input_in_range(&pool, fee, amount_in, zero_for_one(token_in, token_out))?
+    {
+        return Ok(amount_out);
+    }
+
+    let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+        token_in,
+        token_out,
+        fee,
+        amount_in,
+        sqrt_price_limit_x96: U256::ZERO,
+    });
+    match uniswapv3_quoter::execute(command, rpc_url)
+        .await
+        .map_err(|e| LocalQuoteError::QuoterError(e.to_string()))?
+    {
+        UniswapV3QuoterResult::QuoteExactInputSingle(amount_out) => Ok(amount_out),
+        res => Err(LocalQuoteError::QuoterError(format!("{:?}", res))),
+    }
+}
+
+/// Result of a swap simulated locally across ticks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedSwap {
+    /// 实际消耗的输入（含手续费），价格到达边界时可能小于请求的数量
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub sqrt_price_after_x96: U256,
+    pub tick_after: i32,
+    /// 穿过的已初始化 tick 数量
+    pub ticks_crossed: u32,
+}
+
+/// Simulate swapping `amount_in` of `token_in` into `token_out` the way
+/// `UniswapV3Pool.swap` does, walking every initialized tick on the way, so
+/// large swaps can be quoted without the quoter
+pub async fn simulate_swap_local(
+    token_in: Address,
+    token_out: Address,
+    fee: UniswapPoolFee,
+    amount_in: U256,
+    rpc_url: String,
+) -> Result<SimulatedSwap, LocalQuoteError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| LocalQuoteError::InvalidRpcUrl(e.to_string()))?;
+    let client = Arc::new(provider);
+
+    let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+        token_a: token_in,
+        token_b: token_out,
+        fee: fee.as_u32(),
+    });
+    let pool = match uniswapv3_factory::execute_with_provider(
+        command,
+        client.clone(),
+        &ContractAddresses::default(),
+    )
+    .await
+    .map_err(|e| LocalQuoteError::GetPoolStateError(e.to_string()))?
+    {
+        UniswapV3FactoryResult::GetPoolWithState(pool) => pool,
+        UniswapV3FactoryResult::GetPool(_) => unreachable!("GetPoolWithState 只返回池子状态"),
+    };
+    if !pool.exists {
+        return Err(LocalQuoteError::PoolNotFound(
+            token_in,
+            token_out,
+            fee.as_u32(),
+        ));
+    }
+    simulate_swap_local_with_provider(
+        &pool,
+        fee,
+        amount_in,
+        zero_for_one(token_in, token_out),
+        client,
+    )
+    .await
+}
+
+/// Same as `simulate_swap_local` for an already loaded `pool`, reading the
+/// tick bitmap and crossed ticks through `client`
+pub async fn simulate_swap_local_with_provider<T, P>(
+    pool: &PoolWithState,
+    fee: UniswapPoolFee,
+    amount_in: U256,
+    zero_for_one: bool,
+    client: P,
+) -> Result<SimulatedSwap, LocalQuoteError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let math_error = |e: String| LocalQuoteError::MathError(e);
+    let pool_error = |e: crate::unswapv3_pool::UniswapV3PoolError| {
+        LocalQuoteError::GetPoolStateError(e.to_string())
+    };
+    let tick_spacing = fee.tick_spacing();
+    // 与 Quoter 一样不设价格限制
+    let sqrt_price_limit = full_range_sqrt_price_limit(zero_for_one);
+
+    let mut amount_remaining = amount_in;
+    let mut amount_out = U256::ZERO;
+    let mut sqrt_price = pool.sqrt_price_x96;
+    let mut tick = pool.tick;
+    let mut liquidity = pool.liquidity;
+    let mut ticks_crossed = 0;
+    while !amount_remaining.is_zero() && sqrt_price != sqrt_price_limit {
+        let word_pos = bitmap_word_pos(tick, tick_spacing, zero_for_one);
+        let bitmap = get_tick_bitmap_with_provider(pool.address, word_pos, &client)
+            .await
+            .map_err(pool_error)?;
+        let (tick_next, initialized) =
+            next_initialized_tick_within_one_word(bitmap, tick, tick_spacing, zero_for_one);
+        let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+        let sqrt_price_next =
+            get_sqrt_ratio_at_tick(tick_next).map_err(|e| math_error(e.to_string()))?;
+        let target = if zero_for_one {
+            sqrt_price_next.max(sqrt_price_limit)
+        } else {
+            sqrt_price_next.min(sqrt_price_limit)
+        };
+
+        let step = compute_swap_step_exact_in(
+            sqrt_price,
+            target,
+            liquidity,
+            amount_remaining,
+            fee.as_u32(),
+        )
+        .map_err(|e| math_error(e.to_string()))?;
+        let sqrt_price_start = sqrt_price;
+        sqrt_price = step.sqrt_price_next_x96;
+        amount_remaining = amount_remaining
+            .checked_sub(step.amount_in + step.fee_amount)
+            .ok_or_else(|| math_error("兑换数量下溢".to_string()))?;
+        amount_out += step.amount_out;
+
+        if sqrt_price == sqrt_price_next {
+            // 穿过 tick 时更新流动性
+            if initialized {
+                let mut liquidity_net =
+                    get_tick_info_with_provider(pool.address, tick_next, &client)
+                        .await
+                        .map_err(pool_error)?
+                        .liquidity_net;
+                if zero_for_one {
+                    liquidity_net = -liquidity_net;
+                }
+                liquidity = liquidity
+                    .checked_add_signed(liquidity_net)
+                    .ok_or_else(|| math_error("流动性溢出".to_string()))?;
+                ticks_crossed += 1;
+            }
+            tick = if zero_for_one {
+                tick_next - 1
+            } else {
+                tick_next
+            };
+        } else if sqrt_price != sqrt_price_start {
+            tick = get_tick_at_sqrt_ratio(sqrt_price).map_err(|e| math_error(e.to_string()))?;
+        }
+    }
+
+    Ok(SimulatedSwap {
+        amount_in: amount_in - amount_remaining,
+        amount_out,
+        sqrt_price_after_x96: sqrt_price,
+        tick_after: tick,
+        ticks_crossed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{aliases::I56, uint, Bytes, U160},
+        sol_types::SolCall,
+    };
+
+    use crate::{test_utils::MockTransport, unswapv3_pool::UNIV3_POOL};
+
+    use super::*;
+
+    fn pool(tick: i32) -> PoolWithState {
+        PoolWithState {
+            address: Address::ZERO,
+            exists: true,
+            sqrt_price_x96: get_sqrt_ratio_at_tick(tick).unwrap() + U256::from(1),
+            tick,
+            liquidity: 10u128.pow(18),
+        }
+    }
+
+    #[test]
+    fn test_next_tick_boundary_price() {
+        assert_eq!(
+            get_sqrt_ratio_at_tick(-120).unwrap(),
+            next_tick_boundary_price(-61, 60, true).unwrap()
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(-60).unwrap(),
+            next_tick_boundary_price(-61, 60, false).unwrap()
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(60).unwrap(),
+            next_tick_boundary_price(60, 60, true).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_quote_exact_input_in_range() {
+        // tick 30 距离两侧边界都有 30 个 tick，小额兑换不会跨 tick
+        let pool = pool(30);
+        let amount_out = quote_exact_input_in_range(
+            &pool,
+            UniswapPoolFee::Fee3000,
+            U256::from(10u64.pow(15)),
+            true,
+        )
+        .unwrap()
+        .unwrap();
+        let step = compute_swap_step_exact_in(
+            pool.sqrt_price_x96,
+            get_sqrt_ratio_at_tick(0).unwrap(),
+            pool.liquidity,
+            U256::from(10u64.pow(15)),
+            3000,
+        )
+        .unwrap();
+        assert_eq!(step.amount_out, amount_out);
+        // 价格约为 1.003，扣除 0.3% 手续费和价格影响后输出略少于输入
+        assert!(amount_out > uint!(990000000000000_U256));
+        assert!(amount_out < U256::from(10u64.pow(15)));
+
+        // 数量太大会跨过 tick 0，需要回退到链上报价
+        assert_eq!(
+            None,
+            quote_exact_input_in_range(
+                &pool,
+                UniswapPoolFee::Fee3000,
+                U256::from(10u64.pow(18)),
+                true
+            )
+            .unwrap()
+        );
+    }
+
+    fn bitmap_response(bitmap: U256) -> serde_json::Value {
+        serde_json::json!(bitmap.to_be_bytes::<32>())
+    }
+
+    fn tick_response(liquidity_net: i128) -> serde_json::Value {
+        let output = UNIV3_POOL::ticksCall::abi_encode_returns(&(
+            liquidity_net.unsigned_abs(),
+            liquidity_net,
+            U256::ZERO,
+            U256::ZERO,
+            I56::ZERO,
+            U160::ZERO,
+            0u32,
+            true,
+        ));
+        serde_json::json!(Bytes::from(output))
+    }
+
+    #[tokio::test]
+    async fn test_simulate_swap_crosses_tick() {
+        let pool = pool(30);
+        let amount_in = U256::from(2 * 10u64.pow(15));
+        let transport = MockTransport::default();
+        // word 0 中 tick 0 已初始化，穿过后流动性减半；word -1 中 tick -60 已初始化
+        transport.push_response(bitmap_response(U256::from(1)));
+        transport.push_response(tick_response(5 * 10i128.pow(17)));
+        transport.push_response(bitmap_response(U256::from(1) << 255));
+
+        let swap = simulate_swap_local_with_provider(
+            &pool,
+            UniswapPoolFee::Fee3000,
+            amount_in,
+            true,
+            transport.provider(),
+        )
+        .await
+        .unwrap();
+
+        // 逐段计算：先到 tick 0，再以一半的流动性继续
+        let tick_zero = get_sqrt_ratio_at_tick(0).unwrap();
+        let first = compute_swap_step_exact_in(
+            pool.sqrt_price_x96,
+            tick_zero,
+            pool.liquidity,
+            amount_in,
+            3000,
+        )
+        .unwrap();
+        assert_eq!(tick_zero, first.sqrt_price_next_x96);
+        let second = compute_swap_step_exact_in(
+            tick_zero,
+            get_sqrt_ratio_at_tick(-60).unwrap(),
+            5 * 10u128.pow(17),
+            amount_in - first.amount_in - first.fee_amount,
+            3000,
+        )
+        .unwrap();
+        assert_eq!(first.amount_out + second.amount_out, swap.amount_out);
+        assert_eq!(amount_in, swap.amount_in);
+        assert_eq!(second.sqrt_price_next_x96, swap.sqrt_price_after_x96);
+        assert_eq!(1, swap.ticks_crossed);
+        assert!(swap.tick_after < 0 && swap.tick_after > -60);
+        assert_eq!(3, transport.requests().len());
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use std::str::FromStr;
+
+    use crate::test_utils::spawn_mainnet_fork;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_quote_matches_quoter() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let rpc_url = "https://eth.llamarpc.com".to_string();
+        let amount_in = U256::from(10u64.pow(15));
+        let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+            token_a: weth,
+            token_b: usdc,
+            fee: UniswapPoolFee::Fee500.as_u32(),
+        });
+        let UniswapV3FactoryResult::GetPoolWithState(pool) =
+            uniswapv3_factory::execute(command, rpc_url.clone())
+                .await
+                .unwrap()
+        else {
+            panic!("unexpected result");
+        };
+        let local = quote_exact_input_in_range(
+            &pool,
+            UniswapPoolFee::Fee500,
+            amount_in,
+            zero_for_one(weth, usdc),
+        )
+        .unwrap()
+        .expect("0.001 WETH 不应跨 tick");
+
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in: weth,
+            token_out: usdc,
+            fee: UniswapPoolFee::Fee500,
+            amount_in,
+            sqrt_price_limit_x96: U256::ZERO,
+        });
+        let UniswapV3QuoterResult::QuoteExactInputSingle(on_chain) =
+            uniswapv3_quoter::execute(command, rpc_url).await.unwrap()
+        else {
+            panic!("unexpected result");
+        };
+        assert_eq!(on_chain, local);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_swap_matches_quoter_across_ticks() {
+        let fork = spawn_mainnet_fork();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        // 500 WETH 足以穿过多个 tick
+        let amount_in = U256::from(500u64) * U256::from(10u64.pow(18));
+        let local = simulate_swap_local(
+            weth,
+            usdc,
+            UniswapPoolFee::Fee500,
+            amount_in,
+            fork.endpoint(),
+        )
+        .await
+        .unwrap();
+        assert!(local.ticks_crossed >= 1);
+
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in: weth,
+            token_out: usdc,
+            fee: UniswapPoolFee::Fee500,
+            amount_in,
+            sqrt_price_limit_x96: U256::ZERO,
+        });
+        let UniswapV3QuoterResult::QuoteExactInputSingle(on_chain) =
+            uniswapv3_quoter::execute(command, fork.endpoint())
+                .await
+                .unwrap()
+        else {
+            panic!("unexpected result");
+        };
+        assert!(on_chain.abs_diff(local.amount_out) <= U256::from(1));
+    }
+}
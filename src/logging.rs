@@ -0,0 +1,32 @@
+//! Debug logging of contract commands, compiled to no-ops without the `tracing` feature
+
+use std::fmt::{Debug, Display};
+
+use alloy::{primitives::Address, rpc::types::TransactionRequest};
+
+#[cfg(feature = "tracing")]
+pub(crate) fn log_command(module: &str, address: Address, command: &impl Debug) {
+    tracing::debug!(module, %address, ?command, "执行命令");
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn log_command(_module: &str, _address: Address, _command: &impl Debug) {}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn log_result<R: Debug, E: Display>(module: &str, res: &Result<R, E>) {
+    match res {
+        Ok(result) => tracing::debug!(module, ?result, "命令执行成功"),
+        Err(error) => tracing::warn!(module, %error, "命令执行失败"),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn log_result<R: Debug, E: Display>(_module: &str, _res: &Result<R, E>) {}
+
+#[cfg(feature = "tracing")]
+pub(crate) fn log_tx(module: &str, tx: &TransactionRequest) {
+    tracing::debug!(module, ?tx, "交易已构建");
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn log_tx(_module: &str, _tx: &TransactionRequest) {}
@@ -0,0 +1,248 @@
+use std::{str::FromStr, sync::Arc};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    sol,
+    sol_types::SolCall,
+    transports::Transport,
+};
+
+use crate::unswapv3_pool::UNIV3_POOL;
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    MULTICALL3,
+    "src/abi/multicall3.json"
+);
+
+/// Multicall3 is deployed at the same address on every supported chain
+pub const MULTICALL3_CONTRACT_ADDR: &str = "0xcA11bde05977b3631167028862bE2a173976CA11";
+
+/// `slot0` price and liquidity of one pool read through Multicall3
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolState {
+    pub address: Address,
+    /// false 时调用失败（例如地址不是池子），其余字段均为零
+    pub success: bool,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MulticallError {
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(not(feature = "locale-en"), error("地址格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid address: {0}"))]
+    InvalidAddress(String),
+    #[cfg_attr(not(feature = "locale-en"), error("Multicall3 调用失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("Multicall3 call failed: {0}"))]
+    CallError(String),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("Multicall3 返回的结果数量不正确，期望{0}，实际{1}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("Multicall3 returned the wrong number of results, expected {0}, got {1}")
+    )]
+    ResultLengthMismatch(usize, usize),
+}
+
+impl MulticallError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            MulticallError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            MulticallError::InvalidAddress(_) => "INVALID_ADDRESS",
+            MulticallError::CallError(_) => "CALL_ERROR",
+            MulticallError::ResultLengthMismatch(..) => "RESULT_LENGTH_MISMATCH",
+        }
+    }
+}
+
+/// Read `slot0` and `liquidity` of every pool in a single `eth_call`, in the same
+/// order as `pools`. A pool whose reads fail is returned with `success == false`
+/// instead of failing the whole batch.
+pub async fn get_pool_states(
+    pools: &[Address],
+    rpc_url: String,
+) -> Result<Vec<PoolState>, MulticallError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| MulticallError::InvalidRpcUrl(e.to_string()))?;
+
+    get_pool_states_with_provider(pools, Arc::new(provider)).await
+}
+
+/// Same as `get_pool_states` but reuses an already constructed provider
+pub async fn get_pool_states_with_provider<T, P>(
+    pools: &[Address],
+    client: P,
+) -> Result<Vec<PoolState>, MulticallError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let multicall = Address::from_str(MULTICALL3_CONTRACT_ADDR)
+        .map_err(|e| MulticallError::InvalidAddress(e.to_string()))?;
+    // 每个池子两次调用：slot0 和 liquidity
+    let calls = pools
+        .iter()
+        .flat_map(|&pool| {
+            [
+                UNIV3_POOL::slot0Call {}.abi_encode(),
+                UNIV3_POOL::liquidityCall {}.abi_encode(),
+            ]
+            .map(|call_data| Multicall3::Call3 {
+                target: pool,
+                allowFailure: true,
+                callData: call_data.into(),
+            })
+        })
+        .collect();
+    let results = MULTICALL3::new(multicall, client)
+        .aggregate3(calls)
+        .call()
+        .await
+        .map_err(|e| MulticallError::CallError(e.to_string()))?
+        .returnData;
+    if results.len() != pools.len() * 2 {
+        return Err(MulticallError::ResultLengthMismatch(
+            pools.len() * 2,
+            results.len(),
+        ));
+    }
+    Ok(pools
+        .iter()
+        .zip(results.chunks(2))
+        .map(|(&address, results)| decode_pool_state(address, &results[0], &results[1]))
+        .collect())
+}
+
+fn decode_pool_state(
+    address: Address,
+    slot0: &Multicall3::Result,
+    liquidity: &Multicall3::Result,
+) -> PoolState {
+    let decoded = (slot0.success && liquidity.success)
+        .then(|| {
+            let slot0 = UNIV3_POOL::slot0Call::abi_decode_returns(&slot0.returnData, true).ok()?;
+            let liquidity =
+                UNIV3_POOL::liquidityCall::abi_decode_returns(&liquidity.returnData, true).ok()?;
+            Some((slot0, liquidity._0))
+        })
+        .flatten();
+    match decoded {
+        Some((slot0, liquidity)) => PoolState {
+            address,
+            success: true,
+            sqrt_price_x96: U256::from(slot0.sqrtPriceX96),
+            tick: slot0.tick.as_i32(),
+            liquidity,
+        },
+        None => PoolState {
+            address,
+            success: false,
+            sqrt_price_x96: U256::ZERO,
+            tick: 0,
+            liquidity: 0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{aliases::I24, Bytes, U160};
+
+    use crate::test_utils::MockTransport;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_pool_states_in_order() {
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let not_a_pool = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+            U160::from(1u64 << 32),
+            I24::try_from(-42).unwrap(),
+            0u16,
+            1u16,
+            1u16,
+            0u8,
+            true,
+        ));
+        let liquidity = UNIV3_POOL::liquidityCall::abi_encode_returns(&(1000u128,));
+        let result = |success: bool, data: Vec<u8>| Multicall3::Result {
+            success,
+            returnData: data.into(),
+        };
+        let output = MULTICALL3::aggregate3Call::abi_encode_returns(&(vec![
+            result(true, slot0),
+            result(true, liquidity),
+            result(false, Vec::new()),
+            result(false, Vec::new()),
+        ],));
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(Bytes::from(output)));
+
+        let states = get_pool_states_with_provider(&[pool, not_a_pool], transport.provider())
+            .await
+            .unwrap();
+        // 只发送了一次 eth_call
+        assert_eq!(1, transport.requests().len());
+        assert_eq!(
+            vec![
+                PoolState {
+                    address: pool,
+                    success: true,
+                    sqrt_price_x96: U256::from(1u64 << 32),
+                    tick: -42,
+                    liquidity: 1000,
+                },
+                PoolState {
+                    address: not_a_pool,
+                    success: false,
+                    sqrt_price_x96: U256::ZERO,
+                    tick: 0,
+                    liquidity: 0,
+                },
+            ],
+            states
+        );
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use crate::test_utils::spawn_mainnet_fork;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_pool_states_on_fork() {
+        let fork = spawn_mainnet_fork();
+        let pools = [
+            // USDC/WETH 0.05%
+            Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap(),
+            // WETH/USDT 0.05%
+            Address::from_str("0x11b815efB8f581194ae79006d24E0d814B7697F6").unwrap(),
+            // ETHC/WETH 1%
+            Address::from_str("0xFbDbaC2d456A3CC2754A626C2fB83C1af25A3a6F").unwrap(),
+        ];
+        let states = get_pool_states(&pools, fork.endpoint()).await.unwrap();
+        assert_eq!(3, states.len());
+        for (state, pool) in states.iter().zip(pools) {
+            assert_eq!(pool, state.address);
+            assert!(state.success);
+            assert!(state.sqrt_price_x96 > U256::ZERO);
+            assert!(state.liquidity > 0);
+        }
+        assert!(states[1].tick < 0);
+    }
+}
@@ -0,0 +1,345 @@
+use std::str::FromStr;
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{aliases::U48, Address, Bytes, B256, U160, U256},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::{Eip712Domain, SolCall, SolStruct, SolValue},
+};
+
+use crate::{
+    client::UniswapSupportChain,
+    routing::encode_path,
+    unswapv3_pool::UniswapPoolFee,
+    utils::{ApprovalStrategy, Token},
+};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    PERMIT2,
+    "src/abi/permit2.json"
+);
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UNIVERSAL_ROUTER,
+    "src/abi/universal_router.json"
+);
+
+/// Permit2 is deployed at the same address on every supported chain
+pub const PERMIT2_CONTRACT_ADDR: &str = "0x000000000022D473030F116dDEE9F6B43aC78BA3";
+
+/// Universal Router on Ethereum mainnet. Unlike the V3 `SwapRouter`, which pulls
+/// tokens with a plain ERC20 `transferFrom`, it pays swaps through Permit2, so it
+/// is the spender a `PermitSingle` has to name.
+pub const UNIVERSAL_ROUTER_CONTRACT_ADDR: &str = "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD";
+
+/// Universal Router command bytes, see `Commands.sol`
+const V3_SWAP_EXACT_IN: u8 = 0x00;
+const PERMIT2_PERMIT: u8 = 0x0a;
+
+pub struct PermitSingleParams {
+    pub token: Address,
+    pub amount: U256,
+    pub expiration: u64,
+    pub nonce: u64,
+    pub spender: Address,
+    pub sig_deadline: U256,
+}
+
+impl TryFrom<PermitSingleParams> for IAllowanceTransfer::PermitSingle {
+    type Error = Permit2Error;
+    fn try_from(value: PermitSingleParams) -> std::result::Result<Self, Self::Error> {
+        let amount = U160::checked_from_limbs_slice(value.amount.as_limbs())
+            .ok_or_else(|| Permit2Error::AmountOverflow(value.amount.to_string()))?;
+        let expiration: U48 = value
+            .expiration
+            .try_into()
+            .map_err(|_| Permit2Error::Uint48Overflow(value.expiration))?;
+        let nonce: U48 = value
+            .nonce
+            .try_into()
+            .map_err(|_| Permit2Error::Uint48Overflow(value.nonce))?;
+        Ok(IAllowanceTransfer::PermitSingle {
+            details: IAllowanceTransfer::PermitDetails {
+                token: value.token,
+                amount,
+                expiration,
+                nonce,
+            },
+            spender: value.spender,
+            sigDeadline: value.sig_deadline,
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Permit2Error {
+    #[cfg_attr(not(feature = "locale-en"), error("地址格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid address: {0}"))]
+    InvalidAddress(String),
+    #[cfg_attr(not(feature = "locale-en"), error("授权数量超过 uint160 上限{0}"))]
+    #[cfg_attr(feature = "locale-en", error("allowance amount exceeds uint160: {0}"))]
+    AmountOverflow(String),
+    #[cfg_attr(not(feature = "locale-en"), error("数值超过 uint48 上限{0}"))]
+    #[cfg_attr(feature = "locale-en", error("value exceeds uint48: {0}"))]
+    Uint48Overflow(u64),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("permit 的 spender {0} 不是 Universal Router")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("permit spender {0} is not the Universal Router")
+    )]
+    InvalidSpender(Address),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("permit 授权的 token {permit} 与兑换的输入 token {swap} 不一致")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("permit token {permit} does not match the swap input token {swap}")
+    )]
+    TokenMismatch { permit: Address, swap: Address },
+    #[cfg_attr(not(feature = "locale-en"), error("兑换路径不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid swap path: {0}"))]
+    InvalidPath(String),
+}
+
+impl Permit2Error {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            Permit2Error::InvalidAddress(_) => "INVALID_ADDRESS",
+            Permit2Error::AmountOverflow(_) => "AMOUNT_OVERFLOW",
+            Permit2Error::Uint48Overflow(_) => "UINT48_OVERFLOW",
+            Permit2Error::InvalidSpender(_) => "INVALID_SPENDER",
+            Permit2Error::TokenMismatch { .. } => "TOKEN_MISMATCH",
+            Permit2Error::InvalidPath(_) => "INVALID_PATH",
+        }
+    }
+}
+
+fn permit2_address() -> Result<Address, Permit2Error> {
+    Address::from_str(PERMIT2_CONTRACT_ADDR)
+        .map_err(|e| Permit2Error::InvalidAddress(e.to_string()))
+}
+
+/// EIP-712 domain of the Permit2 contract on the given chain
+pub fn permit2_domain(chain: &UniswapSupportChain) -> Result<Eip712Domain, Permit2Error> {
+    Ok(Eip712Domain {
+        name: Some("Permit2".into()),
+        chain_id: Some(U256::from(chain.as_chain_id())),
+        verifying_contract: Some(permit2_address()?),
+        ..Default::default()
+    })
+}
+
+/// The EIP-712 digest the token owner has to sign for a `PermitSingle`
+pub fn permit_single_digest(
+    params: PermitSingleParams,
+    chain: &UniswapSupportChain,
+) -> Result<B256, Permit2Error> {
+    let permit: IAllowanceTransfer::PermitSingle = params.try_into()?;
+    Ok(permit.eip712_signing_hash(&permit2_domain(chain)?))
+}
+
+/// Build the Permit2 `permit` call that consumes the owner's signature and
+/// grants `params.spender` an allowance for `params.token`. The spender must be a
+/// contract that pulls tokens through Permit2, such as the Universal Router; the
+/// V3 `SwapRouter` never calls Permit2 and could not use the allowance. To permit
+/// and swap in one transaction use `permit_and_swap_tx` instead.
+pub fn permit_tx(
+    owner: Address,
+    params: PermitSingleParams,
+    signature: Bytes,
+) -> Result<TransactionRequest, Permit2Error> {
+    let call = PERMIT2::permitCall {
+        owner,
+        permitSingle: params.try_into()?,
+        signature,
+    };
+    Ok(TransactionRequest::default()
+        .with_to(permit2_address()?)
+        .with_input(call.abi_encode()))
+}
+
+/// Exact input swap through the Universal Router, paid through Permit2 with the
+/// owner's signed `permit` instead of an ERC20 approval of the router
+pub struct PermitSwapParams {
+    /// `spender` must be `UNIVERSAL_ROUTER_CONTRACT_ADDR`
+    pub permit: PermitSingleParams,
+    pub signature: Bytes,
+    /// 交易方向的路径，第一个 token 必须是 permit 授权的 token
+    pub tokens: Vec<Address>,
+    pub fees: Vec<UniswapPoolFee>,
+    pub recipient: Address,
+    pub amount_in: U256,
+    pub amount_out_minimum: U256,
+    pub deadline: U256,
+}
+
+/// Build the Universal Router `execute` call running `PERMIT2_PERMIT` with the
+/// signed permit and then `V3_SWAP_EXACT_IN` paid by the sender, so the approval
+/// and the swap happen in a single transaction. Sent from the permit's owner.
+pub fn permit_and_swap_tx(params: PermitSwapParams) -> Result<TransactionRequest, Permit2Error> {
+    let router = Address::from_str(UNIVERSAL_ROUTER_CONTRACT_ADDR)
+        .map_err(|e| Permit2Error::InvalidAddress(e.to_string()))?;
+    if params.permit.spender != router {
+        return Err(Permit2Error::InvalidSpender(params.permit.spender));
+    }
+    let token_in = params.tokens.first().copied().unwrap_or_default();
+    if token_in != params.permit.token {
+        return Err(Permit2Error::TokenMismatch {
+            permit: params.permit.token,
+            swap: token_in,
+        });
+    }
+    let path = encode_path(&params.tokens, &params.fees)
+        .map_err(|e| Permit2Error::InvalidPath(e.to_string()))?;
+    let permit: IAllowanceTransfer::PermitSingle = params.permit.try_into()?;
+    // payerIsUser = true：Universal Router 通过 Permit2 从发送者转出 token_in
+    let swap = (
+        params.recipient,
+        params.amount_in,
+        params.amount_out_minimum,
+        path,
+        true,
+    );
+    let call = UNIVERSAL_ROUTER::executeCall {
+        commands: vec![PERMIT2_PERMIT, V3_SWAP_EXACT_IN].into(),
+        inputs: vec![
+            (permit, params.signature).abi_encode_params().into(),
+            swap.abi_encode_params().into(),
+        ],
+        deadline: params.deadline,
+    };
+    Ok(TransactionRequest::default()
+        .with_to(router)
+        .with_input(call.abi_encode()))
+}
+
+/// Permit2 pulls tokens through a regular ERC20 allowance, so the token has to
+/// approve the Permit2 contract once before any permit can be used
+pub fn approve_permit2_tx(token: &Token) -> Result<TransactionRequest, Permit2Error> {
+    Ok(token.approve_tx(permit2_address()?, ApprovalStrategy::Infinite))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{hex, primitives::b256};
+
+    use super::*;
+
+    fn usdc_permit() -> PermitSingleParams {
+        PermitSingleParams {
+            token: Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            amount: U256::from(1000000000u64),
+            expiration: 1735689600,
+            nonce: 0,
+            spender: Address::from_str(UNIVERSAL_ROUTER_CONTRACT_ADDR).unwrap(),
+            sig_deadline: U256::from(1735689600u64),
+        }
+    }
+
+    #[test]
+    fn test_permit2_domain_separator() {
+        let domain = permit2_domain(&UniswapSupportChain::Ethereum).unwrap();
+        // Permit2.DOMAIN_SEPARATOR() on mainnet
+        assert_eq!(
+            b256!("866a5aba21966af95d6c7ab78eb2b2fc913915c28be3b9aa07cc04ff903e3f28"),
+            domain.separator()
+        );
+    }
+
+    #[test]
+    fn test_permit_single_digest() {
+        let digest = permit_single_digest(usdc_permit(), &UniswapSupportChain::Ethereum).unwrap();
+        assert_eq!(
+            b256!("e33020be9b51567f8945a0b5b650ec4673d054c87586914c7ddd1d5fafd302f7"),
+            digest
+        );
+    }
+
+    #[test]
+    fn test_permit_single_amount_overflow() {
+        let mut params = usdc_permit();
+        params.amount = U256::MAX;
+        let err = permit_single_digest(params, &UniswapSupportChain::Ethereum).unwrap_err();
+        assert!(matches!(err, Permit2Error::AmountOverflow(_)));
+    }
+
+    #[test]
+    fn test_permit_tx() {
+        let owner = Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap();
+        let signature = Bytes::from(vec![1u8; 65]);
+        let tx = permit_tx(owner, usdc_permit(), signature.clone()).unwrap();
+        let data = tx.input.input().unwrap();
+        assert_eq!("2b67b570", hex::encode(&data[..4]));
+        let call = PERMIT2::permitCall::abi_decode(data, true).unwrap();
+        assert_eq!(owner, call.owner);
+        assert_eq!(signature, call.signature);
+    }
+
+    #[test]
+    fn test_permit_and_swap_tx() {
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let recipient = Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap();
+        let signature = Bytes::from(vec![1u8; 65]);
+        let params = |permit: PermitSingleParams, tokens: Vec<Address>| PermitSwapParams {
+            permit,
+            signature: signature.clone(),
+            tokens,
+            fees: vec![UniswapPoolFee::Fee500],
+            recipient,
+            amount_in: U256::from(1000000000u64),
+            amount_out_minimum: U256::from(10u128.pow(17)),
+            deadline: U256::from(1735689600u64),
+        };
+
+        let tx = permit_and_swap_tx(params(usdc_permit(), vec![usdc, weth])).unwrap();
+        assert_eq!(
+            Some(Address::from_str(UNIVERSAL_ROUTER_CONTRACT_ADDR).unwrap()),
+            tx.to.and_then(|to| to.to().copied())
+        );
+        let data = tx.input.input().unwrap();
+        assert_eq!("3593564c", hex::encode(&data[..4]));
+        let call = UNIVERSAL_ROUTER::executeCall::abi_decode(data, true).unwrap();
+        assert_eq!(vec![0x0a, 0x00], call.commands.to_vec());
+        assert_eq!(U256::from(1735689600u64), call.deadline);
+        // PERMIT2_PERMIT 的输入是 (PermitSingle, signature)
+        let (permit, permit_signature) =
+            <(IAllowanceTransfer::PermitSingle, Bytes)>::abi_decode_params(&call.inputs[0], true)
+                .unwrap();
+        assert_eq!(usdc, permit.details.token);
+        assert_eq!(usdc_permit().spender, permit.spender);
+        assert_eq!(signature, permit_signature);
+        // V3_SWAP_EXACT_IN 的输入是 (recipient, amountIn, amountOutMin, path, payerIsUser)
+        let (swap_recipient, amount_in, amount_out_min, path, payer_is_user) =
+            <(Address, U256, U256, Bytes, bool)>::abi_decode_params(&call.inputs[1], true).unwrap();
+        assert_eq!(recipient, swap_recipient);
+        assert_eq!(U256::from(1000000000u64), amount_in);
+        assert_eq!(U256::from(10u128.pow(17)), amount_out_min);
+        assert_eq!(
+            encode_path(&[usdc, weth], &[UniswapPoolFee::Fee500]).unwrap(),
+            path
+        );
+        assert!(payer_is_user);
+
+        // V3 SwapRouter 不经过 Permit2，不能作为 spender
+        let mut permit = usdc_permit();
+        permit.spender = Address::from_str("0xE592427A0AEce92De3Edee1F18E0157C05861564").unwrap();
+        let err = permit_and_swap_tx(params(permit, vec![usdc, weth])).unwrap_err();
+        assert_eq!("INVALID_SPENDER", err.code());
+        let err = permit_and_swap_tx(params(usdc_permit(), vec![weth, usdc])).unwrap_err();
+        assert!(matches!(
+            err,
+            Permit2Error::TokenMismatch { permit, swap } if permit == usdc && swap == weth
+        ));
+    }
+}
@@ -0,0 +1,479 @@
+use std::sync::Arc;
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{
+        aliases::{I24, U24},
+        Address, U256,
+    },
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+    transports::Transport,
+};
+
+use crate::{
+    config::ContractAddresses, uniswapv3_math::min_amount_with_slippage,
+    unswapv3_pool::UniswapPoolFee,
+};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    NONFUNGIBLE_POSITION_MANAGER,
+    "src/abi/nonfungible_position_manager.json"
+);
+
+pub const POSITION_MANAGER_CONTRACT_ADDR: &str = "0xC36442b4a4522E871399CD717aBDD847Ab11FE88";
+
+/// A liquidity position NFT as returned by `positions(tokenId)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub token_id: U256,
+    pub operator: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside0_last_x128: U256,
+    pub fee_growth_inside1_last_x128: U256,
+    /// 已结算但尚未 collect 的数量
+    pub tokens_owed0: u128,
+    pub tokens_owed1: u128,
+}
+
+#[derive(Debug)]
+pub struct MintParams {
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: UniswapPoolFee,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub amount0_desired: U256,
+    pub amount1_desired: U256,
+    pub amount0_min: U256,
+    pub amount1_min: U256,
+    pub recipient: Address,
+    pub deadline: U256,
+}
+
+impl TryFrom<MintParams> for INonfungiblePositionManager::MintParams {
+    type Error = PositionManagerError;
+    fn try_from(value: MintParams) -> std::result::Result<Self, Self::Error> {
+        if value.token0 >= value.token1 {
+            return Err(PositionManagerError::UnsortedTokens(
+                value.token0,
+                value.token1,
+            ));
+        }
+        if value.tick_lower >= value.tick_upper {
+            return Err(PositionManagerError::InvalidTickRange(
+                value.tick_lower,
+                value.tick_upper,
+            ));
+        }
+        Ok(INonfungiblePositionManager::MintParams {
+            token0: value.token0,
+            token1: value.token1,
+            fee: U24::from(value.fee.as_u32()),
+            tickLower: to_i24(value.tick_lower)?,
+            tickUpper: to_i24(value.tick_upper)?,
+            amount0Desired: value.amount0_desired,
+            amount1Desired: value.amount1_desired,
+            amount0Min: value.amount0_min,
+            amount1Min: value.amount1_min,
+            recipient: value.recipient,
+            deadline: value.deadline,
+        })
+    }
+}
+
+fn to_i24(tick: i32) -> Result<I24, PositionManagerError> {
+    I24::try_from(tick).map_err(|_| PositionManagerError::InvalidTick(tick))
+}
+
+#[derive(Debug)]
+pub enum PositionManagerCommand {
+    GetPosition(U256),
+}
+
+#[derive(Debug)]
+pub enum PositionManagerResult {
+    GetPosition(Position),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PositionManagerError {
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(not(feature = "locale-en"), error("地址格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid address: {0}"))]
+    InvalidAddress(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取仓位{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read position: {0}"))]
+    GetPositionError(String),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("token0 必须小于 token1，当前 token0: {0}, token1: {1}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("token0 must be less than token1, got token0: {0}, token1: {1}")
+    )]
+    UnsortedTokens(Address, Address),
+    #[cfg_attr(not(feature = "locale-en"), error("tick 超出 int24 范围{0}"))]
+    #[cfg_attr(feature = "locale-en", error("tick out of int24 range: {0}"))]
+    InvalidTick(i32),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("tick_lower 必须小于 tick_upper，当前{0}..{1}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("tick_lower must be less than tick_upper, got {0}..{1}")
+    )]
+    InvalidTickRange(i32, i32),
+    #[cfg_attr(not(feature = "locale-en"), error("滑点必须小于 10000 bps，当前{0}"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("slippage must be less than 10000 bps, got {0}")
+    )]
+    InvalidSlippage(u32),
+}
+
+impl PositionManagerError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            PositionManagerError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            PositionManagerError::InvalidAddress(_) => "INVALID_ADDRESS",
+            PositionManagerError::GetPositionError(_) => "GET_POSITION_ERROR",
+            PositionManagerError::UnsortedTokens(..) => "UNSORTED_TOKENS",
+            PositionManagerError::InvalidTick(_) => "INVALID_TICK",
+            PositionManagerError::InvalidTickRange(..) => "INVALID_TICK_RANGE",
+            PositionManagerError::InvalidSlippage(_) => "INVALID_SLIPPAGE",
+        }
+    }
+}
+
+pub async fn execute(
+    command: PositionManagerCommand,
+    rpc_url: String,
+) -> Result<PositionManagerResult, PositionManagerError> {
+    execute_with_config(command, rpc_url, &ContractAddresses::default()).await
+}
+
+/// Same as `execute` but against `addresses.position_manager` instead of the
+/// mainnet position manager
+pub async fn execute_with_config(
+    command: PositionManagerCommand,
+    rpc_url: String,
+    addresses: &ContractAddresses,
+) -> Result<PositionManagerResult, PositionManagerError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| PositionManagerError::InvalidRpcUrl(e.to_string()))?;
+
+    execute_with_provider(command, Arc::new(provider), addresses).await
+}
+
+/// Same as `execute_with_config` but reuses an already constructed provider
+pub async fn execute_with_provider<T, P>(
+    command: PositionManagerCommand,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<PositionManagerResult, PositionManagerError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let contract = NONFUNGIBLE_POSITION_MANAGER::new(addresses.position_manager, client);
+    match command {
+        PositionManagerCommand::GetPosition(token_id) => {
+            let position = contract
+                .positions(token_id)
+                .call()
+                .await
+                .map_err(|e| PositionManagerError::GetPositionError(e.to_string()))?;
+            Ok(PositionManagerResult::GetPosition(Position {
+                token_id,
+                operator: position.operator,
+                token0: position.token0,
+                token1: position.token1,
+                fee: position.fee.to(),
+                tick_lower: position.tickLower.as_i32(),
+                tick_upper: position.tickUpper.as_i32(),
+                liquidity: position.liquidity,
+                fee_growth_inside0_last_x128: position.feeGrowthInside0LastX128,
+                fee_growth_inside1_last_x128: position.feeGrowthInside1LastX128,
+                tokens_owed0: position.tokensOwed0,
+                tokens_owed1: position.tokensOwed1,
+            }))
+        }
+    }
+}
+
+/// Build a `mint` transaction to `addresses.position_manager` opening a new
+/// position. Both tokens have to be approved to the position manager beforehand.
+pub fn mint_tx(
+    params: MintParams,
+    addresses: &ContractAddresses,
+) -> Result<TransactionRequest, PositionManagerError> {
+    let call = NONFUNGIBLE_POSITION_MANAGER::mintCall {
+        params: params.try_into()?,
+    };
+    Ok(TransactionRequest::default()
+        .with_to(addresses.position_manager)
+        .with_input(call.abi_encode()))
+}
+
+#[derive(Debug)]
+pub struct DecreaseLiquidityParams {
+    pub token_id: U256,
+    pub liquidity: u128,
+    /// 按当前价格移除 `liquidity` 应得的数量，例如由 `get_amounts_for_liquidity` 计算
+    pub amount0_expected: U256,
+    pub amount1_expected: U256,
+    pub slippage_bps: u32,
+    pub deadline: U256,
+}
+
+/// Build a `decreaseLiquidity` transaction to `addresses.position_manager`
+/// removing `params.liquidity` from the position, with `amount0Min`/`amount1Min`
+/// set `slippage_bps` below the expected amounts. The tokens are only credited
+/// to the position, `collect_tx` withdraws them.
+pub fn decrease_liquidity_tx(
+    params: DecreaseLiquidityParams,
+    addresses: &ContractAddresses,
+) -> Result<TransactionRequest, PositionManagerError> {
+    let min_amount = |amount| {
+        min_amount_with_slippage(amount, params.slippage_bps)
+            .map_err(|_| PositionManagerError::InvalidSlippage(params.slippage_bps))
+    };
+    let call = NONFUNGIBLE_POSITION_MANAGER::decreaseLiquidityCall {
+        params: INonfungiblePositionManager::DecreaseLiquidityParams {
+            tokenId: params.token_id,
+            liquidity: params.liquidity,
+            amount0Min: min_amount(params.amount0_expected)?,
+            amount1Min: min_amount(params.amount1_expected)?,
+            deadline: params.deadline,
+        },
+    };
+    Ok(TransactionRequest::default()
+        .with_to(addresses.position_manager)
+        .with_input(call.abi_encode()))
+}
+
+/// Build a `collect` transaction to `addresses.position_manager` sending everything
+/// owed to the position, i.e. the fees plus whatever `decreaseLiquidity` released,
+/// to `recipient`
+pub fn collect_tx(
+    token_id: U256,
+    recipient: Address,
+    addresses: &ContractAddresses,
+) -> TransactionRequest {
+    let call = NONFUNGIBLE_POSITION_MANAGER::collectCall {
+        params: INonfungiblePositionManager::CollectParams {
+            tokenId: token_id,
+            recipient,
+            // 最大值表示全部提取
+            amount0Max: u128::MAX,
+            amount1Max: u128::MAX,
+        },
+    };
+    TransactionRequest::default()
+        .with_to(addresses.position_manager)
+        .with_input(call.abi_encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy::hex;
+
+    use crate::client::UniswapSupportChain;
+
+    use super::*;
+
+    fn usdc_weth_mint() -> MintParams {
+        MintParams {
+            token0: Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            token1: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            fee: UniswapPoolFee::Fee500,
+            tick_lower: 190000,
+            tick_upper: 200000,
+            amount0_desired: U256::from(1000000000u64),
+            amount1_desired: U256::from(10).pow(U256::from(18)),
+            amount0_min: U256::ZERO,
+            amount1_min: U256::ZERO,
+            recipient: Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap(),
+            deadline: U256::from(1735689600u64),
+        }
+    }
+
+    #[test]
+    fn test_mint_tx() {
+        let tx = mint_tx(usdc_weth_mint(), &ContractAddresses::default()).unwrap();
+        assert_eq!(
+            Some(Address::from_str(POSITION_MANAGER_CONTRACT_ADDR).unwrap()),
+            tx.to.and_then(|to| to.to().copied())
+        );
+        let data = tx.input.input().unwrap();
+        assert_eq!("88316456", hex::encode(&data[..4]));
+        let call = NONFUNGIBLE_POSITION_MANAGER::mintCall::abi_decode(data, true).unwrap();
+        assert_eq!(190000, call.params.tickLower.as_i32());
+        assert_eq!(U24::from(500), call.params.fee);
+    }
+
+    #[test]
+    fn test_mint_tx_invalid_params() {
+        let mut params = usdc_weth_mint();
+        std::mem::swap(&mut params.token0, &mut params.token1);
+        assert!(matches!(
+            mint_tx(params, &ContractAddresses::default()),
+            Err(PositionManagerError::UnsortedTokens(..))
+        ));
+
+        let mut params = usdc_weth_mint();
+        params.tick_upper = params.tick_lower;
+        assert!(matches!(
+            mint_tx(params, &ContractAddresses::default()),
+            Err(PositionManagerError::InvalidTickRange(..))
+        ));
+
+        let mut params = usdc_weth_mint();
+        params.tick_upper = 1 << 23;
+        assert!(matches!(
+            mint_tx(params, &ContractAddresses::default()),
+            Err(PositionManagerError::InvalidTick(_))
+        ));
+    }
+
+    #[test]
+    fn test_mint_tx_per_chain() {
+        for chain in [
+            UniswapSupportChain::Ethereum,
+            UniswapSupportChain::Base,
+            UniswapSupportChain::Sepolia,
+        ] {
+            let addresses = ContractAddresses::for_chain(&chain);
+            let tx = mint_tx(usdc_weth_mint(), &addresses).unwrap();
+            assert_eq!(
+                Some(addresses.position_manager),
+                tx.to.and_then(|to| to.to().copied())
+            );
+        }
+    }
+
+    #[test]
+    fn test_decrease_liquidity_tx() {
+        let params = DecreaseLiquidityParams {
+            token_id: U256::from(12345),
+            liquidity: 1_000_000,
+            amount0_expected: U256::from(10_000),
+            amount1_expected: U256::from(2_000),
+            slippage_bps: 50,
+            deadline: U256::from(1735689600u64),
+        };
+        let tx = decrease_liquidity_tx(params, &ContractAddresses::default()).unwrap();
+        assert_eq!(
+            Some(Address::from_str(POSITION_MANAGER_CONTRACT_ADDR).unwrap()),
+            tx.to.and_then(|to| to.to().copied())
+        );
+        let data = tx.input.input().unwrap();
+        assert_eq!("0c49ccbe", hex::encode(&data[..4]));
+        let call =
+            NONFUNGIBLE_POSITION_MANAGER::decreaseLiquidityCall::abi_decode(data, true).unwrap();
+        assert_eq!(U256::from(12345), call.params.tokenId);
+        assert_eq!(1_000_000, call.params.liquidity);
+        assert_eq!(U256::from(9_950), call.params.amount0Min);
+        assert_eq!(U256::from(1_990), call.params.amount1Min);
+
+        assert!(matches!(
+            decrease_liquidity_tx(
+                DecreaseLiquidityParams {
+                    token_id: U256::from(1),
+                    liquidity: 1,
+                    amount0_expected: U256::ZERO,
+                    amount1_expected: U256::ZERO,
+                    slippage_bps: 10_000,
+                    deadline: U256::ZERO,
+                },
+                &ContractAddresses::default()
+            ),
+            Err(PositionManagerError::InvalidSlippage(10_000))
+        ));
+    }
+
+    #[test]
+    fn test_collect_tx() {
+        let recipient = Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap();
+        let tx = collect_tx(U256::from(12345), recipient, &ContractAddresses::default());
+        assert_eq!(
+            Some(Address::from_str(POSITION_MANAGER_CONTRACT_ADDR).unwrap()),
+            tx.to.and_then(|to| to.to().copied())
+        );
+        let data = tx.input.input().unwrap();
+        assert_eq!("fc6f7865", hex::encode(&data[..4]));
+        let call = NONFUNGIBLE_POSITION_MANAGER::collectCall::abi_decode(data, true).unwrap();
+        assert_eq!(U256::from(12345), call.params.tokenId);
+        assert_eq!(recipient, call.params.recipient);
+        assert_eq!(u128::MAX, call.params.amount0Max);
+        assert_eq!(u128::MAX, call.params.amount1Max);
+    }
+
+    #[test]
+    fn test_exit_txs_per_chain() {
+        let recipient = Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap();
+        for chain in [
+            UniswapSupportChain::Ethereum,
+            UniswapSupportChain::Base,
+            UniswapSupportChain::Sepolia,
+        ] {
+            let addresses = ContractAddresses::for_chain(&chain);
+            let decrease = decrease_liquidity_tx(
+                DecreaseLiquidityParams {
+                    token_id: U256::from(1),
+                    liquidity: 1,
+                    amount0_expected: U256::ZERO,
+                    amount1_expected: U256::ZERO,
+                    slippage_bps: 50,
+                    deadline: U256::ZERO,
+                },
+                &addresses,
+            )
+            .unwrap();
+            let collect = collect_tx(U256::from(1), recipient, &addresses);
+            for tx in [decrease, collect] {
+                assert_eq!(
+                    Some(addresses.position_manager),
+                    tx.to.and_then(|to| to.to().copied())
+                );
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_position() {
+        // 第一个 V3 仓位 NFT
+        let command = PositionManagerCommand::GetPosition(U256::from(1));
+        let PositionManagerResult::GetPosition(position) =
+            execute(command, "https://eth.llamarpc.com".to_string())
+                .await
+                .unwrap();
+        assert!(position.token0 < position.token1);
+        assert!(position.tick_lower < position.tick_upper);
+        assert!(UniswapPoolFee::from_u32(position.fee).is_some());
+    }
+}
@@ -0,0 +1,425 @@
+use std::{future::IntoFuture, sync::Arc};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{Address, U256, U512},
+    providers::{Provider, ProviderBuilder},
+    transports::Transport,
+};
+
+use crate::{
+    tick_math::{self, TickMathError},
+    unswapv3_pool::UNIV3_POOL,
+};
+
+const BPS: u32 = 10_000;
+
+/// Prices a pool by its TWAP and refuses to answer when the spot price has
+/// moved too far from it, which usually means the pool is being manipulated
+/// within the current block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceOracle {
+    pub pool: Address,
+    /// TWAP 的时间窗口（秒）
+    pub twap_window: u32,
+    /// spot 和 TWAP 价格允许的最大偏离
+    pub max_divergence_bps: u32,
+}
+
+/// Spot and TWAP price of token0 in token1, as sqrt prices in Q64.96
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OraclePrice {
+    pub spot_tick: i32,
+    pub spot_sqrt_price_x96: U256,
+    pub twap_tick: i32,
+    pub twap_sqrt_price_x96: U256,
+    pub divergence_bps: U256,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PriceOracleError {
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取池子状态{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read pool state: {0}"))]
+    GetPoolStateError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("TWAP 时间窗口不能为 0"))]
+    #[cfg_attr(feature = "locale-en", error("TWAP window must not be 0"))]
+    InvalidTwapWindow,
+    #[cfg_attr(not(feature = "locale-en"), error("observe 返回的观测点数量不正确{0}"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("observe returned the wrong number of observations: {0}")
+    )]
+    InvalidObservations(usize),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("池子只有 {available} 秒的观测历史，不足 TWAP 窗口 {required} 秒")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error(
+            "pool has {available}s of observation history, less than the {required}s TWAP window"
+        )
+    )]
+    InsufficientHistory { available: u64, required: u32 },
+    #[cfg_attr(not(feature = "locale-en"), error("TWAP tick 计算失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("TWAP tick computation failed: {0}"))]
+    TickMathError(#[from] TickMathError),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("spot 价格偏离 TWAP {divergence_bps} bps，超过上限{max_divergence_bps} bps")
+    )]
+    #[cfg_attr(feature = "locale-en", error("spot price diverges from TWAP by {divergence_bps} bps, above the limit of {max_divergence_bps} bps"))]
+    PriceDiverged {
+        spot_tick: i32,
+        twap_tick: i32,
+        divergence_bps: U256,
+        max_divergence_bps: u32,
+    },
+}
+
+impl PriceOracleError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            PriceOracleError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            PriceOracleError::GetPoolStateError(_) => "GET_POOL_STATE_ERROR",
+            PriceOracleError::InvalidTwapWindow => "INVALID_TWAP_WINDOW",
+            PriceOracleError::InvalidObservations(_) => "INVALID_OBSERVATIONS",
+            PriceOracleError::InsufficientHistory { .. } => "INSUFFICIENT_HISTORY",
+            PriceOracleError::TickMathError(_) => "TICK_MATH_ERROR",
+            PriceOracleError::PriceDiverged { .. } => "PRICE_DIVERGED",
+        }
+    }
+}
+
+impl PriceOracle {
+    pub fn new(pool: Address, twap_window: u32, max_divergence_bps: u32) -> Self {
+        PriceOracle {
+            pool,
+            twap_window,
+            max_divergence_bps,
+        }
+    }
+
+    /// Read the spot and TWAP prices, failing with `PriceDiverged` when they are
+    /// more than `max_divergence_bps` apart and with `InsufficientHistory` when the
+    /// pool's oldest observation is younger than `twap_window`. Use
+    /// `twap_sqrt_price_x96` as the price.
+    pub async fn price(&self, rpc_url: String) -> Result<OraclePrice, PriceOracleError> {
+        let provider = ProviderBuilder::new()
+            .on_builtin(&rpc_url)
+            .await
+            .map_err(|e| PriceOracleError::InvalidRpcUrl(e.to_string()))?;
+
+        self.price_with_provider(Arc::new(provider)).await
+    }
+
+    /// Same as `price` but reuses an already constructed provider
+    pub async fn price_with_provider<T, P>(
+        &self,
+        client: P,
+    ) -> Result<OraclePrice, PriceOracleError>
+    where
+        T: Transport + Clone,
+        P: Provider<T>,
+    {
+        if self.twap_window == 0 {
+            return Err(PriceOracleError::InvalidTwapWindow);
+        }
+        let state_error =
+            |e: alloy::contract::Error| PriceOracleError::GetPoolStateError(e.to_string());
+        let pool = UNIV3_POOL::new(self.pool, &client);
+        let slot0 = pool.slot0();
+        let block = client.get_block_by_number(BlockNumberOrTag::Latest, false);
+        let (slot0, block) = futures::join!(slot0.call().into_future(), block);
+        let slot0 = slot0.map_err(state_error)?;
+        let now = block
+            .map_err(|e| PriceOracleError::GetPoolStateError(e.to_string()))?
+            .ok_or_else(|| PriceOracleError::GetPoolStateError("latest block".to_string()))?
+            .header
+            .timestamp;
+
+        // 与 OracleLibrary.getOldestObservationSecondsAgo 相同：环形缓冲区的下一个位置
+        // 是最早的观测点，尚未写满时最早的是 0 号
+        let cardinality = slot0.observationCardinality;
+        let next = (slot0.observationIndex as u32 + 1) % cardinality.max(1) as u32;
+        let mut oldest = pool
+            .observations(U256::from(next))
+            .call()
+            .await
+            .map_err(state_error)?;
+        if !oldest.initialized {
+            oldest = pool
+                .observations(U256::ZERO)
+                .call()
+                .await
+                .map_err(state_error)?;
+        }
+        let available = now.saturating_sub(oldest.blockTimestamp as u64);
+        if available < self.twap_window as u64 {
+            // 否则 observe 会以 "OLD" revert
+            return Err(PriceOracleError::InsufficientHistory {
+                available,
+                required: self.twap_window,
+            });
+        }
+
+        let observations = pool
+            .observe(vec![self.twap_window, 0])
+            .call()
+            .await
+            .map_err(state_error)?;
+        let [start, end] = observations.tickCumulatives[..] else {
+            return Err(PriceOracleError::InvalidObservations(
+                observations.tickCumulatives.len(),
+            ));
+        };
+
+        let twap_tick = twap_tick(start.as_i64(), end.as_i64(), self.twap_window);
+        let twap_sqrt_price_x96 = tick_math::get_sqrt_ratio_at_tick(twap_tick)?;
+        let spot_sqrt_price_x96 = U256::from(slot0.sqrtPriceX96);
+        let divergence_bps = divergence_bps(spot_sqrt_price_x96, twap_sqrt_price_x96);
+        if divergence_bps > U256::from(self.max_divergence_bps) {
+            return Err(PriceOracleError::PriceDiverged {
+                spot_tick: slot0.tick.as_i32(),
+                twap_tick,
+                divergence_bps,
+                max_divergence_bps: self.max_divergence_bps,
+            });
+        }
+        Ok(OraclePrice {
+            spot_tick: slot0.tick.as_i32(),
+            spot_sqrt_price_x96,
+            twap_tick,
+            twap_sqrt_price_x96,
+            divergence_bps,
+        })
+    }
+}
+
+/// `OracleLibrary.consult`: the arithmetic mean tick between two
+/// `tickCumulative` observations `window` seconds apart, rounded towards
+/// negative infinity
+pub fn twap_tick(tick_cumulative_start: i64, tick_cumulative_end: i64, window: u32) -> i32 {
+    let delta = tick_cumulative_end - tick_cumulative_start;
+    let window = window as i64;
+    let mut tick = delta / window;
+    if delta < 0 && delta % window != 0 {
+        tick -= 1;
+    }
+    tick as i32
+}
+
+/// How far the price of `sqrt_price_x96` is from the price of `reference_x96`,
+/// in bps of the reference price, rounded down
+pub fn divergence_bps(sqrt_price_x96: U256, reference_x96: U256) -> U256 {
+    // price = sqrtPrice^2，平方最多 320 位，U512 不会溢出
+    let price = U512::from(sqrt_price_x96) * U512::from(sqrt_price_x96);
+    let reference = U512::from(reference_x96) * U512::from(reference_x96);
+    if reference.is_zero() {
+        return U256::MAX;
+    }
+    let diff = price.abs_diff(reference);
+    let bps = diff * U512::from(BPS) / reference;
+    U256::checked_from_limbs_slice(bps.as_limbs()).unwrap_or(U256::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy::{
+        primitives::{
+            aliases::{I24, I56},
+            Bytes, U160,
+        },
+        rpc::types::{Block, Transaction},
+        sol_types::SolCall,
+    };
+
+    use crate::test_utils::MockTransport;
+
+    use super::*;
+
+    #[test]
+    fn test_twap_tick() {
+        assert_eq!(100, twap_tick(0, 1800 * 100, 1800));
+        assert_eq!(-100, twap_tick(1800 * 100, 0, 1800));
+        // 负数向负无穷取整
+        assert_eq!(-2, twap_tick(0, -3, 2));
+        assert_eq!(1, twap_tick(0, 3, 2));
+    }
+
+    #[test]
+    fn test_divergence_bps() {
+        let sqrt_price = tick_math::get_sqrt_ratio_at_tick(0).unwrap();
+        assert_eq!(U256::ZERO, divergence_bps(sqrt_price, sqrt_price));
+        // 100 个 tick 约等于 1.0001^100 - 1 ≈ 100.5 bps
+        let higher = tick_math::get_sqrt_ratio_at_tick(100).unwrap();
+        assert_eq!(U256::from(100), divergence_bps(higher, sqrt_price));
+        let lower = tick_math::get_sqrt_ratio_at_tick(-100).unwrap();
+        assert_eq!(U256::from(99), divergence_bps(lower, sqrt_price));
+    }
+
+    const NOW: u64 = 1_700_000_000;
+
+    /// slot0、最新区块和最早观测点的响应，`history` 是最早观测点距今的秒数
+    fn mock_history(spot_tick: i32, history: u64) -> MockTransport {
+        let spot_sqrt_price = tick_math::get_sqrt_ratio_at_tick(spot_tick).unwrap();
+        // observationIndex 1、observationCardinality 10，下一个位置 2 还没有写入
+        let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+            U160::from(spot_sqrt_price),
+            I24::try_from(spot_tick).unwrap(),
+            1u16,
+            10u16,
+            10u16,
+            0u8,
+            true,
+        ));
+        let mut block = Block::<Transaction>::default();
+        block.header.timestamp = NOW;
+        let observation = |timestamp: u64, initialized: bool| {
+            UNIV3_POOL::observationsCall::abi_encode_returns(&(
+                timestamp as u32,
+                I56::ZERO,
+                U160::ZERO,
+                initialized,
+            ))
+        };
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(Bytes::from(slot0)));
+        transport.push_response(serde_json::to_value(block).unwrap());
+        transport.push_response(serde_json::json!(Bytes::from(observation(0, false))));
+        transport.push_response(serde_json::json!(Bytes::from(observation(
+            NOW - history,
+            true
+        ))));
+        transport
+    }
+
+    fn mock_pool(spot_tick: i32, twap_tick: i32, window: u32) -> MockTransport {
+        // 窗口开始和结束时的 tickCumulative
+        let start = 1_000_000_000i64;
+        let end = start + twap_tick as i64 * window as i64;
+        let observations = UNIV3_POOL::observeCall::abi_encode_returns(&(
+            vec![I56::try_from(start).unwrap(), I56::try_from(end).unwrap()],
+            vec![U160::ZERO, U160::ZERO],
+        ));
+        let transport = mock_history(spot_tick, window as u64 * 2);
+        transport.push_response(serde_json::json!(Bytes::from(observations)));
+        transport
+    }
+
+    fn oracle(max_divergence_bps: u32) -> PriceOracle {
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        PriceOracle::new(pool, 1800, max_divergence_bps)
+    }
+
+    #[tokio::test]
+    async fn test_price_within_threshold() {
+        let transport = mock_pool(200_050, 200_000, 1800);
+        let price = oracle(100)
+            .price_with_provider(transport.provider())
+            .await
+            .unwrap();
+        assert_eq!(200_050, price.spot_tick);
+        assert_eq!(200_000, price.twap_tick);
+        assert_eq!(
+            tick_math::get_sqrt_ratio_at_tick(200_000).unwrap(),
+            price.twap_sqrt_price_x96
+        );
+        assert_eq!(U256::from(50), price.divergence_bps);
+
+        let requests = transport.requests();
+        assert_eq!(5, requests.len());
+        let call_input =
+            |i: usize| Bytes::from_str(requests[i].params[0]["input"].as_str().unwrap()).unwrap();
+        // 先读 (observationIndex + 1) % cardinality，未初始化时退回 0 号
+        let next = UNIV3_POOL::observationsCall::abi_decode(&call_input(2), true).unwrap();
+        assert_eq!(U256::from(2), next.index);
+        let first = UNIV3_POOL::observationsCall::abi_decode(&call_input(3), true).unwrap();
+        assert_eq!(U256::ZERO, first.index);
+        let observe = UNIV3_POOL::observeCall::abi_decode(&call_input(4), true).unwrap();
+        assert_eq!(vec![1800, 0], observe.secondsAgos);
+    }
+
+    #[tokio::test]
+    async fn test_price_diverged() {
+        // spot 被拉高了约 10%
+        let transport = mock_pool(-194_000, -195_000, 1800);
+        let err = oracle(500)
+            .price_with_provider(transport.provider())
+            .await
+            .unwrap_err();
+        match err {
+            PriceOracleError::PriceDiverged {
+                spot_tick,
+                twap_tick,
+                divergence_bps,
+                max_divergence_bps,
+            } => {
+                assert_eq!(-194_000, spot_tick);
+                assert_eq!(-195_000, twap_tick);
+                assert_eq!(U256::from(1051), divergence_bps);
+                assert_eq!(500, max_divergence_bps);
+            }
+            e => panic!("unexpected error {e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insufficient_history() {
+        // 最早的观测点只在 600 秒前，不够 30 分钟的 TWAP
+        let transport = mock_history(200_000, 600);
+        let err = oracle(100)
+            .price_with_provider(transport.provider())
+            .await
+            .unwrap_err();
+        assert_eq!("INSUFFICIENT_HISTORY", err.code());
+        assert!(matches!(
+            err,
+            PriceOracleError::InsufficientHistory {
+                available: 600,
+                required: 1800
+            }
+        ));
+        // 没有调用会 revert 的 observe
+        assert_eq!(4, transport.requests().len());
+    }
+
+    #[tokio::test]
+    async fn test_zero_window() {
+        let transport = MockTransport::default();
+        let oracle = PriceOracle::new(Address::ZERO, 0, 100);
+        assert!(matches!(
+            oracle.price_with_provider(transport.provider()).await,
+            Err(PriceOracleError::InvalidTwapWindow)
+        ));
+        assert!(transport.requests().is_empty());
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use std::str::FromStr;
+
+    use crate::test_utils::spawn_mainnet_fork;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_price_on_fork() {
+        let fork = spawn_mainnet_fork();
+        // USDC/WETH 0.05%，30 分钟 TWAP
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let price = PriceOracle::new(pool, 1800, 200)
+            .price(fork.endpoint())
+            .await
+            .unwrap();
+        assert!(price.twap_sqrt_price_x96 > U256::ZERO);
+        assert!((price.spot_tick - price.twap_tick).abs() < 200);
+    }
+}
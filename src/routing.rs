@@ -0,0 +1,661 @@
+use std::{collections::HashSet, sync::Arc};
+
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    providers::{Provider, ProviderBuilder},
+    transports::Transport,
+};
+use anyhow::Result;
+use futures::future::join_all;
+
+use crate::{
+    client::UniswapSupportChain,
+    config::ContractAddresses,
+    multicall::get_pool_states_with_provider,
+    tokens,
+    uniswapv3_factory::{
+        self, GetPoolParam, PoolWithState, UniswapV3FactoryCommand, UniswapV3FactoryError,
+        UniswapV3FactoryResult,
+    },
+    uniswapv3_quoter::{
+        self, QuoteExactInputParams, QuoteExactInputSingleParams, UniswapV3QuoterCommand,
+    },
+    unswapv3_pool::UniswapPoolFee,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    /// token_in, 中间 token, token_out
+    pub tokens: Vec<Address>,
+    /// Fee tier of each hop, always `tokens.len() - 1` entries
+    pub fees: Vec<UniswapPoolFee>,
+    /// Quoted amount out for the amount in the route was searched with
+    pub amount_out: U256,
+}
+
+impl Route {
+    pub fn is_direct(&self) -> bool {
+        self.fees.len() == 1
+    }
+
+    /// The path in the format expected by `exactInput` and `quoteExactInput`
+    pub fn path(&self) -> Result<Bytes, RoutingError> {
+        encode_path(&self.tokens, &self.fees)
+    }
+
+    /// The path in the format expected by `exactOutput` and `quoteExactOutput`
+    pub fn reversed_path(&self) -> Result<Bytes, RoutingError> {
+        encode_reversed_path(&self.tokens, &self.fees)
+    }
+}
+
+/// An existing pool between the scanned token and one of the counter-tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenPool {
+    pub counter_token: Address,
+    pub fee: UniswapPoolFee,
+    pub pool: PoolWithState,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoutingError {
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("路径格式不正确，token 数量{0}，fee 数量{1}")
+    )]
+    #[cfg_attr(feature = "locale-en", error("invalid path, {0} tokens and {1} fees"))]
+    InvalidPath(usize, usize),
+    #[cfg_attr(not(feature = "locale-en"), error("查询池子失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to look up pool: {0}"))]
+    GetPoolError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("找不到可用的兑换路径"))]
+    #[cfg_attr(feature = "locale-en", error("no route found"))]
+    NoRoute,
+    #[cfg_attr(not(feature = "locale-en"), error("编码后的路径长度不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid encoded path length: {0}"))]
+    InvalidEncodedPath(usize),
+    #[cfg_attr(not(feature = "locale-en"), error("路径中的手续费不支持{0}"))]
+    #[cfg_attr(feature = "locale-en", error("unsupported fee in path: {0}"))]
+    InvalidFee(u32),
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(not(feature = "locale-en"), error("不支持的链{0}"))]
+    #[cfg_attr(feature = "locale-en", error("unsupported chain: {0}"))]
+    UnsupportedChain(String),
+}
+
+impl RoutingError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            RoutingError::InvalidPath(..) => "INVALID_PATH",
+            RoutingError::GetPoolError(_) => "GET_POOL_ERROR",
+            RoutingError::NoRoute => "NO_ROUTE",
+            RoutingError::InvalidEncodedPath(_) => "INVALID_ENCODED_PATH",
+            RoutingError::InvalidFee(_) => "INVALID_FEE",
+            RoutingError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            RoutingError::UnsupportedChain(_) => "UNSUPPORTED_CHAIN",
+        }
+    }
+}
+
+/// Encode a swap path as `token (20 bytes) | fee (3 bytes) | token | ...`
+pub fn encode_path(tokens: &[Address], fees: &[UniswapPoolFee]) -> Result<Bytes, RoutingError> {
+    if tokens.len() < 2 || tokens.len() != fees.len() + 1 {
+        return Err(RoutingError::InvalidPath(tokens.len(), fees.len()));
+    }
+    let mut path = Vec::with_capacity(20 * tokens.len() + 3 * fees.len());
+    for (token, fee) in tokens.iter().zip(fees) {
+        path.extend_from_slice(token.as_slice());
+        path.extend_from_slice(&fee.as_u32().to_be_bytes()[1..]);
+    }
+    path.extend_from_slice(tokens[tokens.len() - 1].as_slice());
+    Ok(path.into())
+}
+
+/// Encode the path of a route given from token_in to token_out in reverse, starting
+/// at token_out, as exact output swaps walk the pools backwards
+pub fn encode_reversed_path(
+    tokens: &[Address],
+    fees: &[UniswapPoolFee],
+) -> Result<Bytes, RoutingError> {
+    let tokens: Vec<Address> = tokens.iter().rev().copied().collect();
+    let fees: Vec<UniswapPoolFee> = fees.iter().rev().copied().collect();
+    encode_path(&tokens, &fees)
+}
+
+/// Split an encoded path back into its tokens and fee tiers
+pub fn decode_path(path: &[u8]) -> Result<(Vec<Address>, Vec<UniswapPoolFee>), RoutingError> {
+    let hops = path.len().saturating_sub(20) / 23;
+    if path.len() < 43 || path.len() != 20 + 23 * hops {
+        return Err(RoutingError::InvalidEncodedPath(path.len()));
+    }
+    let mut tokens = Vec::with_capacity(hops + 1);
+    let mut fees = Vec::with_capacity(hops);
+    for hop in path[..23 * hops].chunks(23) {
+        tokens.push(Address::from_slice(&hop[..20]));
+        let fee = u32::from_be_bytes([0, hop[20], hop[21], hop[22]]);
+        fees.push(UniswapPoolFee::from_u32(fee).ok_or(RoutingError::InvalidFee(fee))?);
+    }
+    tokens.push(Address::from_slice(&path[23 * hops..]));
+    Ok((tokens, fees))
+}
+
+/// Tokens tried as the intermediate hop when there is no good direct pool
+pub fn base_tokens(chain: &UniswapSupportChain) -> Vec<Address> {
+    vec![
+        tokens::weth(chain),
+        tokens::usdc(chain),
+        tokens::usdt(chain),
+    ]
+}
+
+/// The best route from `token_in` to `token_out`, see `quote_all_routes`
+pub async fn find_route(
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    chain: &UniswapSupportChain,
+) -> Result<Route, RoutingError> {
+    quote_all_routes(token_in, token_out, amount_in, chain).await
+}
+
+/// Find the route with the best quoted output for swapping `amount_in` of `token_in`
+/// into `token_out`. Every direct pool is quoted with `quoteExactInputSingle` and
+/// every single-hop route through the chain's base tokens with `quoteExactInput`;
+/// routes whose quote reverts are skipped. Chains without the original quoter
+/// fail with `UnsupportedChain`, see `ContractAddresses::periphery_for_chain`.
+pub async fn quote_all_routes(
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    chain: &UniswapSupportChain,
+) -> Result<Route, RoutingError> {
+    let addresses = ContractAddresses::periphery_for_chain(chain)
+        .map_err(|e| RoutingError::UnsupportedChain(e.to_string()))?;
+    let provider = ProviderBuilder::new()
+        .on_builtin(&chain.get_rpc_url())
+        .await
+        .map_err(|e| RoutingError::InvalidRpcUrl(e.to_string()))?;
+
+    quote_all_routes_with_provider(
+        token_in,
+        token_out,
+        amount_in,
+        &base_tokens(chain),
+        Arc::new(provider),
+        &addresses,
+    )
+    .await
+}
+
+/// Same as `quote_all_routes` but reuses an already constructed provider and
+/// routes through `bases` against the contracts in `addresses`
+pub async fn quote_all_routes_with_provider<T, P>(
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    bases: &[Address],
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<Route, RoutingError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let bases: Vec<Address> = bases
+        .iter()
+        .copied()
+        .filter(|base| *base != token_in && *base != token_out)
+        .collect();
+
+    // 先查询所有可能用到的池子是否存在
+    let mut pairs = vec![(token_in, token_out)];
+    for base in &bases {
+        pairs.push((token_in, *base));
+        pairs.push((*base, token_out));
+    }
+    let lookups: Vec<(Address, Address, UniswapPoolFee)> = pairs
+        .iter()
+        .flat_map(|&(a, b)| {
+            UniswapPoolFee::all()
+                .into_iter()
+                .map(move |fee| (a, b, fee))
+        })
+        .collect();
+    let exists = join_all(lookups.iter().map(|&(a, b, fee)| {
+        let command = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a: a,
+            token_b: b,
+            fee: fee.as_u32(),
+        });
+        uniswapv3_factory::execute_with_provider(command, &client, addresses)
+    }))
+    .await;
+    let mut pools = HashSet::new();
+    for (pool, exists) in lookups.into_iter().zip(exists) {
+        match exists {
+            Ok(_) => {
+                pools.insert(pool);
+            }
+            Err(UniswapV3FactoryError::PoolNotFound(..)) => {}
+            Err(e) => return Err(RoutingError::GetPoolError(e.to_string())),
+        }
+    }
+
+    let mut candidates = vec![];
+    for fee in UniswapPoolFee::all() {
+        if pools.contains(&(token_in, token_out, fee)) {
+            candidates.push((vec![token_in, token_out], vec![fee]));
+        }
+    }
+    for base in bases {
+        for fee_in in UniswapPoolFee::all() {
+            if !pools.contains(&(token_in, base, fee_in)) {
+                continue;
+            }
+            for fee_out in UniswapPoolFee::all() {
+                if pools.contains(&(base, token_out, fee_out)) {
+                    candidates.push((vec![token_in, base, token_out], vec![fee_in, fee_out]));
+                }
+            }
+        }
+    }
+
+    let quotes = join_all(
+        candidates
+            .iter()
+            .map(|(tokens, fees)| quote_route(tokens, fees, amount_in, &client, addresses)),
+    )
+    .await;
+    candidates
+        .into_iter()
+        .zip(quotes)
+        .filter_map(|((tokens, fees), amount_out)| {
+            amount_out.map(|amount_out| Route {
+                tokens,
+                fees,
+                amount_out,
+            })
+        })
+        .max_by_key(|route| route.amount_out)
+        .ok_or(RoutingError::NoRoute)
+}
+
+/// Quote one candidate route, `None` when the quote fails
+async fn quote_route<T, P>(
+    tokens: &[Address],
+    fees: &[UniswapPoolFee],
+    amount_in: U256,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Option<U256>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let command = if let ([token_in, token_out], [fee]) = (tokens, fees) {
+        UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in: *token_in,
+            token_out: *token_out,
+            fee: *fee,
+            amount_in,
+            sqrt_price_limit_x96: U256::ZERO,
+        })
+    } else {
+        let path = encode_path(tokens, fees).ok()?;
+        UniswapV3QuoterCommand::QuoteExactInput(QuoteExactInputParams { path, amount_in })
+    };
+    uniswapv3_quoter::execute_with_provider(command, client, addresses)
+        .await
+        .ok()?
+        .as_input()
+}
+
+/// Every Uniswap V3 pool between `token` and one of `counter_tokens`, over all fee
+/// tiers, with its current price and liquidity. Pairs without a pool are left out.
+pub async fn pools_for_token(
+    token: Address,
+    counter_tokens: &[Address],
+    rpc_url: String,
+) -> Result<Vec<TokenPool>, RoutingError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| RoutingError::InvalidRpcUrl(e.to_string()))?;
+
+    pools_for_token_with_provider(
+        token,
+        counter_tokens,
+        Arc::new(provider),
+        &ContractAddresses::default(),
+    )
+    .await
+}
+
+/// Same as `pools_for_token` but reuses an already constructed provider and
+/// looks pools up in `addresses.factory`
+pub async fn pools_for_token_with_provider<T, P>(
+    token: Address,
+    counter_tokens: &[Address],
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<Vec<TokenPool>, RoutingError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let lookups: Vec<(Address, UniswapPoolFee)> = counter_tokens
+        .iter()
+        .filter(|&&counter| counter != token)
+        .flat_map(|&counter| {
+            UniswapPoolFee::all()
+                .into_iter()
+                .map(move |fee| (counter, fee))
+        })
+        .collect();
+    // 并发查询每个 fee tier 的池子地址
+    let results = join_all(lookups.iter().map(|&(counter, fee)| {
+        let command = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a: token,
+            token_b: counter,
+            fee: fee.as_u32(),
+        });
+        uniswapv3_factory::execute_with_provider(command, &client, addresses)
+    }))
+    .await;
+    let mut found = vec![];
+    for (lookup, res) in lookups.into_iter().zip(results) {
+        match res {
+            Ok(UniswapV3FactoryResult::GetPool(address)) => found.push((lookup, address)),
+            Err(UniswapV3FactoryError::PoolNotFound(..)) => {}
+            Ok(_) => {
+                return Err(RoutingError::GetPoolError(
+                    UniswapV3FactoryError::InvalidCommand.to_string(),
+                ))
+            }
+            Err(e) => return Err(RoutingError::GetPoolError(e.to_string())),
+        }
+    }
+    if found.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // 已存在的池子通过一次 Multicall3 读取价格和流动性
+    let pools: Vec<Address> = found.iter().map(|&(_, address)| address).collect();
+    let states = get_pool_states_with_provider(&pools, &client)
+        .await
+        .map_err(|e| RoutingError::GetPoolError(e.to_string()))?;
+    Ok(found
+        .into_iter()
+        .zip(states)
+        // 读取失败的池子跳过
+        .filter(|(_, state)| state.success)
+        .map(|(((counter_token, fee), _), state)| TokenPool {
+            counter_token,
+            fee,
+            pool: PoolWithState {
+                address: state.address,
+                exists: true,
+                sqrt_price_x96: state.sqrt_price_x96,
+                tick: state.tick,
+                liquidity: state.liquidity,
+            },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy::hex;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_path() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let path = encode_path(&[weth, usdc], &[UniswapPoolFee::Fee500]).unwrap();
+        assert_eq!(
+            "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc20001f4a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            hex::encode(path)
+        );
+        assert!(encode_path(&[weth], &[]).is_err());
+        assert!(encode_path(&[weth, usdc], &[]).is_err());
+    }
+
+    #[test]
+    fn test_reversed_path_round_trip() {
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let ethc = Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap();
+        let route = Route {
+            tokens: vec![usdt, weth, ethc],
+            fees: vec![UniswapPoolFee::Fee500, UniswapPoolFee::Fee10000],
+            amount_out: U256::ZERO,
+        };
+        let (tokens, fees) = decode_path(&route.path().unwrap()).unwrap();
+        assert_eq!(route.tokens, tokens);
+        assert_eq!(route.fees, fees);
+
+        let (tokens, fees) = decode_path(&route.reversed_path().unwrap()).unwrap();
+        assert_eq!(vec![ethc, weth, usdt], tokens);
+        assert_eq!(vec![UniswapPoolFee::Fee10000, UniswapPoolFee::Fee500], fees);
+
+        assert!(matches!(
+            decode_path(&route.path().unwrap()[..42]),
+            Err(RoutingError::InvalidEncodedPath(42))
+        ));
+
+        // PancakeSwap 的 2500 档位不是 Uniswap 的手续费
+        let mut path = route.path().unwrap().to_vec();
+        path[20..23].copy_from_slice(&2500u32.to_be_bytes()[1..]);
+        assert!(matches!(
+            decode_path(&path),
+            Err(RoutingError::InvalidFee(2500))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quote_all_routes_unsupported_chain() {
+        let chain = UniswapSupportChain::Base;
+        // Base 只有 QuoterV2，在发送请求之前就拒绝
+        let err = quote_all_routes(
+            tokens::weth(&chain),
+            tokens::usdc(&chain),
+            U256::from(10u128.pow(18)),
+            &chain,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!("UNSUPPORTED_CHAIN", err.code());
+        assert!(err.to_string().contains("8453"));
+    }
+
+    #[tokio::test]
+    async fn test_multi_hop_beats_missing_direct_pool() {
+        use alloy::primitives::B256;
+
+        use crate::test_utils::MockTransport;
+
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let ethc = Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap();
+        let pool = |n: u8| Address::repeat_byte(n);
+
+        let transport = MockTransport::default();
+        // 按 UniswapPoolFee::all()（10000、3000、500、100）的顺序：USDT/ETHC 没有池子，
+        // USDT/WETH 有 0.3% 和 0.05%，WETH/ETHC 只有 1%
+        for address in [
+            Address::ZERO,
+            Address::ZERO,
+            Address::ZERO,
+            Address::ZERO,
+            Address::ZERO,
+            pool(1),
+            pool(2),
+            Address::ZERO,
+            pool(3),
+            Address::ZERO,
+            Address::ZERO,
+            Address::ZERO,
+        ] {
+            transport.push_response(serde_json::json!(address.into_word()));
+        }
+        transport.push_response(serde_json::json!(B256::from(U256::from(100))));
+        transport.push_response(serde_json::json!(B256::from(U256::from(120))));
+
+        let route = quote_all_routes_with_provider(
+            usdt,
+            ethc,
+            U256::from(1_000_000),
+            &[weth],
+            transport.provider(),
+            &ContractAddresses::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(14, transport.requests().len());
+        assert!(!route.is_direct());
+        assert_eq!(vec![usdt, weth, ethc], route.tokens);
+        assert_eq!(
+            vec![UniswapPoolFee::Fee500, UniswapPoolFee::Fee10000],
+            route.fees
+        );
+        assert_eq!(U256::from(120), route.amount_out);
+    }
+
+    #[tokio::test]
+    async fn test_pools_for_token() {
+        use alloy::{
+            primitives::{aliases::I24, U160},
+            sol_types::SolCall,
+        };
+
+        use crate::{
+            multicall::{Multicall3, MULTICALL3},
+            test_utils::MockTransport,
+            unswapv3_pool::UNIV3_POOL,
+        };
+
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let usdc_500 = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let usdt_3000 = Address::from_str("0x4e68Ccd3E89f51C3074ca5072bbAC773960dFa36").unwrap();
+
+        let transport = MockTransport::default();
+        // 按 counter token、UniswapPoolFee::all() 的顺序回复 getPool，只有两个池子存在
+        for address in [
+            Address::ZERO,
+            Address::ZERO,
+            usdc_500,
+            Address::ZERO,
+            Address::ZERO,
+            usdt_3000,
+            Address::ZERO,
+            Address::ZERO,
+        ] {
+            transport.push_response(serde_json::json!(address.into_word()));
+        }
+        let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+            U160::from(1u64 << 32),
+            I24::try_from(-200_000).unwrap(),
+            0u16,
+            1u16,
+            1u16,
+            0u8,
+            true,
+        ));
+        let result = |liquidity: u128| {
+            [
+                Multicall3::Result {
+                    success: true,
+                    returnData: slot0.clone().into(),
+                },
+                Multicall3::Result {
+                    success: true,
+                    returnData: UNIV3_POOL::liquidityCall::abi_encode_returns(&(liquidity,)).into(),
+                },
+            ]
+        };
+        let output = MULTICALL3::aggregate3Call::abi_encode_returns(&(result(1000)
+            .into_iter()
+            .chain(result(2000))
+            .collect::<Vec<_>>(),));
+        transport.push_response(serde_json::json!(Bytes::from(output)));
+
+        let pools = pools_for_token_with_provider(
+            weth,
+            &[usdc, weth, usdt],
+            transport.provider(),
+            &ContractAddresses::default(),
+        )
+        .await
+        .unwrap();
+        // WETH 自身被跳过：8 次 getPool 和 1 次 multicall
+        assert_eq!(9, transport.requests().len());
+        assert_eq!(2, pools.len());
+        assert_eq!(usdc, pools[0].counter_token);
+        assert_eq!(UniswapPoolFee::Fee500, pools[0].fee);
+        assert_eq!(usdc_500, pools[0].pool.address);
+        assert_eq!(1000, pools[0].pool.liquidity);
+        assert_eq!(usdt, pools[1].counter_token);
+        assert_eq!(UniswapPoolFee::Fee3000, pools[1].fee);
+        assert_eq!(usdt_3000, pools[1].pool.address);
+        assert_eq!(2000, pools[1].pool.liquidity);
+        assert_eq!(-200_000, pools[1].pool.tick);
+        assert!(pools.iter().all(|pool| pool.pool.exists));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use std::str::FromStr;
+
+    use crate::utils::from_readable_amount;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_find_route_through_weth() {
+        // USDT 和 ETHC 之间没有直接的池子，只能通过 WETH 兑换
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let ethc = Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap();
+        let chain = UniswapSupportChain::Ethereum;
+        let route = find_route(usdt, ethc, from_readable_amount(100.0, 6), &chain)
+            .await
+            .unwrap();
+        assert!(!route.is_direct());
+        assert_eq!(vec![usdt, tokens::weth(&chain), ethc], route.tokens);
+        assert!(route.amount_out > U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_pools_for_token_weth_on_fork() {
+        let fork = crate::test_utils::spawn_mainnet_fork();
+        let chain = UniswapSupportChain::Ethereum;
+        let weth = tokens::weth(&chain);
+        let usdc = tokens::usdc(&chain);
+        let usdt = tokens::usdt(&chain);
+        let pools = pools_for_token(weth, &[usdc, usdt], fork.endpoint())
+            .await
+            .unwrap();
+        // USDC/WETH 和 WETH/USDT 的 0.05% 池子
+        assert!(pools.iter().any(|pool| pool.counter_token == usdc
+            && pool.fee == UniswapPoolFee::Fee500
+            && pool.pool.address
+                == Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap()));
+        assert!(pools.iter().any(|pool| pool.counter_token == usdt
+            && pool.fee == UniswapPoolFee::Fee500
+            && pool.pool.address
+                == Address::from_str("0x11b815efB8f581194ae79006d24E0d814B7697F6").unwrap()));
+        for pool in &pools {
+            assert!(pool.pool.exists);
+            assert!(pool.pool.sqrt_price_x96 > U256::ZERO);
+        }
+    }
+}
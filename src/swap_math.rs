@@ -0,0 +1,298 @@
+//! Port of the core `SqrtPriceMath` and `SwapMath` libraries, limited to exact
+//! input swaps
+
+use alloy::primitives::U256;
+
+use crate::uniswapv3_math::{mul_div, mul_div_rounding_up, Q96};
+
+/// Pool fees are expressed in hundredths of a bip
+const FEE_DENOMINATOR: u32 = 1_000_000;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum SwapMathError {
+    #[cfg_attr(not(feature = "locale-en"), error("价格和流动性必须大于零"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("price and liquidity must be greater than zero")
+    )]
+    ZeroPriceOrLiquidity,
+    #[cfg_attr(not(feature = "locale-en"), error("计算结果溢出"))]
+    #[cfg_attr(feature = "locale-en", error("arithmetic overflow"))]
+    Overflow,
+}
+
+impl SwapMathError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            SwapMathError::ZeroPriceOrLiquidity => "ZERO_PRICE_OR_LIQUIDITY",
+            SwapMathError::Overflow => "OVERFLOW",
+        }
+    }
+}
+
+fn div_rounding_up(a: U256, b: U256) -> U256 {
+    let quotient = a / b;
+    if (a % b).is_zero() {
+        quotient
+    } else {
+        quotient + U256::from(1)
+    }
+}
+
+fn sorted(a: U256, b: U256) -> (U256, U256) {
+    if a > b {
+        (b, a)
+    } else {
+        (a, b)
+    }
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount0RoundingUp` for adding `amount` of token0
+fn next_sqrt_price_from_amount0_add(sqrt_price_x96: U256, liquidity: u128, amount: U256) -> U256 {
+    if amount.is_zero() {
+        return sqrt_price_x96;
+    }
+    let numerator1: U256 = U256::from(liquidity) << 96;
+    if let Some(product) = amount.checked_mul(sqrt_price_x96) {
+        if let Some(denominator) = numerator1.checked_add(product) {
+            if let Some(price) = mul_div_rounding_up(numerator1, sqrt_price_x96, denominator) {
+                return price;
+            }
+        }
+    }
+    div_rounding_up(numerator1, numerator1 / sqrt_price_x96 + amount)
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount1RoundingDown` for adding `amount` of token1
+fn next_sqrt_price_from_amount1_add(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount: U256,
+) -> Result<U256, SwapMathError> {
+    let quotient = mul_div(amount, Q96, U256::from(liquidity)).ok_or(SwapMathError::Overflow)?;
+    sqrt_price_x96
+        .checked_add(quotient)
+        .ok_or(SwapMathError::Overflow)
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromInput`
+pub fn get_next_sqrt_price_from_input(
+    sqrt_price_x96: U256,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> Result<U256, SwapMathError> {
+    if sqrt_price_x96.is_zero() || liquidity == 0 {
+        return Err(SwapMathError::ZeroPriceOrLiquidity);
+    }
+    if zero_for_one {
+        Ok(next_sqrt_price_from_amount0_add(
+            sqrt_price_x96,
+            liquidity,
+            amount_in,
+        ))
+    } else {
+        next_sqrt_price_from_amount1_add(sqrt_price_x96, liquidity, amount_in)
+    }
+}
+
+/// `SqrtPriceMath.getAmount0Delta`: token0 needed to move between the two prices
+pub fn get_amount0_delta(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<U256, SwapMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    if a.is_zero() {
+        return Err(SwapMathError::ZeroPriceOrLiquidity);
+    }
+    let numerator1: U256 = U256::from(liquidity) << 96;
+    let numerator2 = b - a;
+    if round_up {
+        let amount =
+            mul_div_rounding_up(numerator1, numerator2, b).ok_or(SwapMathError::Overflow)?;
+        Ok(div_rounding_up(amount, a))
+    } else {
+        Ok(mul_div(numerator1, numerator2, b).ok_or(SwapMathError::Overflow)? / a)
+    }
+}
+
+/// `SqrtPriceMath.getAmount1Delta`: token1 needed to move between the two prices
+pub fn get_amount1_delta(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<U256, SwapMathError> {
+    let (a, b) = sorted(sqrt_ratio_a_x96, sqrt_ratio_b_x96);
+    let amount = if round_up {
+        mul_div_rounding_up(U256::from(liquidity), b - a, Q96)
+    } else {
+        mul_div(U256::from(liquidity), b - a, Q96)
+    };
+    amount.ok_or(SwapMathError::Overflow)
+}
+
+/// Result of swapping within a single tick range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next_x96: U256,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+/// `SwapMath.computeSwapStep` for an exact input of `amount_remaining`. The swap
+/// direction follows from the target price, `fee_pips` is the pool fee (3000 = 0.3%).
+pub fn compute_swap_step_exact_in(
+    sqrt_ratio_current_x96: U256,
+    sqrt_ratio_target_x96: U256,
+    liquidity: u128,
+    amount_remaining: U256,
+    fee_pips: u32,
+) -> Result<SwapStep, SwapMathError> {
+    let zero_for_one = sqrt_ratio_current_x96 >= sqrt_ratio_target_x96;
+    let fee = U256::from(fee_pips);
+    let fee_complement = U256::from(FEE_DENOMINATOR - fee_pips);
+
+    let amount_remaining_less_fee = mul_div(
+        amount_remaining,
+        fee_complement,
+        U256::from(FEE_DENOMINATOR),
+    )
+    .ok_or(SwapMathError::Overflow)?;
+    let amount_to_target = if zero_for_one {
+        get_amount0_delta(
+            sqrt_ratio_target_x96,
+            sqrt_ratio_current_x96,
+            liquidity,
+            true,
+        )?
+    } else {
+        get_amount1_delta(
+            sqrt_ratio_current_x96,
+            sqrt_ratio_target_x96,
+            liquidity,
+            true,
+        )?
+    };
+    let sqrt_price_next_x96 = if amount_remaining_less_fee >= amount_to_target {
+        sqrt_ratio_target_x96
+    } else {
+        get_next_sqrt_price_from_input(
+            sqrt_ratio_current_x96,
+            liquidity,
+            amount_remaining_less_fee,
+            zero_for_one,
+        )?
+    };
+
+    let reached_target = sqrt_price_next_x96 == sqrt_ratio_target_x96;
+    let (amount_in, amount_out) = if zero_for_one {
+        (
+            if reached_target {
+                amount_to_target
+            } else {
+                get_amount0_delta(sqrt_price_next_x96, sqrt_ratio_current_x96, liquidity, true)?
+            },
+            get_amount1_delta(
+                sqrt_price_next_x96,
+                sqrt_ratio_current_x96,
+                liquidity,
+                false,
+            )?,
+        )
+    } else {
+        (
+            if reached_target {
+                amount_to_target
+            } else {
+                get_amount1_delta(sqrt_ratio_current_x96, sqrt_price_next_x96, liquidity, true)?
+            },
+            get_amount0_delta(
+                sqrt_ratio_current_x96,
+                sqrt_price_next_x96,
+                liquidity,
+                false,
+            )?,
+        )
+    };
+    // 没有到达目标价格时剩余的输入全部作为手续费
+    let fee_amount = if reached_target {
+        mul_div_rounding_up(amount_in, fee, fee_complement).ok_or(SwapMathError::Overflow)?
+    } else {
+        amount_remaining - amount_in
+    };
+    Ok(SwapStep {
+        sqrt_price_next_x96,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::uint;
+
+    use super::*;
+
+    // 与 v3-core SwapMath.spec.ts 相同的输入
+    const PRICE_1_1: U256 = uint!(79228162514264337593543950336_U256);
+    const PRICE_101_100: U256 = uint!(79623317895830914510639640423_U256);
+    const PRICE_1000_100: U256 = uint!(250541448375047931186413801569_U256);
+    const ONE: U256 = uint!(1000000000000000000_U256);
+
+    #[test]
+    fn test_exact_in_capped_at_target() {
+        let step =
+            compute_swap_step_exact_in(PRICE_1_1, PRICE_101_100, 2 * 10u128.pow(18), ONE, 600)
+                .unwrap();
+        assert_eq!(PRICE_101_100, step.sqrt_price_next_x96);
+        assert_eq!(uint!(9975124224178055_U256), step.amount_in);
+        assert_eq!(uint!(9925619580021728_U256), step.amount_out);
+        assert_eq!(uint!(5988667735148_U256), step.fee_amount);
+    }
+
+    #[test]
+    fn test_exact_in_fully_spent() {
+        let step =
+            compute_swap_step_exact_in(PRICE_1_1, PRICE_1000_100, 2 * 10u128.pow(18), ONE, 600)
+                .unwrap();
+        assert!(step.sqrt_price_next_x96 < PRICE_1000_100);
+        assert_eq!(
+            uint!(118818475322642227089037862318_U256),
+            step.sqrt_price_next_x96
+        );
+        assert_eq!(uint!(999400000000000000_U256), step.amount_in);
+        assert_eq!(uint!(666399946655997866_U256), step.amount_out);
+        assert_eq!(uint!(600000000000000_U256), step.fee_amount);
+        assert_eq!(ONE, step.amount_in + step.fee_amount);
+    }
+
+    #[test]
+    fn test_entire_input_taken_as_fee() {
+        let step = compute_swap_step_exact_in(
+            U256::from(2413),
+            uint!(79887613182836312_U256),
+            1985041575832132834610021537970,
+            U256::from(10),
+            1872,
+        )
+        .unwrap();
+        assert_eq!(U256::from(2413), step.sqrt_price_next_x96);
+        assert_eq!(U256::ZERO, step.amount_in);
+        assert_eq!(U256::ZERO, step.amount_out);
+        assert_eq!(U256::from(10), step.fee_amount);
+    }
+
+    #[test]
+    fn test_zero_liquidity() {
+        assert_eq!(
+            Err(SwapMathError::ZeroPriceOrLiquidity),
+            get_next_sqrt_price_from_input(PRICE_1_1, 0, ONE, true)
+        );
+    }
+}
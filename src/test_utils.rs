@@ -0,0 +1,200 @@
+//! Helpers shared by the `tests` and `live_tests` modules
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+#[cfg(feature = "live-tests")]
+use alloy::node_bindings::{Anvil, AnvilInstance};
+use alloy::{
+    providers::{ProviderBuilder, RootProvider},
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload},
+        types::{Block, Transaction},
+    },
+    transports::{TransportError, TransportFut},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Public RPC the forks are created from
+#[cfg(feature = "live-tests")]
+pub const MAINNET_RPC_URL: &str = "https://eth.llamarpc.com";
+
+/// Every fork starts from this block so the on-chain state, and therefore every quote, is reproducible
+#[cfg(feature = "live-tests")]
+pub const FORK_BLOCK_NUMBER: u64 = 21_000_000;
+
+/// Spawn a local `anvil` node forked from mainnet at `FORK_BLOCK_NUMBER`.
+/// Requires the `anvil` binary in `PATH`; the node is killed when the instance is dropped.
+#[cfg(feature = "live-tests")]
+pub fn spawn_mainnet_fork() -> AnvilInstance {
+    Anvil::new()
+        .fork(MAINNET_RPC_URL)
+        .fork_block_number(FORK_BLOCK_NUMBER)
+        .spawn()
+}
+
+/// A JSON-RPC request seen by `MockTransport`
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub params: serde_json::Value,
+}
+
+/// In-memory transport answering every request with the next queued result,
+/// in order, and recording the requests so tests can run without a node
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    responses: Arc<Mutex<VecDeque<serde_json::Value>>>,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    delay: Duration,
+}
+
+impl MockTransport {
+    /// Answer every request only after `delay`, to simulate a slow node
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn push_response(&self, result: serde_json::Value) {
+        self.responses.lock().unwrap().push_back(result);
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn provider(&self) -> RootProvider<Self> {
+        ProviderBuilder::new().on_client(RpcClient::new(self.clone(), true))
+    }
+}
+
+impl tower::Service<RequestPacket> for MockTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let RequestPacket::Single(req) = req else {
+            panic!("MockTransport 不支持批量请求");
+        };
+        let params = req
+            .params()
+            .map(|params| serde_json::from_str(params.get()).unwrap())
+            .unwrap_or_default();
+        self.requests.lock().unwrap().push(MockRequest {
+            method: req.method().to_string(),
+            params,
+        });
+        let result = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| panic!("没有为 {} 预设响应", req.method()));
+        let response = Response {
+            id: req.id().clone(),
+            payload: ResponsePayload::Success(serde_json::value::to_raw_value(&result).unwrap()),
+        };
+        let delay = self.delay;
+        Box::pin(async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            Ok(ResponsePacket::Single(response))
+        })
+    }
+}
+
+/// Start a local HTTP JSON-RPC endpoint that answers `eth_chainId` with
+/// `chain_id` and `eth_getBlockByNumber` with an empty block stamped with the
+/// current time, for code paths that take a `rpc_url` instead of a provider.
+/// Returns the URL to connect to.
+pub async fn spawn_stub_rpc(chain_id: u64) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve_stub_rpc(stream, chain_id));
+        }
+    });
+    url
+}
+
+async fn serve_stub_rpc(mut stream: TcpStream, chain_id: u64) {
+    let mut buf = Vec::new();
+    // 同一连接上可能有多个请求（keep-alive）
+    loop {
+        let body = loop {
+            if let Some((header_end, len)) = parse_http_request(&buf) {
+                if buf.len() >= header_end + len {
+                    let body = buf[header_end..header_end + len].to_vec();
+                    buf.drain(..header_end + len);
+                    break body;
+                }
+            }
+            let mut chunk = [0u8; 4096];
+            match stream.read(&mut chunk).await {
+                Ok(0) | Err(_) => return,
+                Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            }
+        };
+        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let result = match request["method"].as_str() {
+            Some("eth_chainId") => Some(serde_json::json!(format!("0x{:x}", chain_id))),
+            Some("eth_getBlockByNumber") => {
+                let mut block = Block::<Transaction>::default();
+                block.header.timestamp = chrono::Utc::now().timestamp() as u64;
+                Some(serde_json::to_value(block).unwrap())
+            }
+            _ => None,
+        };
+        let response = if let Some(result) = result {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": result,
+            })
+        } else {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "error": { "code": -32601, "message": "method not found" },
+            })
+        }
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        if stream.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// The end of the headers and the `Content-Length` once the headers are complete
+fn parse_http_request(buf: &[u8]) -> Option<(usize, usize)> {
+    let header_end = buf.windows(4).position(|w| w == b"\r\n\r\n")? + 4;
+    let headers = std::str::from_utf8(&buf[..header_end]).ok()?;
+    let len = headers
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    Some((header_end, len))
+}
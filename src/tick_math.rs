@@ -0,0 +1,187 @@
+//! Port of the core `TickMath` library, bit for bit identical to the on-chain version
+
+use alloy::primitives::{uint, I256, U256};
+
+use crate::uniswapv3_math::{MAX_SQRT_RATIO, MIN_SQRT_RATIO};
+
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = 887272;
+
+/// `2^128 / sqrt(1.0001)^(2^i)` in Q128.128 for i = 1..=19, bit 0 is handled separately
+const RATIOS: [U256; 19] = [
+    uint!(0xfff97272373d413259a46990580e213a_U256),
+    uint!(0xfff2e50f5f656932ef12357cf3c7fdcc_U256),
+    uint!(0xffe5caca7e10e4e61c3624eaa0941cd0_U256),
+    uint!(0xffcb9843d60f6159c9db58835c926644_U256),
+    uint!(0xff973b41fa98c081472e6896dfb254c0_U256),
+    uint!(0xff2ea16466c96a3843ec78b326b52861_U256),
+    uint!(0xfe5dee046a99a2a811c461f1969c3053_U256),
+    uint!(0xfcbe86c7900a88aedcffc83b479aa3a4_U256),
+    uint!(0xf987a7253ac413176f2b074cf7815e54_U256),
+    uint!(0xf3392b0822b70005940c7a398e4b70f3_U256),
+    uint!(0xe7159475a2c29b7443b29c7fa6e889d9_U256),
+    uint!(0xd097f3bdfd2022b8845ad8f792aa5825_U256),
+    uint!(0xa9f746462d870fdf8a65dc1f90e061e5_U256),
+    uint!(0x70d869a156d2a1b890bb3df62baf32f7_U256),
+    uint!(0x31be135f97d08fd981231505542fcfa6_U256),
+    uint!(0x9aa508b5b7a84e1c677de54f3e99bc9_U256),
+    uint!(0x5d6af8dedb81196699c329225ee604_U256),
+    uint!(0x2216e584f5fa1ea926041bedfe98_U256),
+    uint!(0x48a170391f7dc42444e8fa2_U256),
+];
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TickMathError {
+    #[cfg_attr(not(feature = "locale-en"), error("tick 超出范围{0}"))]
+    #[cfg_attr(feature = "locale-en", error("tick out of range: {0}"))]
+    TickOutOfRange(i32),
+    #[cfg_attr(not(feature = "locale-en"), error("sqrtPriceX96 超出范围{0}"))]
+    #[cfg_attr(feature = "locale-en", error("sqrtPriceX96 out of range: {0}"))]
+    SqrtPriceOutOfRange(U256),
+}
+
+impl TickMathError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            TickMathError::TickOutOfRange(_) => "TICK_OUT_OF_RANGE",
+            TickMathError::SqrtPriceOutOfRange(_) => "SQRT_PRICE_OUT_OF_RANGE",
+        }
+    }
+}
+
+/// `TickMath.getSqrtRatioAtTick`: `sqrt(1.0001^tick) * 2^96`
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> Result<U256, TickMathError> {
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        return Err(TickMathError::TickOutOfRange(tick));
+    }
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        uint!(0xfffcb933bd6fad37aa2d162d1a594001_U256)
+    } else {
+        U256::from(1) << 128
+    };
+    for (i, factor) in RATIOS.iter().enumerate() {
+        if abs_tick & (0x2 << i) != 0 {
+            ratio = (ratio * factor) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+    // Q128.128 转 Q64.96，向上取整
+    let round_up = !(ratio % (U256::from(1) << 32usize)).is_zero();
+    Ok((ratio >> 32) + U256::from(round_up as u8))
+}
+
+/// `TickMath.getTickAtSqrtRatio`: the greatest tick whose sqrt ratio is at most
+/// `sqrt_price_x96`
+pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U256) -> Result<i32, TickMathError> {
+    if sqrt_price_x96 < MIN_SQRT_RATIO || sqrt_price_x96 >= MAX_SQRT_RATIO {
+        return Err(TickMathError::SqrtPriceOutOfRange(sqrt_price_x96));
+    }
+    let ratio: U256 = sqrt_price_x96 << 32usize;
+    let msb = ratio.bit_len() - 1;
+    let mut r = if msb >= 128 {
+        ratio >> (msb - 127)
+    } else {
+        ratio << (127 - msb)
+    };
+
+    // log_2(ratio) 作为 Q64.64，逐位计算小数部分
+    let mut log_2 = I256::from_raw(U256::from(msb)) - I256::from_raw(U256::from(128));
+    log_2 <<= 64usize;
+    for shift in (50..=63).rev() {
+        r = (r * r) >> 127;
+        let f: U256 = r >> 128usize;
+        log_2 |= I256::from_raw(f << shift);
+        r >>= f.to::<usize>();
+    }
+
+    let log_sqrt10001: I256 = log_2 * I256::from_raw(uint!(255738958999603826347141_U256));
+    let tick_low = (log_sqrt10001
+        - I256::from_raw(uint!(3402992956809132418596140100660247210_U256)))
+    .asr(128);
+    let tick_high = (log_sqrt10001
+        + I256::from_raw(uint!(291339464771989622907027621153398088495_U256)))
+    .asr(128);
+    let tick_low = tick_low.as_i32();
+    let tick_high = tick_high.as_i32();
+
+    if tick_low == tick_high || get_sqrt_ratio_at_tick(tick_high)? > sqrt_price_x96 {
+        Ok(tick_low)
+    } else {
+        Ok(tick_high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 由 TickMath 的 Python 移植和 sqrt(1.0001^tick) * 2^96 交叉验证
+    const VECTORS: [(i32, U256); 11] = [
+        (0, uint!(79228162514264337593543950336_U256)),
+        (1, uint!(79232123823359799118286999568_U256)),
+        (-1, uint!(79224201403219477170569942574_U256)),
+        (50, uint!(79426470787362580746886972461_U256)),
+        (1000, uint!(83290069058676223003182343270_U256)),
+        (-1000, uint!(75364347830767020784054125655_U256)),
+        (10000, uint!(130621891405341611593710811006_U256)),
+        (-10000, uint!(48055510970269007215549348797_U256)),
+        (100000, uint!(11755562826496067164730007768450_U256)),
+        (-100000, uint!(533968626430936354154228408_U256)),
+        (MIN_TICK, MIN_SQRT_RATIO),
+    ];
+
+    #[test]
+    fn test_get_sqrt_ratio_at_tick() {
+        for (tick, sqrt_price) in VECTORS {
+            assert_eq!(
+                sqrt_price,
+                get_sqrt_ratio_at_tick(tick).unwrap(),
+                "tick {}",
+                tick
+            );
+        }
+        assert_eq!(MAX_SQRT_RATIO, get_sqrt_ratio_at_tick(MAX_TICK).unwrap());
+        assert_eq!(
+            Err(TickMathError::TickOutOfRange(MAX_TICK + 1)),
+            get_sqrt_ratio_at_tick(MAX_TICK + 1)
+        );
+        assert_eq!(
+            Err(TickMathError::TickOutOfRange(MIN_TICK - 1)),
+            get_sqrt_ratio_at_tick(MIN_TICK - 1)
+        );
+    }
+
+    #[test]
+    fn test_get_tick_at_sqrt_ratio() {
+        for (tick, sqrt_price) in VECTORS {
+            assert_eq!(tick, get_tick_at_sqrt_ratio(sqrt_price).unwrap());
+            // 向下取整到不超过该价格的最大 tick
+            assert_eq!(
+                tick,
+                get_tick_at_sqrt_ratio(sqrt_price + U256::from(1)).unwrap()
+            );
+            assert_eq!(
+                tick - 1,
+                get_tick_at_sqrt_ratio(sqrt_price - U256::from(1)).unwrap_or(MIN_TICK - 1)
+            );
+        }
+        assert_eq!(
+            MAX_TICK - 1,
+            get_tick_at_sqrt_ratio(MAX_SQRT_RATIO - U256::from(1)).unwrap()
+        );
+        assert!(get_tick_at_sqrt_ratio(MAX_SQRT_RATIO).is_err());
+        assert!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO - U256::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for tick in (MIN_TICK..=MAX_TICK).step_by(7919) {
+            let sqrt_price = get_sqrt_ratio_at_tick(tick).unwrap();
+            assert_eq!(tick, get_tick_at_sqrt_ratio(sqrt_price).unwrap());
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use alloy::primitives::{address, Address};
+
+use crate::client::UniswapSupportChain;
+
+/// Wrapped ether
+pub fn weth(chain: &UniswapSupportChain) -> Address {
+    match chain {
+        UniswapSupportChain::Ethereum => address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+        UniswapSupportChain::Base => address!("4200000000000000000000000000000000000006"),
+        UniswapSupportChain::Sepolia => address!("fFf9976782d46CC05630D1f6eBAb18b2324d6B14"),
+    }
+}
+
+/// Circle USD Coin (native USDC on Base, not bridged USDbC)
+pub fn usdc(chain: &UniswapSupportChain) -> Address {
+    match chain {
+        UniswapSupportChain::Ethereum => address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+        UniswapSupportChain::Base => address!("833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"),
+        UniswapSupportChain::Sepolia => address!("1c7D4B196Cb0C7B01d743Fbc6116a902379C7238"),
+    }
+}
+
+/// Tether USD. Sepolia has no official deployment, the Aave faucet token is used.
+pub fn usdt(chain: &UniswapSupportChain) -> Address {
+    match chain {
+        UniswapSupportChain::Ethereum => address!("dAC17F958D2ee523a2206206994597C13D831ec7"),
+        UniswapSupportChain::Base => address!("fde4C96c8593536E31F229EA8f37b2ADa2699bb2"),
+        UniswapSupportChain::Sepolia => address!("aA8E23Fb1079EA71e0a56F48a2aA51851D8433D0"),
+    }
+}
+
+/// Maker DAI. Sepolia has no official deployment, the Aave faucet token is used.
+pub fn dai(chain: &UniswapSupportChain) -> Address {
+    match chain {
+        UniswapSupportChain::Ethereum => address!("6B175474E89094C44Da98b954EedeAC495271d0F"),
+        UniswapSupportChain::Base => address!("50c5725949A6F0c72E6C4a641F24049A917DB0Cb"),
+        UniswapSupportChain::Sepolia => address!("FF34B3d4Aee8ddCd6F9AFFFB6Fe49bD371b8a357"),
+    }
+}
+
+/// USD stablecoins known on `chain`
+pub fn stablecoins(chain: &UniswapSupportChain) -> [Address; 3] {
+    [usdc(chain), usdt(chain), dai(chain)]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_mainnet_tokens() {
+        let chain = UniswapSupportChain::Ethereum;
+        assert_eq!(
+            Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            weth(&chain)
+        );
+        assert_eq!(
+            Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            usdc(&chain)
+        );
+        assert_eq!(
+            Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap(),
+            usdt(&chain)
+        );
+    }
+
+    #[test]
+    fn test_tokens_differ_per_chain() {
+        let mainnet = UniswapSupportChain::Ethereum;
+        let base = UniswapSupportChain::Base;
+        assert_ne!(weth(&mainnet), weth(&base));
+        assert_ne!(usdc(&mainnet), usdc(&base));
+        assert_ne!(usdt(&mainnet), usdt(&base));
+        assert_ne!(dai(&mainnet), dai(&base));
+
+        let sepolia = UniswapSupportChain::Sepolia;
+        assert_ne!(weth(&mainnet), weth(&sepolia));
+        assert_ne!(usdc(&mainnet), usdc(&sepolia));
+    }
+}
@@ -0,0 +1,141 @@
+use std::{str::FromStr, sync::Arc};
+
+use alloy::{
+    primitives::Address,
+    providers::{Provider, ProviderBuilder},
+    sol,
+    transports::Transport,
+};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UNIV2_FACTORY,
+    "src/abi/uniswapv2_factory.json"
+);
+
+pub const UNIV2_FACTORY_CONTRACT_ADDR: &str = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f";
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniswapV2FactoryError {
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(not(feature = "locale-en"), error("地址格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid address: {0}"))]
+    InvalidAddress(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法查询 pair 地址{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to look up pair address: {0}"))]
+    GetPairError(String),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("pair 不存在 token_a: {0}, token_b: {1}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("pair not found token_a: {0}, token_b: {1}")
+    )]
+    PairNotFound(Address, Address),
+}
+
+impl UniswapV2FactoryError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV2FactoryError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            UniswapV2FactoryError::InvalidAddress(_) => "INVALID_ADDRESS",
+            UniswapV2FactoryError::GetPairError(_) => "GET_PAIR_ERROR",
+            UniswapV2FactoryError::PairNotFound(..) => "PAIR_NOT_FOUND",
+        }
+    }
+}
+
+/// Resolve the V2 pair of `token_a` and `token_b` through the mainnet factory
+pub async fn get_pair(
+    token_a: Address,
+    token_b: Address,
+    rpc_url: String,
+) -> Result<Address, UniswapV2FactoryError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV2FactoryError::InvalidRpcUrl(e.to_string()))?;
+
+    get_pair_with_provider(token_a, token_b, Arc::new(provider)).await
+}
+
+/// Same as `get_pair` but reuses an already constructed provider
+pub async fn get_pair_with_provider<T, P>(
+    token_a: Address,
+    token_b: Address,
+    client: P,
+) -> Result<Address, UniswapV2FactoryError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let factory = Address::from_str(UNIV2_FACTORY_CONTRACT_ADDR)
+        .map_err(|e| UniswapV2FactoryError::InvalidAddress(e.to_string()))?;
+    let pair = UNIV2_FACTORY::new(factory, client)
+        .getPair(token_a, token_b)
+        .call()
+        .await
+        .map_err(|e| UniswapV2FactoryError::GetPairError(e.to_string()))?
+        .pair;
+    // 工厂对不存在的 pair 返回零地址
+    if pair.is_zero() {
+        return Err(UniswapV2FactoryError::PairNotFound(token_a, token_b));
+    }
+    Ok(pair)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::MockTransport;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_pair() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let pair = Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap();
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(pair.into_word()));
+        transport.push_response(serde_json::json!(Address::ZERO.into_word()));
+
+        assert_eq!(
+            pair,
+            get_pair_with_provider(usdc, weth, transport.provider())
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            UNIV2_FACTORY_CONTRACT_ADDR.to_lowercase(),
+            transport.requests()[0].params[0]["to"]
+        );
+        assert!(matches!(
+            get_pair_with_provider(usdc, Address::ZERO, transport.provider()).await,
+            Err(UniswapV2FactoryError::PairNotFound(..))
+        ));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use crate::test_utils::spawn_mainnet_fork;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_usdc_weth_pair() {
+        let fork = spawn_mainnet_fork();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let pair = get_pair(weth, usdc, fork.endpoint()).await.unwrap();
+        assert_eq!(
+            Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap(),
+            pair
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use alloy::primitives::U256;
+
+const FEE_NUMERATOR: u64 = 997;
+const FEE_DENOMINATOR: u64 = 1000;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum UniswapV2MathError {
+    #[cfg_attr(not(feature = "locale-en"), error("输入数量必须大于零"))]
+    #[cfg_attr(feature = "locale-en", error("input amount must be greater than zero"))]
+    InsufficientInputAmount,
+    #[cfg_attr(not(feature = "locale-en"), error("输出数量必须大于零"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("output amount must be greater than zero")
+    )]
+    InsufficientOutputAmount,
+    #[cfg_attr(not(feature = "locale-en"), error("池子流动性不足"))]
+    #[cfg_attr(feature = "locale-en", error("insufficient liquidity"))]
+    InsufficientLiquidity,
+    #[cfg_attr(not(feature = "locale-en"), error("计算溢出"))]
+    #[cfg_attr(feature = "locale-en", error("arithmetic overflow"))]
+    Overflow,
+}
+
+impl UniswapV2MathError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV2MathError::InsufficientInputAmount => "INSUFFICIENT_INPUT_AMOUNT",
+            UniswapV2MathError::InsufficientOutputAmount => "INSUFFICIENT_OUTPUT_AMOUNT",
+            UniswapV2MathError::InsufficientLiquidity => "INSUFFICIENT_LIQUIDITY",
+            UniswapV2MathError::Overflow => "OVERFLOW",
+        }
+    }
+}
+
+/// `UniswapV2Library.getAmountOut`: the output of swapping `amount_in` against
+/// the reserves after the 0.3% fee, rounded down
+pub fn get_amount_out(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+) -> Result<U256, UniswapV2MathError> {
+    if amount_in.is_zero() {
+        return Err(UniswapV2MathError::InsufficientInputAmount);
+    }
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(UniswapV2MathError::InsufficientLiquidity);
+    }
+    // 与 Solidity 的 SafeMath 一致，溢出时报错而不是回绕
+    let amount_in_with_fee = amount_in
+        .checked_mul(U256::from(FEE_NUMERATOR))
+        .ok_or(UniswapV2MathError::Overflow)?;
+    let numerator = amount_in_with_fee
+        .checked_mul(reserve_out)
+        .ok_or(UniswapV2MathError::Overflow)?;
+    let denominator = reserve_in
+        .checked_mul(U256::from(FEE_DENOMINATOR))
+        .and_then(|r| r.checked_add(amount_in_with_fee))
+        .ok_or(UniswapV2MathError::Overflow)?;
+    Ok(numerator / denominator)
+}
+
+/// `UniswapV2Library.getAmountIn`: the input needed to receive `amount_out`,
+/// rounded up by one wei like the Solidity version
+pub fn get_amount_in(
+    amount_out: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+) -> Result<U256, UniswapV2MathError> {
+    if amount_out.is_zero() {
+        return Err(UniswapV2MathError::InsufficientOutputAmount);
+    }
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(UniswapV2MathError::InsufficientLiquidity);
+    }
+    // 输出不能取走全部储备
+    if amount_out >= reserve_out {
+        return Err(UniswapV2MathError::InsufficientLiquidity);
+    }
+    let numerator = reserve_in
+        .checked_mul(amount_out)
+        .and_then(|n| n.checked_mul(U256::from(FEE_DENOMINATOR)))
+        .ok_or(UniswapV2MathError::Overflow)?;
+    let denominator = (reserve_out - amount_out)
+        .checked_mul(U256::from(FEE_NUMERATOR))
+        .ok_or(UniswapV2MathError::Overflow)?;
+    (numerator / denominator)
+        .checked_add(U256::from(1))
+        .ok_or(UniswapV2MathError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::uint;
+
+    use super::*;
+
+    const ETHER: U256 = uint!(1000000000000000000_U256);
+
+    #[test]
+    fn test_get_amount_out() {
+        // uniswap-v2-core 测试中的向量
+        let vectors = [
+            (1u64, 5u64, 10u64, uint!(1662497915624478906_U256)),
+            (1, 10, 5, uint!(453305446940074565_U256)),
+            (2, 5, 10, uint!(2851015155847869602_U256)),
+            (2, 10, 5, uint!(831248957812239453_U256)),
+            (1, 10, 10, uint!(906610893880149131_U256)),
+            (1, 100, 100, uint!(987158034397061298_U256)),
+            (1, 1000, 1000, uint!(996006981039903216_U256)),
+        ];
+        for (amount_in, reserve_in, reserve_out, expected) in vectors {
+            assert_eq!(
+                Ok(expected),
+                get_amount_out(
+                    U256::from(amount_in) * ETHER,
+                    U256::from(reserve_in) * ETHER,
+                    U256::from(reserve_out) * ETHER,
+                )
+            );
+        }
+        // 手续费后向下取整为零
+        assert_eq!(
+            Ok(U256::ZERO),
+            get_amount_out(U256::from(1), U256::from(100), U256::from(100))
+        );
+    }
+
+    #[test]
+    fn test_get_amount_in() {
+        assert_eq!(
+            Ok(uint!(557227237267357629_U256)),
+            get_amount_in(ETHER, U256::from(5) * ETHER, U256::from(10) * ETHER)
+        );
+        assert_eq!(
+            Ok(U256::from(2)),
+            get_amount_in(U256::from(1), U256::from(100), U256::from(100))
+        );
+        // 往返：按 get_amount_out 的结果反推输入，不超过原输入
+        let out = get_amount_out(ETHER, U256::from(5) * ETHER, U256::from(10) * ETHER).unwrap();
+        assert_eq!(
+            Ok(ETHER),
+            get_amount_in(out, U256::from(5) * ETHER, U256::from(10) * ETHER)
+        );
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let one = U256::from(1);
+        assert_eq!(
+            Err(UniswapV2MathError::InsufficientInputAmount),
+            get_amount_out(U256::ZERO, one, one)
+        );
+        assert_eq!(
+            Err(UniswapV2MathError::InsufficientLiquidity),
+            get_amount_out(one, U256::ZERO, one)
+        );
+        assert_eq!(
+            Err(UniswapV2MathError::InsufficientOutputAmount),
+            get_amount_in(U256::ZERO, one, one)
+        );
+        assert_eq!(
+            Err(UniswapV2MathError::InsufficientLiquidity),
+            get_amount_in(U256::from(10), U256::from(10), U256::from(10))
+        );
+        assert_eq!(
+            Err(UniswapV2MathError::Overflow),
+            get_amount_out(U256::MAX, one, one)
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{future::IntoFuture, sync::Arc};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    sol,
+    transports::Transport,
+};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UNIV2_PAIR,
+    "src/abi/uniswapv2_pair.json"
+);
+
+/// `getReserves()` of a V2 pair together with its tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairReserves {
+    pub token0: Address,
+    pub token1: Address,
+    pub reserve0: U256,
+    pub reserve1: U256,
+    /// 最后一次更新储备的区块时间（uint32，会溢出回绕）
+    pub block_timestamp_last: u32,
+}
+
+impl PairReserves {
+    /// `(reserve_in, reserve_out)` for swapping `token_in`
+    pub fn reserves_for(&self, token_in: Address) -> (U256, U256) {
+        if token_in == self.token0 {
+            (self.reserve0, self.reserve1)
+        } else {
+            (self.reserve1, self.reserve0)
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniswapV2PairError {
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取 pair 储备{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read pair reserves: {0}"))]
+    GetReservesError(String),
+}
+
+impl UniswapV2PairError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV2PairError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            UniswapV2PairError::GetReservesError(_) => "GET_RESERVES_ERROR",
+        }
+    }
+}
+
+pub async fn get_reserves(
+    pair: Address,
+    rpc_url: String,
+) -> Result<PairReserves, UniswapV2PairError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV2PairError::InvalidRpcUrl(e.to_string()))?;
+
+    get_reserves_with_provider(pair, Arc::new(provider)).await
+}
+
+/// Same as `get_reserves` but reuses an already constructed provider
+pub async fn get_reserves_with_provider<T, P>(
+    pair: Address,
+    client: P,
+) -> Result<PairReserves, UniswapV2PairError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let pair = UNIV2_PAIR::new(pair, client);
+    let reserves = pair.getReserves();
+    let token0 = pair.token0();
+    let token1 = pair.token1();
+    let (reserves, token0, token1) = futures::try_join!(
+        reserves.call().into_future(),
+        token0.call().into_future(),
+        token1.call().into_future()
+    )
+    .map_err(|e| UniswapV2PairError::GetReservesError(e.to_string()))?;
+    Ok(PairReserves {
+        token0: token0._0,
+        token1: token1._0,
+        reserve0: U256::from(reserves.reserve0),
+        reserve1: U256::from(reserves.reserve1),
+        block_timestamp_last: reserves.blockTimestampLast,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use alloy::{
+        primitives::{aliases::U112, Bytes},
+        sol_types::SolCall,
+    };
+
+    use crate::test_utils::MockTransport;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_reserves() {
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let reserves = UNIV2_PAIR::getReservesCall::abi_encode_returns(&(
+            U112::from(30_000_000_000_000u64),
+            U112::from(10u128.pow(22)),
+            1_700_000_000u32,
+        ));
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(Bytes::from(reserves)));
+        transport.push_response(serde_json::json!(usdc.into_word()));
+        transport.push_response(serde_json::json!(weth.into_word()));
+
+        let pair = Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap();
+        let reserves = get_reserves_with_provider(pair, transport.provider())
+            .await
+            .unwrap();
+        assert_eq!(
+            PairReserves {
+                token0: usdc,
+                token1: weth,
+                reserve0: U256::from(30_000_000_000_000u64),
+                reserve1: U256::from(10u128.pow(22)),
+                block_timestamp_last: 1_700_000_000,
+            },
+            reserves
+        );
+        assert_eq!(
+            (reserves.reserve1, reserves.reserve0),
+            reserves.reserves_for(weth)
+        );
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use std::str::FromStr;
+
+    use crate::test_utils::spawn_mainnet_fork;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_usdc_weth_reserves() {
+        let fork = spawn_mainnet_fork();
+        // USDC/WETH
+        let pair = Address::from_str("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc").unwrap();
+        let reserves = get_reserves(pair, fork.endpoint()).await.unwrap();
+        assert_eq!(
+            Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            reserves.token0
+        );
+        assert!(reserves.reserve0 > U256::ZERO);
+        assert!(reserves.reserve1 > U256::ZERO);
+        assert!(reserves.block_timestamp_last > 0);
+    }
+}
@@ -0,0 +1,194 @@
+use std::str::FromStr;
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, Bytes, U256},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UNIV2_ROUTER,
+    "src/abi/uniswapv2_router.json"
+);
+
+pub const UNIV2_ROUTER_CONTRACT_ADDR: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
+
+pub struct SwapExactInputParams {
+    pub amount_in: U256,
+    pub amount_out_min: U256,
+    /// token_in, 中间 token, token_out；原生 ETH 一侧要用 WETH 地址
+    pub path: Vec<Address>,
+    pub to: Address,
+    pub deadline: U256,
+    /// 使用原生 ETH 支付，调用 swapExactETHForTokens*
+    pub is_native_in: bool,
+    /// 接收原生 ETH，调用 swapExactTokensForETH*
+    pub is_native_out: bool,
+    /// Use the `*SupportingFeeOnTransferTokens` variants, which check
+    /// `amount_out_min` against the balance the recipient actually received
+    pub supports_fee_on_transfer: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniswapV2RouterError {
+    #[cfg_attr(not(feature = "locale-en"), error("地址格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid address: {0}"))]
+    InvalidAddress(String),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("不能同时使用原生 ETH 作为输入和输出")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("native ETH cannot be both the input and the output")
+    )]
+    NativeInAndOut,
+}
+
+impl UniswapV2RouterError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV2RouterError::InvalidAddress(_) => "INVALID_ADDRESS",
+            UniswapV2RouterError::NativeInAndOut => "NATIVE_IN_AND_OUT",
+        }
+    }
+}
+
+/// Encode the exact input swap call matching the native legs and the
+/// fee-on-transfer flag of `params`
+pub fn encode_swap_exact_input(
+    params: SwapExactInputParams,
+) -> Result<Bytes, UniswapV2RouterError> {
+    let SwapExactInputParams {
+        amount_in,
+        amount_out_min,
+        path,
+        to,
+        deadline,
+        ..
+    } = params;
+    let data = match (
+        params.is_native_in,
+        params.is_native_out,
+        params.supports_fee_on_transfer,
+    ) {
+        (true, true, _) => return Err(UniswapV2RouterError::NativeInAndOut),
+        (false, false, false) => UNIV2_ROUTER::swapExactTokensForTokensCall {
+            amountIn: amount_in,
+            amountOutMin: amount_out_min,
+            path,
+            to,
+            deadline,
+        }
+        .abi_encode(),
+        (false, false, true) => {
+            UNIV2_ROUTER::swapExactTokensForTokensSupportingFeeOnTransferTokensCall {
+                amountIn: amount_in,
+                amountOutMin: amount_out_min,
+                path,
+                to,
+                deadline,
+            }
+            .abi_encode()
+        }
+        (true, false, false) => UNIV2_ROUTER::swapExactETHForTokensCall {
+            amountOutMin: amount_out_min,
+            path,
+            to,
+            deadline,
+        }
+        .abi_encode(),
+        (true, false, true) => {
+            UNIV2_ROUTER::swapExactETHForTokensSupportingFeeOnTransferTokensCall {
+                amountOutMin: amount_out_min,
+                path,
+                to,
+                deadline,
+            }
+            .abi_encode()
+        }
+        (false, true, false) => UNIV2_ROUTER::swapExactTokensForETHCall {
+            amountIn: amount_in,
+            amountOutMin: amount_out_min,
+            path,
+            to,
+            deadline,
+        }
+        .abi_encode(),
+        (false, true, true) => {
+            UNIV2_ROUTER::swapExactTokensForETHSupportingFeeOnTransferTokensCall {
+                amountIn: amount_in,
+                amountOutMin: amount_out_min,
+                path,
+                to,
+                deadline,
+            }
+            .abi_encode()
+        }
+    };
+    Ok(data.into())
+}
+
+/// Build the exact input swap transaction, attaching `amount_in` as value
+/// when paying with native ETH
+pub fn swap_exact_input_tx(
+    params: SwapExactInputParams,
+) -> Result<TransactionRequest, UniswapV2RouterError> {
+    let router = Address::from_str(UNIV2_ROUTER_CONTRACT_ADDR)
+        .map_err(|e| UniswapV2RouterError::InvalidAddress(e.to_string()))?;
+    let value = params.is_native_in.then_some(params.amount_in);
+    let tx = TransactionRequest::default()
+        .with_to(router)
+        .with_input(encode_swap_exact_input(params)?);
+    Ok(match value {
+        Some(value) => tx.with_value(value),
+        None => tx,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::hex;
+
+    use super::*;
+
+    fn params(supports_fee_on_transfer: bool) -> SwapExactInputParams {
+        SwapExactInputParams {
+            amount_in: U256::from(1000),
+            amount_out_min: U256::from(900),
+            path: vec![
+                Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+                Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            ],
+            to: Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap(),
+            deadline: U256::from(1735689600u64),
+            is_native_in: false,
+            is_native_out: false,
+            supports_fee_on_transfer,
+        }
+    }
+
+    #[test]
+    fn test_fee_on_transfer_selects_supporting_variant() {
+        let data = encode_swap_exact_input(params(false)).unwrap();
+        assert_eq!("38ed1739", hex::encode(&data[..4]));
+        let data = encode_swap_exact_input(params(true)).unwrap();
+        assert_eq!("5c11d795", hex::encode(&data[..4]));
+
+        let mut native_in = params(true);
+        native_in.is_native_in = true;
+        let tx = swap_exact_input_tx(native_in).unwrap();
+        assert_eq!("b6f9de95", hex::encode(&tx.input.input().unwrap()[..4]));
+        assert_eq!(Some(U256::from(1000)), tx.value);
+
+        let mut native_out = params(true);
+        native_out.is_native_out = true;
+        let data = encode_swap_exact_input(native_out).unwrap();
+        assert_eq!("791ac947", hex::encode(&data[..4]));
+    }
+}
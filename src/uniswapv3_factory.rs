@@ -2,58 +2,193 @@ use std::sync::Arc;
 
 use anyhow::Result;
 
+use alloy::sol;
 use alloy::{
-    primitives::{aliases::U24, Address, U160, U256},
-    providers::ProviderBuilder,
+    primitives::{aliases::U24, Address, U256},
+    providers::{Provider, ProviderBuilder},
+    transports::Transport,
 };
-use alloy::{rpc::types::error, sol};
-use std::str::FromStr;
 
-use crate::unswapv3_pool::UniswapPoolFee;
+use crate::{config::ContractAddresses, logging, unswapv3_pool::UNIV3_POOL};
+
 sol!(
     #[allow(missing_docs)]
     #[sol(rpc)]
     UNIV3_FACTORY,
     "src/abi/uniswapv3_factory.json"
 );
-const UNIV3_FACTORY_CONTRACT_ADDR: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+pub(crate) const UNIV3_FACTORY_CONTRACT_ADDR: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
 
+#[derive(Debug)]
 pub struct GetPoolParam {
     pub token_a: Address,
     pub token_b: Address,
     pub fee: u32,
 }
 
+/// A pool address together with its current `slot0` price and liquidity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolWithState {
+    pub address: Address,
+    /// false 时其余字段均为零
+    pub exists: bool,
+    pub sqrt_price_x96: U256,
+    /// 合约中为 int24，token1/token0 原始价格小于 1 时为负数
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+#[derive(Debug)]
 pub enum UniswapV3FactoryCommand {
     GetPool(GetPoolParam),
+    /// Resolve the pool address and read its `slot0` and `liquidity`
+    GetPoolWithState(GetPoolParam),
 }
+#[derive(Debug)]
 pub enum UniswapV3FactoryResult {
     GetPool(Address),
+    GetPoolWithState(PoolWithState),
+}
+
+impl UniswapV3FactoryResult {
+    /// The pool address from either variant
+    pub fn pool_address(&self) -> Address {
+        match self {
+            UniswapV3FactoryResult::GetPool(address) => *address,
+            UniswapV3FactoryResult::GetPoolWithState(pool) => pool.address,
+        }
+    }
+
+    /// The pool state, only returned by `GetPoolWithState`
+    pub fn pool_state(&self) -> Option<&PoolWithState> {
+        match self {
+            UniswapV3FactoryResult::GetPoolWithState(pool) => Some(pool),
+            UniswapV3FactoryResult::GetPool(_) => None,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum UniswapV3FactoryError {
-    #[error("请替换rpc url{0}")]
+    #[cfg_attr(not(feature = "locale-en"), error("请替换rpc url{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid RPC url: {0}"))]
     InvalidRpcUrl(String),
 
-    #[error("无法查询pool address，请确认是否开盘或poolfee设置是否正确{0}")]
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("无法查询pool address，请确认是否开盘或poolfee设置是否正确{0}")
+    )]
+    #[cfg_attr(feature = "locale-en", error("failed to look up pool address, check that the pool exists and the fee is correct: {0}"))]
     GetPoolError(String),
 
-    #[error("无效的命令")]
+    #[cfg_attr(not(feature = "locale-en"), error("无效的命令"))]
+    #[cfg_attr(feature = "locale-en", error("invalid command"))]
     InvalidCommand,
+
+    #[cfg_attr(not(feature = "locale-en"), error("token_a 和 token_b 不能相同{0}"))]
+    #[cfg_attr(feature = "locale-en", error("token_a and token_b must differ: {0}"))]
+    IdenticalTokens(Address),
+
+    #[cfg_attr(not(feature = "locale-en"), error("token 地址不能是零地址"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("token address must not be the zero address")
+    )]
+    ZeroAddress,
+
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("池子不存在 token_a: {0}, token_b: {1}, fee: {2}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("pool not found token_a: {0}, token_b: {1}, fee: {2}")
+    )]
+    PoolNotFound(Address, Address, u32),
+
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取池子状态{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read pool state: {0}"))]
+    GetPoolStateError(String),
+}
+
+impl UniswapV3FactoryError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV3FactoryError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            UniswapV3FactoryError::GetPoolError(_) => "GET_POOL_ERROR",
+            UniswapV3FactoryError::InvalidCommand => "INVALID_COMMAND",
+            UniswapV3FactoryError::IdenticalTokens(_) => "IDENTICAL_TOKENS",
+            UniswapV3FactoryError::ZeroAddress => "ZERO_ADDRESS",
+            UniswapV3FactoryError::PoolNotFound(..) => "POOL_NOT_FOUND",
+            UniswapV3FactoryError::GetPoolStateError(_) => "GET_POOL_STATE_ERROR",
+        }
+    }
+}
+
+fn validate_pair(token_a: Address, token_b: Address) -> Result<(), UniswapV3FactoryError> {
+    if token_a.is_zero() || token_b.is_zero() {
+        return Err(UniswapV3FactoryError::ZeroAddress);
+    }
+    if token_a == token_b {
+        return Err(UniswapV3FactoryError::IdenticalTokens(token_a));
+    }
+    Ok(())
 }
 
 pub async fn execute(
     command: UniswapV3FactoryCommand,
     rpc_url: String,
+) -> Result<UniswapV3FactoryResult, UniswapV3FactoryError> {
+    execute_with_config(command, rpc_url, &ContractAddresses::default()).await
+}
+
+/// Same as `execute` but against `addresses.factory` instead of the mainnet factory
+pub async fn execute_with_config(
+    command: UniswapV3FactoryCommand,
+    rpc_url: String,
+    addresses: &ContractAddresses,
 ) -> Result<UniswapV3FactoryResult, UniswapV3FactoryError> {
     let provider = ProviderBuilder::new()
         .on_builtin(&rpc_url)
         .await
         .map_err(|e| UniswapV3FactoryError::InvalidRpcUrl(e.to_string()))?;
 
-    let client = Arc::new(provider);
-    let factory_address = Address::from_str(UNIV3_FACTORY_CONTRACT_ADDR).unwrap();
+    execute_with_provider(command, Arc::new(provider), addresses).await
+}
+
+/// Same as `execute_with_config` but reuses an already constructed provider, e.g. one with an
+/// authenticated transport, instead of connecting to a `rpc_url` on every call.
+pub async fn execute_with_provider<T, P>(
+    command: UniswapV3FactoryCommand,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<UniswapV3FactoryResult, UniswapV3FactoryError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    logging::log_command("factory", addresses.factory, &command);
+    let res = run_command(command, client, addresses.factory).await;
+    logging::log_result("factory", &res);
+    res
+}
+
+async fn run_command<T, P>(
+    command: UniswapV3FactoryCommand,
+    client: P,
+    factory_address: Address,
+) -> Result<UniswapV3FactoryResult, UniswapV3FactoryError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    match &command {
+        UniswapV3FactoryCommand::GetPool(params)
+        | UniswapV3FactoryCommand::GetPoolWithState(params) => {
+            validate_pair(params.token_a, params.token_b)?
+        }
+    }
     match command {
         UniswapV3FactoryCommand::GetPool(params) => {
             let contract = UNIV3_FACTORY::new(factory_address, client);
@@ -61,18 +196,259 @@ pub async fn execute(
                 .getPool(params.token_a, params.token_b, U24::from(params.fee))
                 .call()
                 .await
-                .map_err(|e| UniswapV3FactoryError::GetPoolError(e.to_string()))?;
-            Ok(UniswapV3FactoryResult::GetPool(pool_address._0))
+                .map_err(|e| UniswapV3FactoryError::GetPoolError(e.to_string()))?
+                ._0;
+            if pool_address.is_zero() {
+                return Err(UniswapV3FactoryError::PoolNotFound(
+                    params.token_a,
+                    params.token_b,
+                    params.fee,
+                ));
+            }
+            Ok(UniswapV3FactoryResult::GetPool(pool_address))
         }
-        _ => Err(UniswapV3FactoryError::InvalidCommand),
+        UniswapV3FactoryCommand::GetPoolWithState(params) => {
+            let contract = UNIV3_FACTORY::new(factory_address, &client);
+            let pool_address = contract
+                .getPool(params.token_a, params.token_b, U24::from(params.fee))
+                .call()
+                .await
+                .map_err(|e| UniswapV3FactoryError::GetPoolError(e.to_string()))?
+                ._0;
+            if pool_address.is_zero() {
+                return Ok(UniswapV3FactoryResult::GetPoolWithState(PoolWithState {
+                    address: pool_address,
+                    exists: false,
+                    sqrt_price_x96: U256::ZERO,
+                    tick: 0,
+                    liquidity: 0,
+                }));
+            }
+            let pool = UNIV3_POOL::new(pool_address, client);
+            let slot0 = pool
+                .slot0()
+                .call()
+                .await
+                .map_err(|e| UniswapV3FactoryError::GetPoolStateError(e.to_string()))?;
+            let liquidity = pool
+                .liquidity()
+                .call()
+                .await
+                .map_err(|e| UniswapV3FactoryError::GetPoolStateError(e.to_string()))?
+                ._0;
+            Ok(UniswapV3FactoryResult::GetPoolWithState(PoolWithState {
+                address: pool_address,
+                exists: true,
+                sqrt_price_x96: U256::from(slot0.sqrtPriceX96),
+                tick: slot0.tick.as_i32(),
+                liquidity,
+            }))
+        }
+    }
+}
+
+/// Whether a pool has been created for the pair and fee tier
+pub async fn pool_exists(
+    token_a: Address,
+    token_b: Address,
+    fee: u32,
+    rpc_url: String,
+) -> Result<bool, UniswapV3FactoryError> {
+    let command = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+        token_a,
+        token_b,
+        fee,
+    });
+    match execute(command, rpc_url).await {
+        Ok(_) => Ok(true),
+        Err(UniswapV3FactoryError::PoolNotFound(..)) => Ok(false),
+        Err(e) => Err(e),
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::{test_utils::MockTransport, unswapv3_pool::UniswapPoolFee};
+
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_result_accessors() {
+        let address = Address::repeat_byte(1);
+        let pool = PoolWithState {
+            address,
+            exists: true,
+            sqrt_price_x96: U256::from(1u64 << 32),
+            tick: -42,
+            liquidity: 1000,
+        };
+        let get_pool = UniswapV3FactoryResult::GetPool(address);
+        let with_state = UniswapV3FactoryResult::GetPoolWithState(pool.clone());
+        assert_eq!(address, get_pool.pool_address());
+        assert_eq!(address, with_state.pool_address());
+        assert_eq!(None, get_pool.pool_state());
+        assert_eq!(Some(&pool), with_state.pool_state());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(
+            "POOL_NOT_FOUND",
+            UniswapV3FactoryError::PoolNotFound(Address::ZERO, Address::ZERO, 500).code()
+        );
+        assert_eq!(
+            "IDENTICAL_TOKENS",
+            UniswapV3FactoryError::IdenticalTokens(Address::ZERO).code()
+        );
+        assert_eq!("ZERO_ADDRESS", UniswapV3FactoryError::ZeroAddress.code());
+    }
+
+    #[tokio::test]
+    pub async fn test_get_pool_identical_tokens() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let get_pool = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a: weth,
+            token_b: weth,
+            fee: UniswapPoolFee::Fee3000.as_u32(),
+        });
+        let err = execute(get_pool, "https://eth.llamarpc.com".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UniswapV3FactoryError::IdenticalTokens(token) if token == weth));
+    }
+
+    #[tokio::test]
+    pub async fn test_get_pool_zero_address() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let get_pool = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a: Address::ZERO,
+            token_b: weth,
+            fee: UniswapPoolFee::Fee3000.as_u32(),
+        });
+        let err = execute(get_pool, "https://eth.llamarpc.com".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UniswapV3FactoryError::ZeroAddress));
+    }
+
+    #[tokio::test]
+    pub async fn test_get_pool_with_state_negative_tick() {
+        use alloy::{
+            primitives::{aliases::I24, Bytes, U160},
+            sol_types::SolCall,
+        };
+
+        use crate::tick_math::{get_sqrt_ratio_at_tick, get_tick_at_sqrt_ratio};
+
+        // WETH/USDT：token0 是 WETH，原始价格远小于 1，当前 tick 为负
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let pool = Address::from_str("0x11b815efB8f581194ae79006d24E0d814B7697F6").unwrap();
+        let tick = -195_000;
+        let sqrt_price_x96 = get_sqrt_ratio_at_tick(tick).unwrap();
+
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(pool.into_word()));
+        let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+            U160::from(sqrt_price_x96),
+            I24::try_from(tick).unwrap(),
+            0u16,
+            1u16,
+            1u16,
+            0u8,
+            true,
+        ));
+        transport.push_response(serde_json::json!(Bytes::from(slot0)));
+        let liquidity = UNIV3_POOL::liquidityCall::abi_encode_returns(&(1u128 << 64,));
+        transport.push_response(serde_json::json!(Bytes::from(liquidity)));
+
+        let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+            token_a: weth,
+            token_b: usdt,
+            fee: UniswapPoolFee::Fee500.as_u32(),
+        });
+        let UniswapV3FactoryResult::GetPoolWithState(state) =
+            execute_with_provider(command, transport.provider(), &ContractAddresses::default())
+                .await
+                .unwrap()
+        else {
+            panic!("unexpected result");
+        };
+        assert_eq!(tick, state.tick);
+        assert_eq!(sqrt_price_x96, state.sqrt_price_x96);
+        assert_eq!(tick, get_tick_at_sqrt_ratio(state.sqrt_price_x96).unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn test_get_pool_custom_factory() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let pool = Address::from_str("0x1ac1A8FEaAEa1900C4166dEeed0C11cC10669D36").unwrap();
+        // PancakeSwap V3 factory
+        let addresses = ContractAddresses {
+            factory: Address::from_str("0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865").unwrap(),
+            ..Default::default()
+        };
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(pool.into_word()));
+
+        let get_pool = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a: usdc,
+            token_b: weth,
+            fee: UniswapPoolFee::Fee500.as_u32(),
+        });
+        let res = execute_with_provider(get_pool, transport.provider(), &addresses)
+            .await
+            .unwrap();
+        assert!(matches!(res, UniswapV3FactoryResult::GetPool(address) if address == pool));
+
+        let requests = transport.requests();
+        assert_eq!(1, requests.len());
+        assert_eq!("eth_call", requests[0].method);
+        assert_eq!(
+            addresses.factory,
+            Address::from_str(requests[0].params[0]["to"].as_str().unwrap()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_pool_zero_address_is_not_found() {
+        let token_a = Address::from_str("0x535887989b9EdffB63b1Fd5C6b99a4d45443b49a").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(Address::ZERO.into_word()));
+        let get_pool = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a,
+            token_b: weth,
+            fee: UniswapPoolFee::Fee100.as_u32(),
+        });
+        let err = execute_with_provider(
+            get_pool,
+            transport.provider(),
+            &ContractAddresses::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            UniswapV3FactoryError::PoolNotFound(a, b, 100) if a == token_a && b == weth
+        ));
+        assert_eq!("POOL_NOT_FOUND", err.code());
+        // 只查询了 getPool，没有继续读取零地址
+        assert_eq!(1, transport.requests().len());
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+
     use crate::unswapv3_pool::UniswapPoolFee;
 
+    use std::str::FromStr;
+
     use super::*;
 
     #[tokio::test]
@@ -96,4 +472,93 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    pub async fn test_get_pool_not_found() {
+        let token_a = Address::from_str("0x535887989b9EdffB63b1Fd5C6b99a4d45443b49a").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let get_pool = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+            token_a,
+            token_b: weth,
+            fee: UniswapPoolFee::Fee100.as_u32(),
+        });
+        let err = execute(get_pool, "https://eth.llamarpc.com".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            UniswapV3FactoryError::PoolNotFound(a, b, 100) if a == token_a && b == weth
+        ));
+    }
+
+    #[tokio::test]
+    pub async fn test_get_pool_with_state() {
+        let token_a = Address::from_str("0x535887989b9EdffB63b1Fd5C6b99a4d45443b49a").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+            token_a,
+            token_b: weth,
+            fee: UniswapPoolFee::Fee10000.as_u32(),
+        });
+        let res = execute(command, "https://eth.llamarpc.com".to_string())
+            .await
+            .unwrap();
+        if let UniswapV3FactoryResult::GetPoolWithState(pool) = res {
+            assert_eq!(
+                Address::from_str("0xFbDbaC2d456A3CC2754A626C2fB83C1af25A3a6F").unwrap(),
+                pool.address
+            );
+            assert!(pool.exists);
+            assert!(pool.sqrt_price_x96 > U256::ZERO);
+            assert!(pool.liquidity > 0);
+        } else {
+            panic!("unexpected result");
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_get_pool_with_state_negative_tick_online() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+            token_a: weth,
+            token_b: usdt,
+            fee: UniswapPoolFee::Fee500.as_u32(),
+        });
+        let res = execute(command, "https://eth.llamarpc.com".to_string())
+            .await
+            .unwrap();
+        let UniswapV3FactoryResult::GetPoolWithState(pool) = res else {
+            panic!("unexpected result");
+        };
+        // USDT 只有 6 位小数，1 wei WETH 远不值 1 个最小单位的 USDT
+        assert!(pool.tick < 0);
+        assert_eq!(
+            pool.tick,
+            crate::tick_math::get_tick_at_sqrt_ratio(pool.sqrt_price_x96).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_get_pool_with_state_not_exists() {
+        let token_a = Address::from_str("0x535887989b9EdffB63b1Fd5C6b99a4d45443b49a").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+            token_a,
+            token_b: weth,
+            fee: UniswapPoolFee::Fee100.as_u32(),
+        });
+        let res = execute(command, "https://eth.llamarpc.com".to_string())
+            .await
+            .unwrap();
+        if let UniswapV3FactoryResult::GetPoolWithState(pool) = res {
+            assert!(!pool.exists);
+            assert_eq!(Address::ZERO, pool.address);
+            assert_eq!(U256::ZERO, pool.sqrt_price_x96);
+            assert_eq!(0, pool.tick);
+            assert_eq!(0, pool.liquidity);
+        } else {
+            panic!("unexpected result");
+        }
+    }
 }
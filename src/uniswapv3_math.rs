@@ -0,0 +1,364 @@
+use alloy::primitives::{uint, Address, U256, U512};
+
+/// `TickMath.MIN_SQRT_RATIO`, the sqrt price at the minimum tick
+pub const MIN_SQRT_RATIO: U256 = uint!(4295128739_U256);
+/// `TickMath.MAX_SQRT_RATIO`, the sqrt price at the maximum tick
+pub const MAX_SQRT_RATIO: U256 = uint!(1461446703485210103287273052203988822378723970342_U256);
+
+/// `FixedPoint96.Q96`, 1 in Q64.96
+pub const Q96: U256 = uint!(79228162514264337593543950336_U256);
+
+const BPS: u32 = 10_000;
+
+/// `FullMath.mulDiv`: `a * b / denominator` with a 512-bit intermediate, `None`
+/// where the Solidity version reverts (zero denominator or result overflowing 256 bits)
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    if denominator.is_zero() {
+        return None;
+    }
+    let result = U512::from(a) * U512::from(b) / U512::from(denominator);
+    U256::checked_from_limbs_slice(result.as_limbs())
+}
+
+/// `FullMath.mulDivRoundingUp`
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    let result = mul_div(a, b, denominator)?;
+    if (U512::from(a) * U512::from(b) % U512::from(denominator)).is_zero() {
+        Some(result)
+    } else {
+        result.checked_add(U256::from(1))
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum UniswapV3MathError {
+    #[cfg_attr(not(feature = "locale-en"), error("滑点必须小于 10000 bps，当前{0}"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("slippage must be less than 10000 bps, got {0}")
+    )]
+    InvalidSlippage(u32),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("价格限制{limit}方向错误，当前价格{current}，zero_for_one={zero_for_one}")
+    )]
+    #[cfg_attr(feature = "locale-en", error("price limit {limit} is on the wrong side of the current price {current}, zero_for_one={zero_for_one}"))]
+    WrongSideLimit {
+        limit: U256,
+        current: U256,
+        zero_for_one: bool,
+    },
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("价格限制{0}超出 MIN_SQRT_RATIO..MAX_SQRT_RATIO 范围")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("price limit {0} is outside MIN_SQRT_RATIO..MAX_SQRT_RATIO")
+    )]
+    LimitOutOfRange(U256),
+    #[cfg_attr(not(feature = "locale-en"), error("价格必须是正数，当前{0}"))]
+    #[cfg_attr(feature = "locale-en", error("price must be positive, got {0}"))]
+    InvalidPrice(String),
+}
+
+impl UniswapV3MathError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV3MathError::InvalidSlippage(_) => "INVALID_SLIPPAGE",
+            UniswapV3MathError::WrongSideLimit { .. } => "WRONG_SIDE_LIMIT",
+            UniswapV3MathError::LimitOutOfRange(_) => "LIMIT_OUT_OF_RANGE",
+            UniswapV3MathError::InvalidPrice(_) => "INVALID_PRICE",
+        }
+    }
+}
+
+/// The minimum amount out accepted for a quoted `amount` with `slippage_bps` of
+/// tolerance, rounded down
+pub fn min_amount_with_slippage(
+    amount: U256,
+    slippage_bps: u32,
+) -> Result<U256, UniswapV3MathError> {
+    if slippage_bps >= BPS {
+        return Err(UniswapV3MathError::InvalidSlippage(slippage_bps));
+    }
+    // 结果不会超过 amount，512 位中间结果保证不会溢出
+    mul_div(amount, U256::from(BPS - slippage_bps), U256::from(BPS))
+        .ok_or(UniswapV3MathError::InvalidSlippage(slippage_bps))
+}
+
+/// Whether swapping `token_in` for `token_out` moves the pool from token0 to
+/// token1, which makes the price go down
+pub fn zero_for_one(token_in: Address, token_out: Address) -> bool {
+    token_in < token_out
+}
+
+/// `(token0, token1, a_is_token0)`: the pair in pool order, the lower address
+/// first, and whether `token_a` ended up as token0. `slot0` prices are always
+/// token1 per token0.
+pub fn sort_tokens(token_a: Address, token_b: Address) -> (Address, Address, bool) {
+    if token_a < token_b {
+        (token_a, token_b, true)
+    } else {
+        (token_b, token_a, false)
+    }
+}
+
+/// The limit `UniswapV3Pool.swap` effectively runs to when `sqrtPriceLimitX96`
+/// is zero, i.e. no price protection
+pub fn full_range_sqrt_price_limit(zero_for_one: bool) -> U256 {
+    if zero_for_one {
+        MIN_SQRT_RATIO + U256::from(1)
+    } else {
+        MAX_SQRT_RATIO - U256::from(1)
+    }
+}
+
+/// Convert a human readable `target_price` (token1 per token0, e.g. WETH per
+/// USDC) into the `sqrtPriceX96` bound the swap stops at, clamped into
+/// `(MIN_SQRT_RATIO, MAX_SQRT_RATIO)` whichever way the swap goes. Goes through
+/// `f64`, which is plenty for a limit.
+pub fn price_limit_from_price(
+    target_price: f64,
+    decimals0: u8,
+    decimals1: u8,
+    _zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    if !target_price.is_finite() || target_price <= 0.0 {
+        return Err(UniswapV3MathError::InvalidPrice(target_price.to_string()));
+    }
+    // 换算成链上单位的价格：token1 最小单位 / token0 最小单位
+    let raw_price = if decimals1 >= decimals0 {
+        target_price * 10_f64.powi((decimals1 - decimals0) as i32)
+    } else {
+        target_price / 10_f64.powi((decimals0 - decimals1) as i32)
+    };
+    let sqrt_price_x96 = raw_price.sqrt() * 2_f64.powi(96);
+    let limit = U256::try_from(sqrt_price_x96).unwrap_or(MAX_SQRT_RATIO);
+    // 两个方向都要限制在 (MIN_SQRT_RATIO, MAX_SQRT_RATIO) 之内，否则 swap 会 revert
+    Ok(limit.clamp(
+        full_range_sqrt_price_limit(true),
+        full_range_sqrt_price_limit(false),
+    ))
+}
+
+/// The sqrt price limit that stops the swap once the price has moved by
+/// `slippage_bps` from `current_sqrt_price_x96`, clamped to the valid range
+pub fn sqrt_price_limit_from_slippage(
+    current_sqrt_price_x96: U256,
+    slippage_bps: u32,
+    zero_for_one: bool,
+) -> Result<U256, UniswapV3MathError> {
+    if slippage_bps >= BPS {
+        return Err(UniswapV3MathError::InvalidSlippage(slippage_bps));
+    }
+    // price = sqrtPrice^2，所以 sqrt 价格限制 = sqrt(sqrtPrice^2 * (1 ± slippage))
+    let factor = if zero_for_one {
+        BPS - slippage_bps
+    } else {
+        BPS + slippage_bps
+    };
+    let current = U512::from(current_sqrt_price_x96);
+    let limit = (current * current * U512::from(factor) / U512::from(BPS)).root(2);
+    let limit = U256::checked_from_limbs_slice(limit.as_limbs()).unwrap_or(MAX_SQRT_RATIO);
+    Ok(if zero_for_one {
+        limit.max(full_range_sqrt_price_limit(true))
+    } else {
+        limit.min(full_range_sqrt_price_limit(false))
+    })
+}
+
+/// Check a user supplied limit the same way `UniswapV3Pool.swap` does, so a
+/// limit on the wrong side fails before sending instead of reverting with `SPL`.
+/// Zero means no limit and is always accepted.
+pub fn validate_sqrt_price_limit(
+    sqrt_price_limit_x96: U256,
+    current_sqrt_price_x96: U256,
+    zero_for_one: bool,
+) -> Result<(), UniswapV3MathError> {
+    if sqrt_price_limit_x96.is_zero() {
+        return Ok(());
+    }
+    if sqrt_price_limit_x96 <= MIN_SQRT_RATIO || sqrt_price_limit_x96 >= MAX_SQRT_RATIO {
+        return Err(UniswapV3MathError::LimitOutOfRange(sqrt_price_limit_x96));
+    }
+    let on_correct_side = if zero_for_one {
+        sqrt_price_limit_x96 < current_sqrt_price_x96
+    } else {
+        sqrt_price_limit_x96 > current_sqrt_price_x96
+    };
+    if !on_correct_side {
+        return Err(UniswapV3MathError::WrongSideLimit {
+            limit: sqrt_price_limit_x96,
+            current: current_sqrt_price_x96,
+            zero_for_one,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_sort_tokens() {
+        // USDC/WETH 池子的 token0 是 USDC
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        assert_eq!((usdc, weth, true), sort_tokens(usdc, weth));
+        assert_eq!((usdc, weth, false), sort_tokens(weth, usdc));
+        assert!(zero_for_one(usdc, weth));
+    }
+
+    #[test]
+    fn test_mul_div() {
+        assert_eq!(
+            Some(U256::from(6)),
+            mul_div(U256::from(4), U256::from(3), U256::from(2))
+        );
+        assert_eq!(
+            Some(U256::from(3)),
+            mul_div(U256::from(7), U256::from(1), U256::from(2))
+        );
+        assert_eq!(
+            Some(U256::from(4)),
+            mul_div_rounding_up(U256::from(7), U256::from(1), U256::from(2))
+        );
+        // 中间结果超过 256 位也不会溢出
+        assert_eq!(Some(U256::MAX), mul_div(U256::MAX, Q96, Q96));
+        assert_eq!(None, mul_div(U256::MAX, U256::from(2), U256::from(1)));
+        assert_eq!(None, mul_div(U256::from(1), U256::from(1), U256::ZERO));
+        assert_eq!(
+            None,
+            mul_div_rounding_up(U256::MAX, U256::from(3), U256::from(2))
+        );
+    }
+
+    #[test]
+    fn test_min_amount_with_slippage() {
+        assert_eq!(
+            Ok(U256::from(9950)),
+            min_amount_with_slippage(U256::from(10_000), 50)
+        );
+        // 向下取整
+        assert_eq!(
+            Ok(U256::from(98)),
+            min_amount_with_slippage(U256::from(99), 50)
+        );
+        assert_eq!(Ok(U256::MAX), min_amount_with_slippage(U256::MAX, 0));
+        assert_eq!(
+            Err(UniswapV3MathError::InvalidSlippage(10_000)),
+            min_amount_with_slippage(U256::from(1), 10_000)
+        );
+    }
+
+    #[test]
+    fn test_zero_for_one() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        // USDC 是 token0
+        assert!(zero_for_one(usdc, weth));
+        assert!(!zero_for_one(weth, usdc));
+    }
+
+    #[test]
+    fn test_limit_zero_for_one() {
+        // 价格下降 1%，sqrt 价格约下降 0.5%
+        let limit = sqrt_price_limit_from_slippage(Q96, 100, true).unwrap();
+        assert!(limit < Q96);
+        assert_eq!(uint!(78831026366734652303669917531_U256), limit);
+        validate_sqrt_price_limit(limit, Q96, true).unwrap();
+        assert!(matches!(
+            validate_sqrt_price_limit(limit, Q96, false),
+            Err(UniswapV3MathError::WrongSideLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn test_limit_one_for_zero() {
+        let limit = sqrt_price_limit_from_slippage(Q96, 100, false).unwrap();
+        assert!(limit > Q96);
+        assert_eq!(uint!(79623317895830914510639640423_U256), limit);
+        validate_sqrt_price_limit(limit, Q96, false).unwrap();
+        assert!(matches!(
+            validate_sqrt_price_limit(limit, Q96, true),
+            Err(UniswapV3MathError::WrongSideLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn test_limit_clamped_and_validated() {
+        let limit = sqrt_price_limit_from_slippage(MIN_SQRT_RATIO, 100, true).unwrap();
+        assert_eq!(MIN_SQRT_RATIO + U256::from(1), limit);
+        let limit = sqrt_price_limit_from_slippage(MAX_SQRT_RATIO, 100, false).unwrap();
+        assert_eq!(MAX_SQRT_RATIO - U256::from(1), limit);
+
+        assert_eq!(
+            Err(UniswapV3MathError::InvalidSlippage(10_000)),
+            sqrt_price_limit_from_slippage(Q96, 10_000, true)
+        );
+        assert_eq!(Ok(()), validate_sqrt_price_limit(U256::ZERO, Q96, true));
+        assert_eq!(
+            Err(UniswapV3MathError::LimitOutOfRange(MIN_SQRT_RATIO)),
+            validate_sqrt_price_limit(MIN_SQRT_RATIO, Q96, true)
+        );
+    }
+
+    #[test]
+    fn test_price_limit_from_price() {
+        assert_eq!(
+            Ok(uint!(158456325028528675187087900672_U256)),
+            price_limit_from_price(4.0, 18, 18, true)
+        );
+        // USDC(6)/WETH(18)：1 USDC = 0.0004 WETH
+        let limit = price_limit_from_price(0.0004, 6, 18, true).unwrap();
+        assert_eq!(uint!(1584563250285286751870879006720000_U256), limit);
+        let current = uint!(1771595571142957166518320255467520_U256);
+        validate_sqrt_price_limit(limit, current, true).unwrap();
+        // token0 精度更高时价格向下换算
+        assert_eq!(
+            Ok(uint!(3961408125713217069514752_U256)),
+            price_limit_from_price(2500.0, 18, 6, false)
+        );
+    }
+
+    #[test]
+    fn test_price_limit_from_price_clamped() {
+        assert_eq!(
+            Ok(full_range_sqrt_price_limit(true)),
+            price_limit_from_price(1e-40, 18, 18, true)
+        );
+        assert_eq!(
+            Ok(full_range_sqrt_price_limit(false)),
+            price_limit_from_price(1e40, 18, 18, false)
+        );
+        // 反方向越界同样要截断
+        assert_eq!(
+            Ok(MAX_SQRT_RATIO - U256::from(1)),
+            price_limit_from_price(1e40, 18, 18, true)
+        );
+        assert_eq!(
+            Ok(MIN_SQRT_RATIO + U256::from(1)),
+            price_limit_from_price(1e-40, 18, 18, false)
+        );
+        // 超出 f64 范围的价格
+        assert_eq!(
+            Ok(MAX_SQRT_RATIO - U256::from(1)),
+            price_limit_from_price(1e300, 0, 255, true)
+        );
+        assert_eq!(
+            Ok(MIN_SQRT_RATIO + U256::from(1)),
+            price_limit_from_price(1e-300, 255, 0, false)
+        );
+        for price in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                price_limit_from_price(price, 18, 18, true),
+                Err(UniswapV3MathError::InvalidPrice(_))
+            ));
+        }
+    }
+}
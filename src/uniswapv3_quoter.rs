@@ -1,22 +1,32 @@
 use std::sync::Arc;
 
 use anyhow::Result;
+use futures::future::join_all;
 
-use alloy::sol;
+use alloy::{contract, sol};
 use alloy::{
-    primitives::{aliases::U24, Address, U160, U256},
-    providers::ProviderBuilder,
+    primitives::{aliases::U24, Address, Bytes, U160, U256},
+    providers::{Provider, ProviderBuilder},
+    transports::Transport,
 };
-use std::str::FromStr;
 
-use crate::unswapv3_pool::UniswapPoolFee;
+use crate::{
+    config::ContractAddresses,
+    logging,
+    swap_math::get_next_sqrt_price_from_input,
+    uniswapv3_factory::{self, GetPoolParam, UniswapV3FactoryCommand, UniswapV3FactoryResult},
+    uniswapv3_math::{mul_div, zero_for_one},
+    unswapv3_pool::UniswapPoolFee,
+    utils::transport_revert_reason,
+};
 sol!(
     #[allow(missing_docs)]
     #[sol(rpc)]
     UNIV3_QUOTER,
     "src/abi/uniswapv3_quoter.json"
 );
-const UNIV3_QUOTER_CONTRACT_ADDR: &str = "0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6";
+pub(crate) const UNIV3_QUOTER_CONTRACT_ADDR: &str = "0xb27308f9F90D607463bb33eA1BeBb41C27CE5AB6";
+#[derive(Debug)]
 pub struct QuoteExactInputSingleParams {
     pub token_in: Address,
     pub token_out: Address,
@@ -25,6 +35,7 @@ pub struct QuoteExactInputSingleParams {
     pub sqrt_price_limit_x96: U256,
 }
 
+#[derive(Debug)]
 pub struct QuoteExactOutputSingleParams {
     pub token_in: Address,
     pub token_out: Address,
@@ -33,44 +44,229 @@ pub struct QuoteExactOutputSingleParams {
     pub sqrt_price_limit_x96: U256,
 }
 
+#[derive(Debug)]
+pub struct QuoteExactInputParams {
+    /// Encoded multi-hop path, see `routing::encode_path`
+    pub path: Bytes,
+    pub amount_in: U256,
+}
+
+#[derive(Debug)]
 pub enum UniswapV3QuoterCommand {
     /// quoteExactInputSingle - given the amount you want to swap, produces a quote for the amount out for a swap of a single pool
     QuoteExactInputSingle(QuoteExactInputSingleParams),
     // quoteExactOutputSingle - given the amount you want to get out, produces a quote for the amount in for a swap over a single pool
     QuoteExactOutputSingle(QuoteExactOutputSingleParams),
+    /// quoteExactInput - given the amount you want to swap, produces a quote for the amount out for a swap over a multi-hop path
+    QuoteExactInput(QuoteExactInputParams),
 }
 
 #[derive(Debug)]
 pub enum UniswapV3QuoterResult {
     QuoteExactInputSingle(U256),
     QuoteExactOutputSingle(U256),
+    QuoteExactInput(U256),
+}
+
+impl UniswapV3QuoterResult {
+    /// The quoted amount whatever the variant: the amount out for exact input
+    /// quotes, the amount in for exact output quotes
+    pub fn amount(&self) -> U256 {
+        match self {
+            UniswapV3QuoterResult::QuoteExactInputSingle(amount)
+            | UniswapV3QuoterResult::QuoteExactOutputSingle(amount)
+            | UniswapV3QuoterResult::QuoteExactInput(amount) => *amount,
+        }
+    }
+
+    /// The amount out, if this is the result of an exact input quote (single or multi-hop)
+    pub fn as_input(&self) -> Option<U256> {
+        match self {
+            UniswapV3QuoterResult::QuoteExactInputSingle(amount_out)
+            | UniswapV3QuoterResult::QuoteExactInput(amount_out) => Some(*amount_out),
+            UniswapV3QuoterResult::QuoteExactOutputSingle(_) => None,
+        }
+    }
+
+    /// The amount in, if this is the result of an exact output quote
+    pub fn as_output(&self) -> Option<U256> {
+        match self {
+            UniswapV3QuoterResult::QuoteExactOutputSingle(amount_in) => Some(*amount_in),
+            _ => None,
+        }
+    }
+}
+
+/// An exact input single quote together with the pool price before and after the swap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteWithPrice {
+    pub amount_out: U256,
+    pub sqrt_price_x96_before: U256,
+    /// 假设兑换不跨 tick、按当前流动性计算；池子没有流动性时为 None
+    pub sqrt_price_x96_after: Option<U256>,
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum UniswapV3QuoterError {
-    #[error("错误的池子费用")]
+    #[cfg_attr(not(feature = "locale-en"), error("错误的池子费用"))]
+    #[cfg_attr(feature = "locale-en", error("wrong pool fee"))]
     WrongPoolFee,
-    #[error("无效的命令")]
+    #[cfg_attr(not(feature = "locale-en"), error("无效的命令"))]
+    #[cfg_attr(feature = "locale-en", error("invalid command"))]
     InvalidCommand,
-    #[error("RPC 无法连接 {0}")]
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
     InvalidRpcUrl(String),
 
-    #[error("地址格式不正确{0}")]
+    #[cfg_attr(not(feature = "locale-en"), error("地址格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid address: {0}"))]
     InvalidAddress(String),
+
+    #[cfg_attr(not(feature = "locale-en"), error("报价失败，合约回滚原因: {0}"))]
+    #[cfg_attr(feature = "locale-en", error("quote reverted: {0}"))]
+    QuoteReverted(String),
+
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("报价数量必须大于零且小于 2^255，当前{0}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("quote amount must be greater than zero and below 2^255, got {0}")
+    )]
+    InvalidAmount(U256),
+
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取池子状态{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read pool state: {0}"))]
+    GetPoolStateError(String),
+
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("不支持的 RPC 协议 {0}，请使用 http(s):// 或 ws(s)://")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("unsupported RPC scheme {0}, use http(s):// or ws(s)://")
+    )]
+    UnsupportedRpcScheme(String),
+
+    #[cfg_attr(not(feature = "locale-en"), error("价格限制超过 uint160 上限{0}"))]
+    #[cfg_attr(feature = "locale-en", error("price limit exceeds uint160: {0}"))]
+    InvalidPriceLimit(String),
+}
+
+impl UniswapV3QuoterError {
+    /// Stable identifier of the variant, e.g. `"WRONG_POOL_FEE"`, for matching on
+    /// errors without depending on the message text
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV3QuoterError::WrongPoolFee => "WRONG_POOL_FEE",
+            UniswapV3QuoterError::InvalidCommand => "INVALID_COMMAND",
+            UniswapV3QuoterError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            UniswapV3QuoterError::InvalidAddress(_) => "INVALID_ADDRESS",
+            UniswapV3QuoterError::QuoteReverted(_) => "QUOTE_REVERTED",
+            UniswapV3QuoterError::InvalidAmount(_) => "INVALID_AMOUNT",
+            UniswapV3QuoterError::GetPoolStateError(_) => "GET_POOL_STATE_ERROR",
+            UniswapV3QuoterError::UnsupportedRpcScheme(_) => "UNSUPPORTED_RPC_SCHEME",
+            UniswapV3QuoterError::InvalidPriceLimit(_) => "INVALID_PRICE_LIMIT",
+        }
+    }
+}
+
+/// Reject schemes `on_builtin` cannot connect to before trying, since it would
+/// otherwise fall back to treating the URL as an IPC path. URLs without a scheme
+/// (`localhost:8545` or an IPC socket path) are left to `on_builtin`.
+fn check_rpc_scheme(rpc_url: &str) -> Result<(), UniswapV3QuoterError> {
+    let Some((scheme, _)) = rpc_url.split_once("://") else {
+        return Ok(());
+    };
+    match scheme.to_ascii_lowercase().as_str() {
+        "http" | "https" | "ws" | "wss" => Ok(()),
+        _ => Err(UniswapV3QuoterError::UnsupportedRpcScheme(
+            scheme.to_string(),
+        )),
+    }
+}
+
+/// Keep the on-chain revert reason (e.g. `SPL`) when the node returns one,
+/// otherwise the pool most likely does not exist for this fee tier
+fn map_call_error(e: contract::Error) -> UniswapV3QuoterError {
+    let contract::Error::TransportError(e) = e else {
+        return UniswapV3QuoterError::WrongPoolFee;
+    };
+    transport_revert_reason(&e)
+        .map(UniswapV3QuoterError::QuoteReverted)
+        .unwrap_or(UniswapV3QuoterError::WrongPoolFee)
 }
 
 pub async fn execute(
     command: UniswapV3QuoterCommand,
     rpc_url: String,
 ) -> Result<UniswapV3QuoterResult, UniswapV3QuoterError> {
+    execute_with_config(command, rpc_url, &ContractAddresses::default()).await
+}
+
+/// Same as `execute` but against `addresses.quoter` instead of the mainnet quoter
+pub async fn execute_with_config(
+    command: UniswapV3QuoterCommand,
+    rpc_url: String,
+    addresses: &ContractAddresses,
+) -> Result<UniswapV3QuoterResult, UniswapV3QuoterError> {
+    check_rpc_scheme(&rpc_url)?;
     let provider = ProviderBuilder::new()
         .on_builtin(&rpc_url)
         .await
         .map_err(|e| UniswapV3QuoterError::InvalidRpcUrl(e.to_string()))?;
 
-    let client = Arc::new(provider);
-    let quoter_address = Address::from_str(UNIV3_QUOTER_CONTRACT_ADDR)
-        .map_err(|e| UniswapV3QuoterError::InvalidAddress(e.to_string()))?;
+    execute_with_provider(command, Arc::new(provider), addresses).await
+}
+
+/// Same as `execute_with_config` but reuses an already constructed provider, e.g. one with an
+/// authenticated transport, instead of connecting to a `rpc_url` on every call.
+pub async fn execute_with_provider<T, P>(
+    command: UniswapV3QuoterCommand,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<UniswapV3QuoterResult, UniswapV3QuoterError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    logging::log_command("quoter", addresses.quoter, &command);
+    let res = run_command(command, client, addresses.quoter).await;
+    logging::log_result("quoter", &res);
+    res
+}
+
+/// Reject amounts the pool can not swap before spending a RPC call: zero quotes
+/// nothing, and the pool reverts when casting amounts of 2^255 and above to `int256`
+fn validate_amount(amount: U256) -> Result<(), UniswapV3QuoterError> {
+    if amount.is_zero() || amount.bit(255) {
+        return Err(UniswapV3QuoterError::InvalidAmount(amount));
+    }
+    Ok(())
+}
+
+/// `U160::from` panics on overflow, so out of range limits are reported as an error
+fn to_u160(sqrt_price_limit_x96: U256) -> Result<U160, UniswapV3QuoterError> {
+    U160::checked_from_limbs_slice(sqrt_price_limit_x96.as_limbs())
+        .ok_or_else(|| UniswapV3QuoterError::InvalidPriceLimit(sqrt_price_limit_x96.to_string()))
+}
+
+async fn run_command<T, P>(
+    command: UniswapV3QuoterCommand,
+    client: P,
+    quoter_address: Address,
+) -> Result<UniswapV3QuoterResult, UniswapV3QuoterError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    validate_amount(match &command {
+        UniswapV3QuoterCommand::QuoteExactInputSingle(params) => params.amount_in,
+        UniswapV3QuoterCommand::QuoteExactOutputSingle(params) => params.amount_out,
+        UniswapV3QuoterCommand::QuoteExactInput(params) => params.amount_in,
+    })?;
     let contract = UNIV3_QUOTER::new(quoter_address, client);
     match command {
         UniswapV3QuoterCommand::QuoteExactInputSingle(params) => {
@@ -80,11 +276,11 @@ pub async fn execute(
                     params.token_out,
                     U24::from(params.fee.as_u32()),
                     params.amount_in,
-                    U160::from(params.sqrt_price_limit_x96),
+                    to_u160(params.sqrt_price_limit_x96)?,
                 )
                 .call()
                 .await
-                .map_err(|e| UniswapV3QuoterError::WrongPoolFee)?;
+                .map_err(map_call_error)?;
             Ok(UniswapV3QuoterResult::QuoteExactInputSingle(
                 call_res.amountOut,
             ))
@@ -96,27 +292,442 @@ pub async fn execute(
                     params.token_out,
                     U24::from(params.fee.as_u32()),
                     params.amount_out,
-                    U160::from(params.sqrt_price_limit_x96),
+                    to_u160(params.sqrt_price_limit_x96)?,
                 )
                 .call()
                 .await
-                .map_err(|e| UniswapV3QuoterError::WrongPoolFee)?;
+                .map_err(map_call_error)?;
             Ok(UniswapV3QuoterResult::QuoteExactOutputSingle(
                 call_res.amountIn,
             ))
         }
-        _ => Err(UniswapV3QuoterError::InvalidCommand),
+        UniswapV3QuoterCommand::QuoteExactInput(params) => {
+            let call_res = contract
+                .quoteExactInput(params.path, params.amount_in)
+                .call()
+                .await
+                .map_err(map_call_error)?;
+            Ok(UniswapV3QuoterResult::QuoteExactInput(call_res.amountOut))
+        }
     }
 }
 
+/// Quote `params` and estimate where the swap leaves the pool price, from the
+/// current `slot0` and liquidity. The estimate is exact while the swap stays inside
+/// the current tick range.
+pub async fn quote_exact_input_single_with_price(
+    params: QuoteExactInputSingleParams,
+    rpc_url: String,
+) -> Result<QuoteWithPrice, UniswapV3QuoterError> {
+    check_rpc_scheme(&rpc_url)?;
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3QuoterError::InvalidRpcUrl(e.to_string()))?;
+
+    quote_exact_input_single_with_price_with_provider(
+        params,
+        Arc::new(provider),
+        &ContractAddresses::default(),
+    )
+    .await
+}
+
+/// Same as `quote_exact_input_single_with_price` but reuses an already constructed provider
+pub async fn quote_exact_input_single_with_price_with_provider<T, P>(
+    params: QuoteExactInputSingleParams,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<QuoteWithPrice, UniswapV3QuoterError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let (token_in, token_out, fee, amount_in) = (
+        params.token_in,
+        params.token_out,
+        params.fee,
+        params.amount_in,
+    );
+    let amount_out = match execute_with_provider(
+        UniswapV3QuoterCommand::QuoteExactInputSingle(params),
+        &client,
+        addresses,
+    )
+    .await?
+    {
+        UniswapV3QuoterResult::QuoteExactInputSingle(amount_out) => amount_out,
+        _ => return Err(UniswapV3QuoterError::InvalidCommand),
+    };
+    let command = UniswapV3FactoryCommand::GetPoolWithState(GetPoolParam {
+        token_a: token_in,
+        token_b: token_out,
+        fee: fee.as_u32(),
+    });
+    let pool = match uniswapv3_factory::execute_with_provider(command, &client, addresses)
+        .await
+        .map_err(|e| UniswapV3QuoterError::GetPoolStateError(e.to_string()))?
+    {
+        UniswapV3FactoryResult::GetPoolWithState(pool) => pool,
+        _ => return Err(UniswapV3QuoterError::InvalidCommand),
+    };
+    // 手续费先从输入中扣除，剩余部分才推动价格
+    let sqrt_price_x96_after = mul_div(
+        amount_in,
+        U256::from(1_000_000 - fee.as_u32()),
+        U256::from(1_000_000),
+    )
+    .and_then(|amount_in_less_fee| {
+        get_next_sqrt_price_from_input(
+            pool.sqrt_price_x96,
+            pool.liquidity,
+            amount_in_less_fee,
+            zero_for_one(token_in, token_out),
+        )
+        .ok()
+    });
+    Ok(QuoteWithPrice {
+        amount_out,
+        sqrt_price_x96_before: pool.sqrt_price_x96,
+        sqrt_price_x96_after,
+    })
+}
+
+/// Pick the tier with the highest amount out, skipping tiers whose quote
+/// failed. When every tier failed the first error is returned.
+fn best_fee_tier(
+    quotes: Vec<(UniswapPoolFee, Result<U256, UniswapV3QuoterError>)>,
+) -> Result<(UniswapPoolFee, U256), UniswapV3QuoterError> {
+    let mut best: Option<(UniswapPoolFee, U256)> = None;
+    let mut first_err = None;
+    for (fee, quote) in quotes {
+        match quote {
+            Ok(amount_out) => {
+                if best.is_none_or(|(_, best_amount)| amount_out > best_amount) {
+                    best = Some((fee, amount_out));
+                }
+            }
+            Err(e) => {
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+    best.ok_or_else(|| first_err.unwrap_or(UniswapV3QuoterError::WrongPoolFee))
+}
+
+/// Quote `amount_in` of `token_in` into `token_out` on every fee tier concurrently
+/// and return the tier with the best output
+pub async fn quote_best_fee_tier(
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    rpc_url: String,
+) -> Result<(UniswapPoolFee, U256), UniswapV3QuoterError> {
+    check_rpc_scheme(&rpc_url)?;
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3QuoterError::InvalidRpcUrl(e.to_string()))?;
+    let addresses = ContractAddresses::default();
+    let quotes = join_all(UniswapPoolFee::all().into_iter().map(|fee| {
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in,
+            token_out,
+            fee,
+            amount_in,
+            sqrt_price_limit_x96: U256::ZERO,
+        });
+        let (provider, addresses) = (&provider, &addresses);
+        async move {
+            let amount_out = match execute_with_provider(command, provider, addresses).await {
+                Ok(UniswapV3QuoterResult::QuoteExactInputSingle(amount_out)) => Ok(amount_out),
+                Ok(_) => Err(UniswapV3QuoterError::InvalidCommand),
+                Err(e) => Err(e),
+            };
+            (fee, amount_out)
+        }
+    }))
+    .await;
+    best_fee_tier(quotes)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
+    use crate::test_utils::MockTransport;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_invalid_amounts() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        // 没有预设响应，发出请求就会 panic
+        let transport = MockTransport::default();
+        let addresses = ContractAddresses::default();
+        let max_valid = U256::MAX >> 1;
+        for amount in [U256::ZERO, max_valid + U256::from(1), U256::MAX] {
+            let command =
+                UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+                    token_in: weth,
+                    token_out: usdc,
+                    fee: UniswapPoolFee::Fee500,
+                    amount_in: amount,
+                    sqrt_price_limit_x96: U256::ZERO,
+                });
+            let err = execute_with_provider(command, transport.provider(), &addresses)
+                .await
+                .unwrap_err();
+            assert!(matches!(err, UniswapV3QuoterError::InvalidAmount(a) if a == amount));
+        }
+        let command =
+            UniswapV3QuoterCommand::QuoteExactOutputSingle(QuoteExactOutputSingleParams {
+                token_in: weth,
+                token_out: usdc,
+                fee: UniswapPoolFee::Fee500,
+                amount_out: U256::ZERO,
+                sqrt_price_limit_x96: U256::ZERO,
+            });
+        assert!(matches!(
+            execute_with_provider(command, transport.provider(), &addresses).await,
+            Err(UniswapV3QuoterError::InvalidAmount(_))
+        ));
+        assert!(transport.requests().is_empty());
+
+        validate_amount(U256::from(1)).unwrap();
+        validate_amount(max_valid).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_price_limit() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        // 超过 160 位的价格限制应在发请求前报错，而不是 panic
+        let transport = MockTransport::default();
+        let addresses = ContractAddresses::default();
+        let limit = U256::from(1) << 160;
+        let commands = [
+            UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+                token_in: weth,
+                token_out: usdc,
+                fee: UniswapPoolFee::Fee500,
+                amount_in: U256::from(1),
+                sqrt_price_limit_x96: limit,
+            }),
+            UniswapV3QuoterCommand::QuoteExactOutputSingle(QuoteExactOutputSingleParams {
+                token_in: weth,
+                token_out: usdc,
+                fee: UniswapPoolFee::Fee500,
+                amount_out: U256::from(1),
+                sqrt_price_limit_x96: limit,
+            }),
+        ];
+        for command in commands {
+            let err = execute_with_provider(command, transport.provider(), &addresses)
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(&err, UniswapV3QuoterError::InvalidPriceLimit(l) if *l == limit.to_string())
+            );
+            assert_eq!("INVALID_PRICE_LIMIT", err.code());
+        }
+        assert!(transport.requests().is_empty());
+
+        let max = (U256::from(1) << 160) - U256::from(1);
+        assert_eq!(U160::MAX, to_u160(max).unwrap());
+    }
+
+    #[cfg(not(feature = "locale-en"))]
+    #[test]
+    fn test_default_messages() {
+        assert_eq!(
+            "错误的池子费用",
+            UniswapV3QuoterError::WrongPoolFee.to_string()
+        );
+    }
+
+    #[cfg(feature = "locale-en")]
+    #[test]
+    fn test_english_messages() {
+        assert_eq!(
+            "wrong pool fee",
+            UniswapV3QuoterError::WrongPoolFee.to_string()
+        );
+        assert_eq!(
+            "quote reverted: SPL",
+            UniswapV3QuoterError::QuoteReverted("SPL".to_string()).to_string()
+        );
+        assert_eq!(
+            "RPC chain id 8453 does not match the selected chain 1",
+            crate::client::ClientError::ChainMismatch {
+                expected: 1,
+                actual: 8453
+            }
+            .to_string()
+        );
+        // 错误码与语言无关
+        assert_eq!("WRONG_POOL_FEE", UniswapV3QuoterError::WrongPoolFee.code());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        // 错误码是对外的稳定接口，不能随提示文案修改
+        assert_eq!("WRONG_POOL_FEE", UniswapV3QuoterError::WrongPoolFee.code());
+        assert_eq!(
+            "INVALID_COMMAND",
+            UniswapV3QuoterError::InvalidCommand.code()
+        );
+        assert_eq!(
+            "INVALID_RPC_URL",
+            UniswapV3QuoterError::InvalidRpcUrl(String::new()).code()
+        );
+        assert_eq!(
+            "INVALID_ADDRESS",
+            UniswapV3QuoterError::InvalidAddress(String::new()).code()
+        );
+        assert_eq!(
+            "QUOTE_REVERTED",
+            UniswapV3QuoterError::QuoteReverted("SPL".to_string()).code()
+        );
+    }
+
+    #[test]
+    fn test_result_accessors() {
+        let single = UniswapV3QuoterResult::QuoteExactInputSingle(U256::from(1));
+        let multi_hop = UniswapV3QuoterResult::QuoteExactInput(U256::from(2));
+        let exact_output = UniswapV3QuoterResult::QuoteExactOutputSingle(U256::from(3));
+        assert_eq!(U256::from(1), single.amount());
+        assert_eq!(U256::from(2), multi_hop.amount());
+        assert_eq!(U256::from(3), exact_output.amount());
+        assert_eq!(Some(U256::from(1)), single.as_input());
+        assert_eq!(Some(U256::from(2)), multi_hop.as_input());
+        assert_eq!(None, exact_output.as_input());
+        assert_eq!(None, single.as_output());
+        assert_eq!(None, multi_hop.as_output());
+        assert_eq!(Some(U256::from(3)), exact_output.as_output());
+    }
+
+    #[tokio::test]
+    async fn test_rpc_scheme() {
+        let command = || {
+            UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+                token_in: Address::repeat_byte(1),
+                token_out: Address::repeat_byte(2),
+                fee: UniswapPoolFee::Fee500,
+                amount_in: U256::from(1),
+                sqrt_price_limit_x96: U256::ZERO,
+            })
+        };
+        let err = execute(command(), "ftp://127.0.0.1:1".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            UniswapV3QuoterError::UnsupportedRpcScheme(scheme) if scheme == "ftp"
+        ));
+        assert_eq!("UNSUPPORTED_RPC_SCHEME", err.code());
+
+        // ws(s):// 交给 on_builtin 的 WebSocket transport，只会因为连不上而失败
+        let err = execute(command(), "wss://127.0.0.1:1".to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UniswapV3QuoterError::InvalidRpcUrl(_)));
+        assert!(check_rpc_scheme("WSS://example.com").is_ok());
+        assert!(check_rpc_scheme("localhost:8545").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_quote_with_price_moves_in_swap_direction() {
+        use alloy::{
+            primitives::{aliases::I24, B256},
+            sol_types::SolCall,
+        };
+
+        use crate::{tick_math::get_sqrt_ratio_at_tick, unswapv3_pool::UNIV3_POOL};
+
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let tick = 195_000;
+        let sqrt_price_x96 = get_sqrt_ratio_at_tick(tick).unwrap();
+        let quote = |token_in: Address, token_out: Address, amount_in: U256| {
+            let transport = MockTransport::default();
+            transport.push_response(serde_json::json!(B256::from(U256::from(12345))));
+            transport.push_response(serde_json::json!(pool.into_word()));
+            let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+                U160::from(sqrt_price_x96),
+                I24::try_from(tick).unwrap(),
+                0u16,
+                1u16,
+                1u16,
+                0u8,
+                true,
+            ));
+            transport.push_response(serde_json::json!(Bytes::from(slot0)));
+            let liquidity = UNIV3_POOL::liquidityCall::abi_encode_returns(&(10u128.pow(18),));
+            transport.push_response(serde_json::json!(Bytes::from(liquidity)));
+            async move {
+                quote_exact_input_single_with_price_with_provider(
+                    QuoteExactInputSingleParams {
+                        token_in,
+                        token_out,
+                        fee: UniswapPoolFee::Fee500,
+                        amount_in,
+                        sqrt_price_limit_x96: U256::ZERO,
+                    },
+                    transport.provider(),
+                    &ContractAddresses::default(),
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        // USDC 是 token0，卖出 USDC 价格下降
+        let sell_usdc = quote(usdc, weth, U256::from(1_000_000_000u64)).await;
+        assert_eq!(U256::from(12345), sell_usdc.amount_out);
+        assert_eq!(sqrt_price_x96, sell_usdc.sqrt_price_x96_before);
+        assert!(sell_usdc.sqrt_price_x96_after.unwrap() < sqrt_price_x96);
+
+        let sell_weth = quote(weth, usdc, U256::from(10u128.pow(17))).await;
+        assert!(sell_weth.sqrt_price_x96_after.unwrap() > sqrt_price_x96);
+    }
+
+    #[test]
+    fn test_best_fee_tier() {
+        let best = best_fee_tier(vec![
+            (UniswapPoolFee::Fee10000, Ok(U256::from(90))),
+            (
+                UniswapPoolFee::Fee3000,
+                Err(UniswapV3QuoterError::WrongPoolFee),
+            ),
+            (UniswapPoolFee::Fee500, Ok(U256::from(100))),
+            (UniswapPoolFee::Fee100, Ok(U256::from(95))),
+        ])
+        .unwrap();
+        assert_eq!((UniswapPoolFee::Fee500, U256::from(100)), best);
+
+        let err = best_fee_tier(vec![(
+            UniswapPoolFee::Fee100,
+            Err(UniswapV3QuoterError::QuoteReverted("SPL".to_string())),
+        )])
+        .unwrap_err();
+        assert!(matches!(err, UniswapV3QuoterError::QuoteReverted(_)));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
 
     use crate::{
+        test_utils::spawn_mainnet_fork,
         unswapv3_pool::UniswapPoolFee,
         utils::{from_readable_amount, to_readable_amount},
     };
 
+    use std::str::FromStr;
+
     use super::*;
     #[tokio::test]
     async fn test_get_token_price() {
@@ -130,7 +741,7 @@ mod tests {
             token_in: weth,
             token_out,
             fee: UniswapPoolFee::Fee10000,
-            amount_in: amount_in.into(),
+            amount_in,
             sqrt_price_limit_x96: U256::from(sqrt_price_limit_x96),
         };
         let command = UniswapV3QuoterCommand::QuoteExactInputSingle(quote_exact_input_params);
@@ -144,33 +755,63 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_get_token_price_turbo() {
+    async fn quote_one_weth_to_turbo(rpc_url: String) -> U256 {
         let weth: Address =
             Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
         let turbo: Address =
             Address::from_str("0xA35923162C49cF95e6BF26623385eb431ad920D3").unwrap();
-        let amount_in = from_readable_amount(1.0, 18);
-        let sqrt_price_limit_x96 = 0;
         let quote_exact_input_params = QuoteExactInputSingleParams {
             token_in: weth,
             token_out: turbo,
             fee: UniswapPoolFee::Fee10000,
-            amount_in: amount_in.into(),
-            sqrt_price_limit_x96: U256::from(sqrt_price_limit_x96),
+            amount_in: from_readable_amount(1.0, 18),
+            sqrt_price_limit_x96: U256::ZERO,
         };
         let command = UniswapV3QuoterCommand::QuoteExactInputSingle(quote_exact_input_params);
-        let res = execute(command, "https://eth.llamarpc.com".to_string())
-            .await
-            .unwrap();
-
-        if let UniswapV3QuoterResult::QuoteExactInputSingle(res) = res {
-            let amount_out = to_readable_amount(res, 18);
-            println!("amount_out: {}", amount_out);
-            assert!(amount_out > 0.0);
+        match execute(command, rpc_url).await.unwrap() {
+            UniswapV3QuoterResult::QuoteExactInputSingle(amount_out) => amount_out,
+            res => panic!("unexpected result {:?}", res),
         }
     }
 
+    #[tokio::test]
+    async fn test_quote_with_price_on_fork() {
+        let fork = spawn_mainnet_fork();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let quote = quote_exact_input_single_with_price(
+            QuoteExactInputSingleParams {
+                token_in: weth,
+                token_out: usdc,
+                fee: UniswapPoolFee::Fee500,
+                amount_in: from_readable_amount(1.0, 18),
+                sqrt_price_limit_x96: U256::ZERO,
+            },
+            fork.endpoint(),
+        )
+        .await
+        .unwrap();
+        assert!(quote.amount_out > U256::ZERO);
+        // WETH 是 token1，卖出 WETH 价格上升
+        assert!(quote.sqrt_price_x96_after.unwrap() > quote.sqrt_price_x96_before);
+    }
+
+    /// 在固定区块的 anvil fork 上报价，结果与公共 RPC 的最新状态无关
+    #[tokio::test]
+    async fn test_get_token_price_turbo() {
+        let fork = spawn_mainnet_fork();
+        let amount_out = quote_one_weth_to_turbo(fork.endpoint()).await;
+        println!("amount_out: {}", to_readable_amount(amount_out, 18));
+        assert!(amount_out > U256::ZERO);
+
+        // 另一个 fork 在同一区块上必须得到完全相同的数量
+        let other_fork = spawn_mainnet_fork();
+        assert_eq!(
+            amount_out,
+            quote_one_weth_to_turbo(other_fork.endpoint()).await
+        );
+    }
+
     #[tokio::test]
     async fn test_exact_output_single() {
         let weth: Address =
@@ -183,7 +824,7 @@ mod tests {
             token_in: weth,
             token_out: meme,
             fee: UniswapPoolFee::Fee10000,
-            amount_out: amount_out.into(),
+            amount_out,
             sqrt_price_limit_x96: U256::from(sqrt_price_limit_x96),
         };
         let command = UniswapV3QuoterCommand::QuoteExactOutputSingle(quote_exact_out_params);
@@ -209,7 +850,7 @@ mod tests {
             token_in: weth,
             token_out: meme,
             fee: UniswapPoolFee::Fee10000,
-            amount_out: amount_out.into(),
+            amount_out,
             sqrt_price_limit_x96: U256::from(sqrt_price_limit_x96),
         };
         let command = UniswapV3QuoterCommand::QuoteExactOutputSingle(quote_exact_out_params);
@@ -223,6 +864,29 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_get_token_price_with_tight_price_limit() {
+        let weth: Address =
+            Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let turbo: Address =
+            Address::from_str("0xA35923162C49cF95e6BF26623385eb431ad920D3").unwrap();
+        // TURBO 是 token0，WETH -> TURBO 的价格限制必须高于当前价格，这里传入最小值
+        let quote_exact_input_params = QuoteExactInputSingleParams {
+            token_in: weth,
+            token_out: turbo,
+            fee: UniswapPoolFee::Fee10000,
+            amount_in: from_readable_amount(1.0, 18),
+            sqrt_price_limit_x96: U256::from(4295128740u64),
+        };
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(quote_exact_input_params);
+        let res = execute(command, "https://eth.llamarpc.com".to_string())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(res, UniswapV3QuoterError::QuoteReverted(ref reason) if reason == "SPL"));
+        assert!(res.to_string().contains("SPL"));
+    }
+
     #[tokio::test]
     async fn test_get_token_price_with_wrong_fee() {
         let weth: Address =
@@ -235,7 +899,7 @@ mod tests {
             token_in: weth,
             token_out,
             fee: UniswapPoolFee::Fee100,
-            amount_in: amount_in.into(),
+            amount_in,
             sqrt_price_limit_x96: U256::from(sqrt_price_limit_x96),
         };
         let command = UniswapV3QuoterCommand::QuoteExactInputSingle(quote_exact_input_params);
@@ -248,4 +912,76 @@ mod tests {
             UniswapV3QuoterError::WrongPoolFee.to_string()
         );
     }
+
+    #[tokio::test]
+    async fn test_quote_best_fee_tier() {
+        let weth: Address =
+            Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc: Address =
+            Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let amount_in = from_readable_amount(1.0, 18);
+        let rpc_url = "https://eth.llamarpc.com".to_string();
+        let (fee, amount_out) = quote_best_fee_tier(weth, usdc, amount_in, rpc_url.clone())
+            .await
+            .unwrap();
+        for tier in [
+            UniswapPoolFee::Fee10000,
+            UniswapPoolFee::Fee3000,
+            UniswapPoolFee::Fee500,
+            UniswapPoolFee::Fee100,
+        ] {
+            let command =
+                UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+                    token_in: weth,
+                    token_out: usdc,
+                    fee: tier,
+                    amount_in,
+                    sqrt_price_limit_x96: U256::ZERO,
+                });
+            if let Ok(UniswapV3QuoterResult::QuoteExactInputSingle(other)) =
+                execute(command, rpc_url.clone()).await
+            {
+                assert!(other <= amount_out, "{:?} quoted more than {:?}", tier, fee);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_token_price_with_custom_provider() {
+        use alloy::{
+            rpc::client::RpcClient,
+            transports::http::{
+                reqwest::{
+                    header::{HeaderMap, HeaderValue, AUTHORIZATION},
+                    Client,
+                },
+                Http,
+            },
+        };
+
+        // 例如 Alchemy/Infura 等需要鉴权的 RPC
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer test-token"));
+        let http_client = Client::builder().default_headers(headers).build().unwrap();
+        let transport = Http::with_client(http_client, "https://eth.llamarpc.com".parse().unwrap());
+        let provider = ProviderBuilder::new().on_client(RpcClient::new(transport, false));
+
+        let weth: Address =
+            Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let turbo: Address =
+            Address::from_str("0xA35923162C49cF95e6BF26623385eb431ad920D3").unwrap();
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in: weth,
+            token_out: turbo,
+            fee: UniswapPoolFee::Fee10000,
+            amount_in: from_readable_amount(1.0, 18),
+            sqrt_price_limit_x96: U256::ZERO,
+        });
+        let res = execute_with_provider(command, &provider, &ContractAddresses::default())
+            .await
+            .unwrap();
+        if let UniswapV3QuoterResult::QuoteExactInputSingle(res) = res {
+            assert!(res > U256::ZERO);
+        }
+    }
 }
@@ -1,12 +1,12 @@
 use std::sync::Arc;
 
+use alloy::{network::TransactionBuilder, rpc::types::TransactionRequest, sol, sol_types::SolCall};
 use alloy::{
-    primitives::{aliases::U24, Address, U160, U256},
-    providers::ProviderBuilder,
+    primitives::{aliases::U24, Address, Bytes, U160, U256},
+    providers::{Provider, ProviderBuilder},
+    transports::Transport,
 };
-use alloy::{rpc::types::TransactionRequest, sol};
 use anyhow::Result;
-use std::str::FromStr;
 
 // Codegen from ABI file to interact with the contract.
 sol!(
@@ -16,8 +16,26 @@ sol!(
     "src/abi/uniswapv3_router.json"
 );
 
-use crate::unswapv3_pool::UniswapPoolFee;
-const UNIV3_ROUTER_CONTRACT_ADDR: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
+use crate::{
+    config::ContractAddresses,
+    logging,
+    routing::{decode_path, encode_path, encode_reversed_path},
+    uniswapv3_factory::{self, GetPoolParam, UniswapV3FactoryCommand},
+    uniswapv3_math::{min_amount_with_slippage, sqrt_price_limit_from_slippage, zero_for_one},
+    uniswapv3_quoter::{
+        self, QuoteExactInputSingleParams, UniswapV3QuoterCommand, UniswapV3QuoterResult,
+    },
+    unswapv3_pool::{UniswapPoolFee, UNIV3_POOL},
+    utils::{to_readable_string, transport_revert_reason, Token},
+};
+pub const UNIV3_ROUTER_CONTRACT_ADDR: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
+/// `U160::from` panics on overflow, so out of range limits are reported as an error
+fn to_u160(sqrt_price_limit_x96: U256) -> Result<U160, UniswapV3RouterError> {
+    U160::checked_from_limbs_slice(sqrt_price_limit_x96.as_limbs())
+        .ok_or_else(|| UniswapV3RouterError::InvalidPriceLimit(sqrt_price_limit_x96.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExactInputSingleParams {
     pub token_in: Address,
     pub token_out: Address,
@@ -27,6 +45,11 @@ pub struct ExactInputSingleParams {
     pub amount_in: U256,
     pub amount_out_minimum: U256,
     pub sqrt_price_limit_x96: U256,
+    /// 用原生 ETH 支付：交易带上 `value = amount_in`，token_in 必须是 WETH
+    pub native_value: bool,
+    /// Accept any output when `amount_out_minimum` is zero. Without it a zero
+    /// minimum is rejected with `MissingSlippageProtection`.
+    pub allow_zero_min: bool,
 }
 
 impl TryFrom<ExactInputSingleParams>
@@ -34,6 +57,9 @@ impl TryFrom<ExactInputSingleParams>
 {
     type Error = UniswapV3RouterError;
     fn try_from(value: ExactInputSingleParams) -> std::result::Result<Self, Self::Error> {
+        if value.amount_out_minimum.is_zero() && !value.allow_zero_min {
+            return Err(UniswapV3RouterError::MissingSlippageProtection);
+        }
         let val = crate::uniswapv3_router::ISwapRouter::ExactInputSingleParams {
             tokenIn: value.token_in,
             tokenOut: value.token_out,
@@ -42,12 +68,13 @@ impl TryFrom<ExactInputSingleParams>
             deadline: value.deadline,
             amountIn: value.amount_in,
             amountOutMinimum: value.amount_out_minimum,
-            sqrtPriceLimitX96: U160::from(value.sqrt_price_limit_x96),
+            sqrtPriceLimitX96: to_u160(value.sqrt_price_limit_x96)?,
         };
         Ok(val)
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExactOutputSingleParams {
     pub token_in: Address,
     pub token_out: Address,
@@ -72,69 +99,1725 @@ impl TryFrom<ExactOutputSingleParams>
             deadline: value.deadline,
             amountOut: value.amount_out,
             amountInMaximum: value.amount_in_maximum,
-            sqrtPriceLimitX96: U160::from(value.sqrt_price_limit_x96),
+            sqrtPriceLimitX96: to_u160(value.sqrt_price_limit_x96)?,
         };
         Ok(val)
     }
 }
 
+/// Multi-hop exact input swap. `tokens` and `fees` describe the route in the
+/// trade direction, token_in first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExactInputParams {
+    pub tokens: Vec<Address>,
+    pub fees: Vec<UniswapPoolFee>,
+    pub recipient: Address,
+    pub deadline: U256,
+    pub amount_in: U256,
+    pub amount_out_minimum: U256,
+    /// Same as `ExactInputSingleParams::allow_zero_min`
+    pub allow_zero_min: bool,
+}
+
+impl TryFrom<ExactInputParams> for crate::uniswapv3_router::ISwapRouter::ExactInputParams {
+    type Error = UniswapV3RouterError;
+    fn try_from(value: ExactInputParams) -> std::result::Result<Self, Self::Error> {
+        if value.amount_out_minimum.is_zero() && !value.allow_zero_min {
+            return Err(UniswapV3RouterError::MissingSlippageProtection);
+        }
+        let path = encode_path(&value.tokens, &value.fees)
+            .map_err(|e| UniswapV3RouterError::InvalidPath(e.to_string()))?;
+        Ok(crate::uniswapv3_router::ISwapRouter::ExactInputParams {
+            path,
+            recipient: value.recipient,
+            deadline: value.deadline,
+            amountIn: value.amount_in,
+            amountOutMinimum: value.amount_out_minimum,
+        })
+    }
+}
+
+/// Multi-hop exact output swap. `tokens` and `fees` describe the route in the
+/// trade direction, token_in first; the path is reversed when encoding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExactOutputParams {
+    pub tokens: Vec<Address>,
+    pub fees: Vec<UniswapPoolFee>,
+    pub recipient: Address,
+    pub deadline: U256,
+    pub amount_out: U256,
+    pub amount_in_maximum: U256,
+}
+
+impl TryFrom<ExactOutputParams> for crate::uniswapv3_router::ISwapRouter::ExactOutputParams {
+    type Error = UniswapV3RouterError;
+    fn try_from(value: ExactOutputParams) -> std::result::Result<Self, Self::Error> {
+        let invalid_path =
+            |e: crate::routing::RoutingError| UniswapV3RouterError::InvalidPath(e.to_string());
+        let path = encode_reversed_path(&value.tokens, &value.fees).map_err(invalid_path)?;
+        // 反向路径必须是正向路径的 token 倒序，否则会兑换错 token
+        let (reversed, _) = decode_path(&path).map_err(invalid_path)?;
+        if !reversed.iter().eq(value.tokens.iter().rev()) {
+            return Err(UniswapV3RouterError::InvalidPath(format!(
+                "{:?}",
+                value.tokens
+            )));
+        }
+        Ok(crate::uniswapv3_router::ISwapRouter::ExactOutputParams {
+            path,
+            recipient: value.recipient,
+            deadline: value.deadline,
+            amountOut: value.amount_out,
+            amountInMaximum: value.amount_in_maximum,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UniswapV3RouterCommand {
     /// The swapExactInputSingle function is for performing exact input swaps, which swap a fixed amount of one token for a maximum possible amount of another toke
     ExactInputSingle(ExactInputSingleParams),
     /// The swapExactOutputSingle function is for performing exact output swaps, which swap a minimum possible amount of one token for a fixed amount of another token
     ExactOutputSingle(ExactOutputSingleParams),
+    /// Sell an exact amount of the first token of a multi-hop route with `exactInput`
+    ExactInput(ExactInputParams),
+    /// Buy an exact amount of the last token of a multi-hop route with `exactOutput`
+    ExactOutput(ExactOutputParams),
+    /// Batch several already encoded router calls into a single `multicall(bytes[])`
+    Multicall(Vec<Bytes>),
+}
+
+pub fn encode_exact_input_single(
+    params: ExactInputSingleParams,
+) -> Result<Bytes, UniswapV3RouterError> {
+    Ok(UNIV3_ROUTER::exactInputSingleCall {
+        params: params.try_into()?,
+    }
+    .abi_encode()
+    .into())
+}
+
+pub fn encode_exact_input(params: ExactInputParams) -> Result<Bytes, UniswapV3RouterError> {
+    Ok(UNIV3_ROUTER::exactInputCall {
+        params: params.try_into()?,
+    }
+    .abi_encode()
+    .into())
+}
+
+pub fn encode_exact_output(params: ExactOutputParams) -> Result<Bytes, UniswapV3RouterError> {
+    Ok(UNIV3_ROUTER::exactOutputCall {
+        params: params.try_into()?,
+    }
+    .abi_encode()
+    .into())
+}
+
+pub fn encode_exact_output_single(
+    params: ExactOutputSingleParams,
+) -> Result<Bytes, UniswapV3RouterError> {
+    Ok(UNIV3_ROUTER::exactOutputSingleCall {
+        params: params.try_into()?,
+    }
+    .abi_encode()
+    .into())
+}
+
+/// Encode `unwrapWETH9`, sending all WETH held by the router to `recipient` as ETH
+pub fn encode_unwrap_weth9(amount_minimum: U256, recipient: Address) -> Bytes {
+    UNIV3_ROUTER::unwrapWETH9Call {
+        amountMinimum: amount_minimum,
+        recipient,
+    }
+    .abi_encode()
+    .into()
+}
+
+/// Encode `sweepToken`, sending all of `token` held by the router to `recipient`
+pub fn encode_sweep_token(token: Address, amount_minimum: U256, recipient: Address) -> Bytes {
+    UNIV3_ROUTER::sweepTokenCall {
+        token,
+        amountMinimum: amount_minimum,
+        recipient,
+    }
+    .abi_encode()
+    .into()
+}
+
+/// Encode `refundETH`, returning any ETH left in the router to the sender
+pub fn encode_refund_eth() -> Bytes {
+    UNIV3_ROUTER::refundETHCall {}.abi_encode().into()
+}
+
+/// Swap into WETH and unwrap it to ETH for `params.recipient` in one multicall
+/// sent to `addresses.router`. `params.token_out` must be WETH.
+pub fn exact_input_single_and_unwrap(
+    mut params: ExactInputSingleParams,
+    addresses: &ContractAddresses,
+) -> Result<UniswapV3RouterCommand, UniswapV3RouterError> {
+    let recipient = params.recipient;
+    let amount_minimum = params.amount_out_minimum;
+    // WETH 先留在执行 multicall 的 router 中，再由 unwrapWETH9 发送给 recipient
+    params.recipient = addresses.router;
+    Ok(UniswapV3RouterCommand::Multicall(vec![
+        encode_exact_input_single(params)?,
+        encode_unwrap_weth9(amount_minimum, recipient),
+    ]))
+}
+
+/// Buy an exact amount of WETH and unwrap it to ETH for `params.recipient` in one
+/// multicall sent to `addresses.router`. `params.token_out` must be WETH.
+pub fn exact_output_single_and_unwrap(
+    mut params: ExactOutputSingleParams,
+    addresses: &ContractAddresses,
+) -> Result<UniswapV3RouterCommand, UniswapV3RouterError> {
+    let recipient = params.recipient;
+    let amount_minimum = params.amount_out;
+    params.recipient = addresses.router;
+    Ok(UniswapV3RouterCommand::Multicall(vec![
+        encode_exact_output_single(params)?,
+        encode_unwrap_weth9(amount_minimum, recipient),
+    ]))
+}
+
+/// Quote `params` and set `amount_out_minimum` to the quote less `slippage_bps`,
+/// instead of quoting separately before building the swap
+pub async fn fill_amount_out_minimum(
+    params: ExactInputSingleParams,
+    slippage_bps: u32,
+    rpc_url: String,
+) -> Result<ExactInputSingleParams, UniswapV3RouterError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3RouterError::InvalidRpcUrl(e.to_string()))?;
+
+    fill_amount_out_minimum_with_provider(
+        params,
+        slippage_bps,
+        Arc::new(provider),
+        &ContractAddresses::default(),
+    )
+    .await
+}
+
+/// Same as `fill_amount_out_minimum` but reuses an already constructed provider
+pub async fn fill_amount_out_minimum_with_provider<T, P>(
+    mut params: ExactInputSingleParams,
+    slippage_bps: u32,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<ExactInputSingleParams, UniswapV3RouterError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+        token_in: params.token_in,
+        token_out: params.token_out,
+        fee: params.fee,
+        amount_in: params.amount_in,
+        sqrt_price_limit_x96: params.sqrt_price_limit_x96,
+    });
+    let quote = match uniswapv3_quoter::execute_with_provider(command, client, addresses)
+        .await
+        .map_err(|e| UniswapV3RouterError::QuoteError(e.to_string()))?
+    {
+        UniswapV3QuoterResult::QuoteExactInputSingle(amount_out) => amount_out,
+        res => return Err(UniswapV3RouterError::QuoteError(format!("{:?}", res))),
+    };
+    params.amount_out_minimum = min_amount_with_slippage(quote, slippage_bps)
+        .map_err(|e| UniswapV3RouterError::InvalidSlippage(e.to_string()))?;
+    Ok(params)
+}
+
+/// Read the pool's current price and set `sqrt_price_limit_x96` to the worst
+/// price `slippage_bps` away from it, so the swap stops there instead of
+/// running without price protection
+pub async fn fill_sqrt_price_limit(
+    params: ExactInputSingleParams,
+    slippage_bps: u32,
+    rpc_url: String,
+) -> Result<ExactInputSingleParams, UniswapV3RouterError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3RouterError::InvalidRpcUrl(e.to_string()))?;
+
+    fill_sqrt_price_limit_with_provider(
+        params,
+        slippage_bps,
+        Arc::new(provider),
+        &ContractAddresses::default(),
+    )
+    .await
+}
+
+/// Same as `fill_sqrt_price_limit` but reuses an already constructed provider
+pub async fn fill_sqrt_price_limit_with_provider<T, P>(
+    mut params: ExactInputSingleParams,
+    slippage_bps: u32,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<ExactInputSingleParams, UniswapV3RouterError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let command = UniswapV3FactoryCommand::GetPool(GetPoolParam {
+        token_a: params.token_in,
+        token_b: params.token_out,
+        fee: params.fee.as_u32(),
+    });
+    let pool = uniswapv3_factory::execute_with_provider(command, &client, addresses)
+        .await
+        .map_err(|e| UniswapV3RouterError::GetPoolStateError(e.to_string()))?
+        .pool_address();
+    let slot0 = UNIV3_POOL::new(pool, &client)
+        .slot0()
+        .call()
+        .await
+        .map_err(|e| UniswapV3RouterError::GetPoolStateError(e.to_string()))?;
+    params.sqrt_price_limit_x96 = sqrt_price_limit_from_slippage(
+        U256::from(slot0.sqrtPriceX96),
+        slippage_bps,
+        zero_for_one(params.token_in, params.token_out),
+    )
+    .map_err(|e| UniswapV3RouterError::InvalidSlippage(e.to_string()))?;
+    Ok(params)
 }
 
+/// Set both `amount_out_minimum` and `sqrt_price_limit_x96` from the same
+/// `slippage_bps`: the minimum from a fresh quote, the limit from the pool's
+/// current price. The swap then reverts on a bad average price and also stops
+/// before the pool price is pushed further than the slippage allows.
+pub async fn fill_price_guards(
+    params: ExactInputSingleParams,
+    slippage_bps: u32,
+    rpc_url: String,
+) -> Result<ExactInputSingleParams, UniswapV3RouterError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3RouterError::InvalidRpcUrl(e.to_string()))?;
+
+    fill_price_guards_with_provider(
+        params,
+        slippage_bps,
+        Arc::new(provider),
+        &ContractAddresses::default(),
+    )
+    .await
+}
+
+/// Same as `fill_price_guards` but reuses an already constructed provider
+pub async fn fill_price_guards_with_provider<T, P>(
+    mut params: ExactInputSingleParams,
+    slippage_bps: u32,
+    client: P,
+    addresses: &ContractAddresses,
+) -> Result<ExactInputSingleParams, UniswapV3RouterError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    // 先不带价格限制报价，否则报价会在旧的限制处截断
+    params.sqrt_price_limit_x96 = U256::ZERO;
+    let params =
+        fill_amount_out_minimum_with_provider(params, slippage_bps, &client, addresses).await?;
+    fill_sqrt_price_limit_with_provider(params, slippage_bps, &client, addresses).await
+}
+
+/// Output of a simulated swap: the amount out for `ExactInputSingle` and
+/// `ExactInput`, the amount in for `ExactOutputSingle` and `ExactOutput`
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UniswapV3RouterResult {
     ExactInputSingle(U256),
     ExactOutputSingle(U256),
+    ExactInput(U256),
+    ExactOutput(U256),
+}
+
+impl UniswapV3RouterResult {
+    /// The simulated amount whatever the variant
+    pub fn amount(&self) -> U256 {
+        match self {
+            UniswapV3RouterResult::ExactInputSingle(amount)
+            | UniswapV3RouterResult::ExactOutputSingle(amount)
+            | UniswapV3RouterResult::ExactInput(amount)
+            | UniswapV3RouterResult::ExactOutput(amount) => *amount,
+        }
+    }
+
+    /// The amount out of an exact input swap, single or multi-hop
+    pub fn amount_out(&self) -> Option<U256> {
+        match self {
+            UniswapV3RouterResult::ExactInputSingle(amount_out)
+            | UniswapV3RouterResult::ExactInput(amount_out) => Some(*amount_out),
+            _ => None,
+        }
+    }
+
+    /// The amount in of an exact output swap, single or multi-hop
+    pub fn amount_in(&self) -> Option<U256> {
+        match self {
+            UniswapV3RouterResult::ExactOutputSingle(amount_in)
+            | UniswapV3RouterResult::ExactOutput(amount_in) => Some(*amount_in),
+            UniswapV3RouterResult::ExactInputSingle(_) | UniswapV3RouterResult::ExactInput(_) => {
+                None
+            }
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum UniswapV3RouterError {
-    #[error("RPC URL 格式不正确{0}")]
+    #[cfg_attr(not(feature = "locale-en"), error("RPC URL 格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid RPC url: {0}"))]
     InvalidRpcUrl(String),
-    #[error("地址格式不正确{0}")]
+    #[cfg_attr(not(feature = "locale-en"), error("地址格式不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid address: {0}"))]
     InvalidAddress(String),
-    #[error("池子手续费不正确{0}")]
+    #[cfg_attr(not(feature = "locale-en"), error("池子手续费不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid pool fee: {0}"))]
     WrongPoolFee(String),
+    #[cfg_attr(not(feature = "locale-en"), error("价格限制超过 uint160 上限{0}"))]
+    #[cfg_attr(feature = "locale-en", error("price limit exceeds uint160: {0}"))]
+    InvalidPriceLimit(String),
+    #[cfg_attr(not(feature = "locale-en"), error("模拟兑换失败，合约回滚原因: {0}"))]
+    #[cfg_attr(feature = "locale-en", error("swap simulation reverted: {0}"))]
+    SimulationReverted(String),
+    #[cfg_attr(not(feature = "locale-en"), error("模拟兑换失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("swap simulation failed: {0}"))]
+    SimulationError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法解析 router 调用{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to decode router call: {0}"))]
+    InvalidCalldata(String),
+    #[cfg_attr(not(feature = "locale-en"), error("兑换路径不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid swap path: {0}"))]
+    InvalidPath(String),
+    #[cfg_attr(not(feature = "locale-en"), error("报价失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("quote failed: {0}"))]
+    QuoteError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("滑点设置不正确{0}"))]
+    #[cfg_attr(feature = "locale-en", error("invalid slippage: {0}"))]
+    InvalidSlippage(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取池子状态{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read pool state: {0}"))]
+    GetPoolStateError(String),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("amount_out_minimum 为 0 会接受任意输出，请设置滑点保护或显式设置 allow_zero_min")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("amount_out_minimum is zero and would accept any output, set slippage protection or allow_zero_min explicitly")
+    )]
+    MissingSlippageProtection,
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("用原生 ETH 支付时 token_in 必须是 WETH，当前{0}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("paying with native ETH requires token_in to be WETH, got {0}")
+    )]
+    NativeInputNotWeth(Address),
+}
+
+impl UniswapV3RouterError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV3RouterError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            UniswapV3RouterError::InvalidAddress(_) => "INVALID_ADDRESS",
+            UniswapV3RouterError::WrongPoolFee(_) => "WRONG_POOL_FEE",
+            UniswapV3RouterError::InvalidPriceLimit(_) => "INVALID_PRICE_LIMIT",
+            UniswapV3RouterError::SimulationReverted(_) => "SIMULATION_REVERTED",
+            UniswapV3RouterError::SimulationError(_) => "SIMULATION_ERROR",
+            UniswapV3RouterError::InvalidCalldata(_) => "INVALID_CALLDATA",
+            UniswapV3RouterError::InvalidPath(_) => "INVALID_PATH",
+            UniswapV3RouterError::QuoteError(_) => "QUOTE_ERROR",
+            UniswapV3RouterError::InvalidSlippage(_) => "INVALID_SLIPPAGE",
+            UniswapV3RouterError::GetPoolStateError(_) => "GET_POOL_STATE_ERROR",
+            UniswapV3RouterError::MissingSlippageProtection => "MISSING_SLIPPAGE_PROTECTION",
+            UniswapV3RouterError::NativeInputNotWeth(_) => "NATIVE_INPUT_NOT_WETH",
+        }
+    }
 }
 
+/// The transaction sending `command` to the mainnet router. Only ABI encoding is
+/// involved, nothing connects to `rpc_url`.
+#[deprecated(note = "no RPC request is made, use `build_tx` instead")]
 pub async fn execute(
     command: UniswapV3RouterCommand,
-    rpc_url: String,
+    _rpc_url: String,
+) -> Result<TransactionRequest, UniswapV3RouterError> {
+    logged_build_tx(command, &ContractAddresses::default())
+}
+
+/// Same as `execute` but against `addresses.router` instead of the mainnet router
+#[deprecated(note = "no RPC request is made, use `build_tx` instead")]
+pub async fn execute_with_config(
+    command: UniswapV3RouterCommand,
+    _rpc_url: String,
+    addresses: &ContractAddresses,
+) -> Result<TransactionRequest, UniswapV3RouterError> {
+    logged_build_tx(command, addresses)
+}
+
+fn logged_build_tx(
+    command: UniswapV3RouterCommand,
+    addresses: &ContractAddresses,
+) -> Result<TransactionRequest, UniswapV3RouterError> {
+    // 只生成 calldata，不需要连接节点
+    logging::log_command("router", addresses.router, &command);
+    let res = build_tx(command, addresses);
+    logging::log_result("router", &res);
+    res
+}
+
+impl UniswapV3RouterCommand {
+    /// The sendable transaction, same as `build_tx`
+    pub fn into_transaction_request(
+        self,
+        addresses: &ContractAddresses,
+    ) -> Result<TransactionRequest, UniswapV3RouterError> {
+        build_tx(self, addresses)
+    }
+
+    /// The same transaction sent from `from`, ready for `eth_call`. Exact output swaps
+    /// refund what is left of `amount_in_maximum`, which is only simulated correctly
+    /// with the real sender.
+    pub fn into_call(
+        self,
+        from: Address,
+        addresses: &ContractAddresses,
+    ) -> Result<TransactionRequest, UniswapV3RouterError> {
+        Ok(build_tx(self, addresses)?.with_from(from))
+    }
+}
+
+/// ABI encode `command` without a provider, e.g. to generate calldata offline
+/// for a hardware wallet
+pub fn encode_command(command: UniswapV3RouterCommand) -> Result<Bytes, UniswapV3RouterError> {
+    match command {
+        UniswapV3RouterCommand::ExactInputSingle(params) => encode_exact_input_single(params),
+        UniswapV3RouterCommand::ExactOutputSingle(params) => encode_exact_output_single(params),
+        UniswapV3RouterCommand::ExactInput(params) => encode_exact_input(params),
+        UniswapV3RouterCommand::ExactOutput(params) => encode_exact_output(params),
+        UniswapV3RouterCommand::Multicall(data) => {
+            Ok(UNIV3_ROUTER::multicallCall { data }.abi_encode().into())
+        }
+    }
+}
+
+/// The transaction sending `command` to `addresses.router`, built without a provider.
+/// An `ExactInputSingle` with `native_value` carries `amount_in` as the value and
+/// must sell `addresses.weth`.
+pub fn build_tx(
+    command: UniswapV3RouterCommand,
+    addresses: &ContractAddresses,
 ) -> Result<TransactionRequest, UniswapV3RouterError> {
+    let value = match &command {
+        UniswapV3RouterCommand::ExactInputSingle(params) if params.native_value => {
+            // 路由只会把 ETH 包装成 WETH，其他 token_in 会 revert 或留下 ETH
+            if params.token_in != addresses.weth {
+                return Err(UniswapV3RouterError::NativeInputNotWeth(params.token_in));
+            }
+            params.amount_in
+        }
+        _ => U256::ZERO,
+    };
+    let tx = TransactionRequest::default()
+        .with_to(addresses.router)
+        .with_input(encode_command(command)?);
+    Ok(if value.is_zero() {
+        tx
+    } else {
+        tx.with_value(value)
+    })
+}
+
+/// Decode the return data of a swap call; for a multicall the result of the
+/// first swap in the batch is returned
+fn decode_swap_output(
+    input: &[u8],
+    output: &[u8],
+) -> Result<UniswapV3RouterResult, UniswapV3RouterError> {
+    let invalid = |e: alloy::sol_types::Error| UniswapV3RouterError::InvalidCalldata(e.to_string());
+    let selector = input
+        .get(..4)
+        .ok_or_else(|| UniswapV3RouterError::InvalidCalldata("calldata 长度不足".to_string()))?;
+    if selector == UNIV3_ROUTER::exactInputSingleCall::SELECTOR {
+        let res = UNIV3_ROUTER::exactInputSingleCall::abi_decode_returns(output, true)
+            .map_err(invalid)?;
+        Ok(UniswapV3RouterResult::ExactInputSingle(res.amountOut))
+    } else if selector == UNIV3_ROUTER::exactOutputSingleCall::SELECTOR {
+        let res = UNIV3_ROUTER::exactOutputSingleCall::abi_decode_returns(output, true)
+            .map_err(invalid)?;
+        Ok(UniswapV3RouterResult::ExactOutputSingle(res.amountIn))
+    } else if selector == UNIV3_ROUTER::exactInputCall::SELECTOR {
+        let res =
+            UNIV3_ROUTER::exactInputCall::abi_decode_returns(output, true).map_err(invalid)?;
+        Ok(UniswapV3RouterResult::ExactInput(res.amountOut))
+    } else if selector == UNIV3_ROUTER::exactOutputCall::SELECTOR {
+        let res =
+            UNIV3_ROUTER::exactOutputCall::abi_decode_returns(output, true).map_err(invalid)?;
+        Ok(UniswapV3RouterResult::ExactOutput(res.amountIn))
+    } else if selector == UNIV3_ROUTER::multicallCall::SELECTOR {
+        let calls = UNIV3_ROUTER::multicallCall::abi_decode(input, true).map_err(invalid)?;
+        let results = UNIV3_ROUTER::multicallCall::abi_decode_returns(output, true)
+            .map_err(invalid)?
+            .results;
+        calls
+            .data
+            .iter()
+            .zip(results.iter())
+            .find_map(|(call, result)| decode_swap_output(call, result).ok())
+            .ok_or_else(|| {
+                UniswapV3RouterError::InvalidCalldata("multicall 中没有兑换".to_string())
+            })
+    } else {
+        Err(UniswapV3RouterError::InvalidCalldata(alloy::hex::encode(
+            selector,
+        )))
+    }
+}
+
+/// Decode router calldata back into the command that `encode_command` would
+/// encode it from. `native_value` is not part of the calldata and is always
+/// false, `allow_zero_min` is set when the decoded minimum is zero. Calls inside
+/// a `multicall` are kept as raw calldata, use `decode_router_call` on each of them.
+pub fn decode_router_call(data: &[u8]) -> Result<UniswapV3RouterCommand, UniswapV3RouterError> {
+    let invalid = |e: alloy::sol_types::Error| UniswapV3RouterError::InvalidCalldata(e.to_string());
+    let fee = |fee: U24| {
+        UniswapPoolFee::from_u32(fee.to())
+            .ok_or_else(|| UniswapV3RouterError::InvalidCalldata(format!("fee {}", fee)))
+    };
+    let path = |path: &[u8]| {
+        decode_path(path).map_err(|e| UniswapV3RouterError::InvalidPath(e.to_string()))
+    };
+    let selector = data
+        .get(..4)
+        .ok_or_else(|| UniswapV3RouterError::InvalidCalldata("calldata 长度不足".to_string()))?;
+    if selector == UNIV3_ROUTER::exactInputSingleCall::SELECTOR {
+        let p = UNIV3_ROUTER::exactInputSingleCall::abi_decode(data, true)
+            .map_err(invalid)?
+            .params;
+        Ok(UniswapV3RouterCommand::ExactInputSingle(
+            ExactInputSingleParams {
+                token_in: p.tokenIn,
+                token_out: p.tokenOut,
+                fee: fee(p.fee)?,
+                recipient: p.recipient,
+                deadline: p.deadline,
+                amount_in: p.amountIn,
+                amount_out_minimum: p.amountOutMinimum,
+                sqrt_price_limit_x96: U256::from(p.sqrtPriceLimitX96),
+                native_value: false,
+                allow_zero_min: p.amountOutMinimum.is_zero(),
+            },
+        ))
+    } else if selector == UNIV3_ROUTER::exactOutputSingleCall::SELECTOR {
+        let p = UNIV3_ROUTER::exactOutputSingleCall::abi_decode(data, true)
+            .map_err(invalid)?
+            .params;
+        Ok(UniswapV3RouterCommand::ExactOutputSingle(
+            ExactOutputSingleParams {
+                token_in: p.tokenIn,
+                token_out: p.tokenOut,
+                fee: fee(p.fee)?,
+                recipient: p.recipient,
+                deadline: p.deadline,
+                amount_out: p.amountOut,
+                amount_in_maximum: p.amountInMaximum,
+                sqrt_price_limit_x96: U256::from(p.sqrtPriceLimitX96),
+            },
+        ))
+    } else if selector == UNIV3_ROUTER::exactInputCall::SELECTOR {
+        let p = UNIV3_ROUTER::exactInputCall::abi_decode(data, true)
+            .map_err(invalid)?
+            .params;
+        let (tokens, fees) = path(&p.path)?;
+        Ok(UniswapV3RouterCommand::ExactInput(ExactInputParams {
+            tokens,
+            fees,
+            recipient: p.recipient,
+            deadline: p.deadline,
+            amount_in: p.amountIn,
+            amount_out_minimum: p.amountOutMinimum,
+            allow_zero_min: p.amountOutMinimum.is_zero(),
+        }))
+    } else if selector == UNIV3_ROUTER::exactOutputCall::SELECTOR {
+        let p = UNIV3_ROUTER::exactOutputCall::abi_decode(data, true)
+            .map_err(invalid)?
+            .params;
+        // exactOutput 的路径是反向的，还原成交易方向
+        let (mut tokens, mut fees) = path(&p.path)?;
+        tokens.reverse();
+        fees.reverse();
+        Ok(UniswapV3RouterCommand::ExactOutput(ExactOutputParams {
+            tokens,
+            fees,
+            recipient: p.recipient,
+            deadline: p.deadline,
+            amount_out: p.amountOut,
+            amount_in_maximum: p.amountInMaximum,
+        }))
+    } else if selector == UNIV3_ROUTER::multicallCall::SELECTOR {
+        let call = UNIV3_ROUTER::multicallCall::abi_decode(data, true).map_err(invalid)?;
+        Ok(UniswapV3RouterCommand::Multicall(call.data))
+    } else {
+        Err(UniswapV3RouterError::InvalidCalldata(alloy::hex::encode(
+            selector,
+        )))
+    }
+}
+
+/// One line summary of a router transaction for logs and debugging, e.g.
+/// `Swap 0.02 WETH for at least 1500 ETHC via 1% pool`. Amounts of tokens found
+/// in `tokens` are scaled by their decimals, other tokens are shown by address
+/// with raw amounts. Calls inside a `multicall` are described in order.
+pub fn describe_tx(tx: &TransactionRequest, tokens: &[Token]) -> String {
+    match tx.input.input() {
+        Some(input) => describe_call(input, tokens),
+        None => "No calldata".to_string(),
+    }
+}
+
+fn describe_call(input: &[u8], tokens: &[Token]) -> String {
+    let amount = |amount: U256, token: Address| match tokens.iter().find(|t| t.address == token) {
+        Some(t) => format!(
+            "{} {}",
+            to_readable_string(amount, t.decimals),
+            t.token_name
+        ),
+        None => format!("{} {}", amount, token),
+    };
+    let fee = |fee: u32| match UniswapPoolFee::from_u32(fee) {
+        Some(fee) => format!("{}%", fee.as_percent()),
+        None => format!("fee {}", fee),
+    };
+    let route = |path: &[u8]| match decode_path(path) {
+        Ok((path_tokens, fees)) => {
+            let mut route = token_label(path_tokens[0], tokens);
+            for (token, pool_fee) in path_tokens[1..].iter().zip(fees) {
+                route.push_str(&format!(
+                    " -({})-> {}",
+                    fee(pool_fee.as_u32()),
+                    token_label(*token, tokens)
+                ));
+            }
+            route
+        }
+        Err(_) => "invalid path".to_string(),
+    };
+    let Some(selector) = input.get(..4) else {
+        return "Invalid calldata".to_string();
+    };
+    let decoded = if selector == UNIV3_ROUTER::exactInputSingleCall::SELECTOR {
+        UNIV3_ROUTER::exactInputSingleCall::abi_decode(input, true).map(|call| {
+            let p = call.params;
+            format!(
+                "Swap {} for at least {} via {} pool",
+                amount(p.amountIn, p.tokenIn),
+                amount(p.amountOutMinimum, p.tokenOut),
+                fee(p.fee.to())
+            )
+        })
+    } else if selector == UNIV3_ROUTER::exactOutputSingleCall::SELECTOR {
+        UNIV3_ROUTER::exactOutputSingleCall::abi_decode(input, true).map(|call| {
+            let p = call.params;
+            format!(
+                "Swap at most {} for {} via {} pool",
+                amount(p.amountInMaximum, p.tokenIn),
+                amount(p.amountOut, p.tokenOut),
+                fee(p.fee.to())
+            )
+        })
+    } else if selector == UNIV3_ROUTER::exactInputCall::SELECTOR {
+        UNIV3_ROUTER::exactInputCall::abi_decode(input, true).map(|call| {
+            let p = call.params;
+            // 正向路径：第一个 token 是输入，最后一个是输出
+            let (path_tokens, _) = decode_path(&p.path).unwrap_or_default();
+            format!(
+                "Swap {} for at least {} via {}",
+                amount(p.amountIn, path_tokens.first().copied().unwrap_or_default()),
+                amount(
+                    p.amountOutMinimum,
+                    path_tokens.last().copied().unwrap_or_default()
+                ),
+                route(&p.path)
+            )
+        })
+    } else if selector == UNIV3_ROUTER::exactOutputCall::SELECTOR {
+        UNIV3_ROUTER::exactOutputCall::abi_decode(input, true).map(|call| {
+            let p = call.params;
+            // exactOutput 的路径是反向的：第一个 token 是输出
+            let (path_tokens, _) = decode_path(&p.path).unwrap_or_default();
+            format!(
+                "Swap at most {} for {} via {}",
+                amount(
+                    p.amountInMaximum,
+                    path_tokens.last().copied().unwrap_or_default()
+                ),
+                amount(
+                    p.amountOut,
+                    path_tokens.first().copied().unwrap_or_default()
+                ),
+                route(&p.path)
+            )
+        })
+    } else if selector == UNIV3_ROUTER::unwrapWETH9Call::SELECTOR {
+        UNIV3_ROUTER::unwrapWETH9Call::abi_decode(input, true).map(|call| {
+            format!(
+                "Unwrap at least {} WETH to ETH for {}",
+                to_readable_string(call.amountMinimum, 18),
+                call.recipient
+            )
+        })
+    } else if selector == UNIV3_ROUTER::sweepTokenCall::SELECTOR {
+        UNIV3_ROUTER::sweepTokenCall::abi_decode(input, true).map(|call| {
+            format!(
+                "Sweep at least {} to {}",
+                amount(call.amountMinimum, call.token),
+                call.recipient
+            )
+        })
+    } else if selector == UNIV3_ROUTER::refundETHCall::SELECTOR {
+        Ok("Refund ETH".to_string())
+    } else if selector == UNIV3_ROUTER::multicallCall::SELECTOR {
+        UNIV3_ROUTER::multicallCall::abi_decode(input, true).map(|call| {
+            call.data
+                .iter()
+                .map(|data| describe_call(data, tokens))
+                .collect::<Vec<_>>()
+                .join(", then ")
+        })
+    } else {
+        return format!("Unknown call 0x{}", alloy::hex::encode(selector));
+    };
+    decoded.unwrap_or_else(|e| format!("Invalid calldata: {}", e))
+}
+
+/// The name of `token` if it is in `tokens`, otherwise its address
+fn token_label(token: Address, tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .find(|t| t.address == token)
+        .map(|t| t.token_name.clone())
+        .unwrap_or_else(|| token.to_string())
+}
+
+/// Dry-run a swap transaction built by `execute` (or `client::swap`) from `from`
+/// with `eth_call` against the latest state, returning the actual swap output
+/// or the revert reason
+pub async fn simulate_swap(
+    tx: TransactionRequest,
+    from: Address,
+    rpc_url: String,
+) -> Result<UniswapV3RouterResult, UniswapV3RouterError> {
     let provider = ProviderBuilder::new()
-        .with_recommended_fillers()
         .on_builtin(&rpc_url)
         .await
         .map_err(|e| UniswapV3RouterError::InvalidRpcUrl(e.to_string()))?;
 
-    let client = Arc::new(provider);
-    let router_address = Address::from_str(UNIV3_ROUTER_CONTRACT_ADDR)
-        .map_err(|e| UniswapV3RouterError::InvalidAddress(e.to_string()))?;
-    let contract = UNIV3_ROUTER::new(router_address, client);
+    simulate_swap_with_provider(tx, from, Arc::new(provider)).await
+}
 
-    match command {
-        UniswapV3RouterCommand::ExactInputSingle(params) => Ok(contract
-            .exactInputSingle(params.try_into()?)
-            .into_transaction_request()),
-        UniswapV3RouterCommand::ExactOutputSingle(params) => Ok(contract
-            .exactOutputSingle(params.try_into()?)
-            .into_transaction_request()),
-    }
+/// Same as `simulate_swap` but reuses an already constructed provider
+pub async fn simulate_swap_with_provider<T, P>(
+    tx: TransactionRequest,
+    from: Address,
+    client: P,
+) -> Result<UniswapV3RouterResult, UniswapV3RouterError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let input =
+        tx.input.input().cloned().ok_or_else(|| {
+            UniswapV3RouterError::InvalidCalldata("交易没有 calldata".to_string())
+        })?;
+    let tx = tx.with_from(from);
+    let output = client.call(&tx).await.map_err(|e| {
+        transport_revert_reason(&e)
+            .map(UniswapV3RouterError::SimulationReverted)
+            .unwrap_or_else(|| UniswapV3RouterError::SimulationError(e.to_string()))
+    })?;
+    decode_swap_output(&input, &output)
 }
 
 // 0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
 
     use alloy::hex;
 
-    use crate::utils::{from_readable_amount, Token};
+    use crate::{
+        test_utils::MockTransport,
+        utils::{from_readable_amount, from_readable_str},
+    };
 
     use super::*;
 
+    #[test]
+    fn test_error_codes() {
+        let cases = [
+            (
+                UniswapV3RouterError::InvalidRpcUrl(String::new()),
+                "INVALID_RPC_URL",
+            ),
+            (
+                UniswapV3RouterError::InvalidAddress(String::new()),
+                "INVALID_ADDRESS",
+            ),
+            (
+                UniswapV3RouterError::WrongPoolFee(String::new()),
+                "WRONG_POOL_FEE",
+            ),
+            (
+                UniswapV3RouterError::InvalidPriceLimit(String::new()),
+                "INVALID_PRICE_LIMIT",
+            ),
+            (
+                UniswapV3RouterError::SimulationReverted(String::new()),
+                "SIMULATION_REVERTED",
+            ),
+            (
+                UniswapV3RouterError::SimulationError(String::new()),
+                "SIMULATION_ERROR",
+            ),
+            (
+                UniswapV3RouterError::InvalidCalldata(String::new()),
+                "INVALID_CALLDATA",
+            ),
+            (
+                UniswapV3RouterError::InvalidPath(String::new()),
+                "INVALID_PATH",
+            ),
+            (
+                UniswapV3RouterError::QuoteError(String::new()),
+                "QUOTE_ERROR",
+            ),
+            (
+                UniswapV3RouterError::InvalidSlippage(String::new()),
+                "INVALID_SLIPPAGE",
+            ),
+            (
+                UniswapV3RouterError::NativeInputNotWeth(Address::ZERO),
+                "NATIVE_INPUT_NOT_WETH",
+            ),
+        ];
+        for (err, code) in cases {
+            assert_eq!(code, err.code());
+        }
+    }
+
+    /// Same calldata as `test_exact_input_single_eg2`, built without fetching the tokens
+    /// https://etherscan.io/tx/0x9f130d339b1b444c86593603ce5ade9f4edb7dc2e181b068eb925a5bec1101b3
+    #[test]
+    fn test_encode_exact_input_single() {
+        let params = ExactInputSingleParams {
+            token_in: Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap(),
+            token_out: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            fee: UniswapPoolFee::Fee10000,
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(200.0, 18),
+            amount_out_minimum: U256::from(47639961375419603i64),
+            sqrt_price_limit_x96: U256::ZERO,
+            native_value: false,
+            allow_zero_min: false,
+        };
+        let data = encode_exact_input_single(params).unwrap();
+        assert_eq!("414bf38900000000000000000000000035c8941c294e9d60e0742cb9f3d58c0d1ba2dec4000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc200000000000000000000000000000000000000000000000000000000000027100000000000000000000000001a2f6a0ac3646992e4864159c41bc38990424ca300000000000000000000000000000000000000000000000000000000671a568900000000000000000000000000000000000000000000000ad78ebc5ac620000000000000000000000000000000000000000000000000000000a9404adee52cd30000000000000000000000000000000000000000000000000000000000000000", hex::encode(data));
+    }
+
+    #[test]
+    fn test_result_accessors() {
+        let exact_input = UniswapV3RouterResult::ExactInputSingle(U256::from(1));
+        let exact_output = UniswapV3RouterResult::ExactOutputSingle(U256::from(2));
+        let multi_hop = UniswapV3RouterResult::ExactOutput(U256::from(3));
+        assert_eq!(U256::from(1), exact_input.amount());
+        assert_eq!(U256::from(3), multi_hop.amount());
+        assert_eq!(Some(U256::from(1)), exact_input.amount_out());
+        assert_eq!(None, exact_output.amount_out());
+        assert_eq!(None, exact_input.amount_in());
+        assert_eq!(Some(U256::from(2)), exact_output.amount_in());
+        assert_eq!(Some(U256::from(3)), multi_hop.amount_in());
+        let multi_hop_in = UniswapV3RouterResult::ExactInput(U256::from(4));
+        assert_eq!(Some(U256::from(4)), multi_hop_in.amount_out());
+        assert_eq!(None, multi_hop_in.amount_in());
+    }
+
+    #[test]
+    fn test_build_tx_without_provider() {
+        let params = || ExactInputSingleParams {
+            token_in: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            token_out: Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            fee: UniswapPoolFee::Fee500,
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(1.0, 18),
+            amount_out_minimum: U256::ZERO,
+            sqrt_price_limit_x96: U256::ZERO,
+            native_value: false,
+            allow_zero_min: true,
+        };
+        let expected = encode_exact_input_single(params()).unwrap();
+        // 只做 ABI 编码，不需要 provider，也不会填充 nonce/gas
+        let tx = build_tx(
+            UniswapV3RouterCommand::ExactInputSingle(params()),
+            &ContractAddresses::default(),
+        )
+        .unwrap();
+        assert_eq!(Some(&expected), tx.input.input());
+        assert_eq!(None, tx.nonce);
+        assert_eq!(None, tx.gas);
+    }
+
+    #[test]
+    fn test_build_tx_native_value() {
+        let params = |native_value| ExactInputSingleParams {
+            token_in: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            token_out: Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            fee: UniswapPoolFee::Fee500,
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(1.5, 18),
+            amount_out_minimum: U256::ZERO,
+            sqrt_price_limit_x96: U256::ZERO,
+            native_value,
+            allow_zero_min: true,
+        };
+        let addresses = ContractAddresses::default();
+        let tx = build_tx(
+            UniswapV3RouterCommand::ExactInputSingle(params(true)),
+            &addresses,
+        )
+        .unwrap();
+        assert_eq!(Some(from_readable_amount(1.5, 18)), tx.value);
+        let tx = build_tx(
+            UniswapV3RouterCommand::ExactInputSingle(params(false)),
+            &addresses,
+        )
+        .unwrap();
+        assert_eq!(U256::ZERO, tx.value.unwrap_or_default());
+        // 卖出的不是 WETH 时不能附带 ETH
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let err = build_tx(
+            UniswapV3RouterCommand::ExactInputSingle(ExactInputSingleParams {
+                token_in: usdc,
+                token_out: addresses.weth,
+                ..params(true)
+            }),
+            &addresses,
+        )
+        .unwrap_err();
+        assert!(matches!(err, UniswapV3RouterError::NativeInputNotWeth(token) if token == usdc));
+        // 按配置中的 WETH 校验，而不是主网地址
+        let base = ContractAddresses {
+            weth: Address::repeat_byte(0x42),
+            ..addresses
+        };
+        assert!(build_tx(
+            UniswapV3RouterCommand::ExactInputSingle(params(true)),
+            &base
+        )
+        .is_err());
+        // calldata 与是否附带 ETH 无关
+        assert_eq!(
+            encode_exact_input_single(params(true)).unwrap(),
+            encode_exact_input_single(params(false)).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fill_amount_out_minimum() {
+        let quote = U256::from(47639961375419603i64);
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(quote.to_be_bytes::<32>()));
+        let params = ExactInputSingleParams {
+            token_in: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            token_out: Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap(),
+            fee: UniswapPoolFee::Fee10000,
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(0.02, 18),
+            amount_out_minimum: U256::ZERO,
+            sqrt_price_limit_x96: U256::ZERO,
+            native_value: false,
+            allow_zero_min: false,
+        };
+        let addresses = ContractAddresses::default();
+        let params =
+            fill_amount_out_minimum_with_provider(params, 50, transport.provider(), &addresses)
+                .await
+                .unwrap();
+        // 0.5% 滑点
+        assert_eq!(
+            quote * U256::from(9950) / U256::from(10_000),
+            params.amount_out_minimum
+        );
+        assert!(params.amount_out_minimum < quote);
+        let requests = transport.requests();
+        assert_eq!(
+            addresses.quoter,
+            Address::from_str(requests[0].params[0]["to"].as_str().unwrap()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fill_sqrt_price_limit() {
+        use alloy::primitives::aliases::I24;
+
+        use crate::tick_math::get_sqrt_ratio_at_tick;
+
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let spot = get_sqrt_ratio_at_tick(195_000).unwrap();
+        let fill = |token_in: Address, token_out: Address| {
+            let transport = MockTransport::default();
+            transport.push_response(serde_json::json!(pool.into_word()));
+            let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+                U160::from(spot),
+                I24::try_from(195_000).unwrap(),
+                0u16,
+                1u16,
+                1u16,
+                0u8,
+                true,
+            ));
+            transport.push_response(serde_json::json!(Bytes::from(slot0)));
+            let params = ExactInputSingleParams {
+                token_in,
+                token_out,
+                fee: UniswapPoolFee::Fee500,
+                recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+                deadline: U256::from(1729779337),
+                amount_in: U256::from(1_000_000),
+                amount_out_minimum: U256::ZERO,
+                sqrt_price_limit_x96: U256::ZERO,
+                native_value: false,
+                allow_zero_min: false,
+            };
+            async move {
+                let params = fill_sqrt_price_limit_with_provider(
+                    params,
+                    100,
+                    transport.provider(),
+                    &ContractAddresses::default(),
+                )
+                .await
+                .unwrap();
+                let requests = transport.requests();
+                assert_eq!(
+                    pool,
+                    Address::from_str(requests[1].params[0]["to"].as_str().unwrap()).unwrap()
+                );
+                params.sqrt_price_limit_x96
+            }
+        };
+
+        // 卖出 token0（USDC）价格下降，限制在现价下方
+        let limit = fill(usdc, weth).await;
+        assert!(!limit.is_zero());
+        assert!(limit < spot);
+        crate::uniswapv3_math::validate_sqrt_price_limit(limit, spot, true).unwrap();
+
+        let limit = fill(weth, usdc).await;
+        assert!(limit > spot);
+        crate::uniswapv3_math::validate_sqrt_price_limit(limit, spot, false).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fill_price_guards() {
+        use alloy::primitives::{aliases::I24, B256};
+
+        use crate::{tick_math::get_sqrt_ratio_at_tick, uniswapv3_quoter::UNIV3_QUOTER};
+
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let spot = get_sqrt_ratio_at_tick(195_000).unwrap();
+        let quote = U256::from(3_000_000_000u64);
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(B256::from(quote)));
+        transport.push_response(serde_json::json!(pool.into_word()));
+        let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+            U160::from(spot),
+            I24::try_from(195_000).unwrap(),
+            0u16,
+            1u16,
+            1u16,
+            0u8,
+            true,
+        ));
+        transport.push_response(serde_json::json!(Bytes::from(slot0)));
+        let params = ExactInputSingleParams {
+            token_in: weth,
+            token_out: usdc,
+            fee: UniswapPoolFee::Fee500,
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(1.0, 18),
+            amount_out_minimum: U256::ZERO,
+            // 旧的限制会被忽略
+            sqrt_price_limit_x96: spot + U256::from(1),
+            native_value: false,
+            allow_zero_min: false,
+        };
+
+        let params = fill_price_guards_with_provider(
+            params,
+            50,
+            transport.provider(),
+            &ContractAddresses::default(),
+        )
+        .await
+        .unwrap();
+        // 报价请求不带价格限制
+        let quote_call = UNIV3_QUOTER::quoteExactInputSingleCall::abi_decode(
+            &Bytes::from_str(transport.requests()[0].params[0]["input"].as_str().unwrap()).unwrap(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(U160::ZERO, quote_call.sqrtPriceLimitX96);
+        assert_eq!(
+            min_amount_with_slippage(quote, 50).unwrap(),
+            params.amount_out_minimum
+        );
+        assert_eq!(
+            sqrt_price_limit_from_slippage(spot, 50, false).unwrap(),
+            params.sqrt_price_limit_x96
+        );
+        // 两个保护都按 0.5% 设置：数量少 0.5%，WETH -> USDC 价格上升不超过 0.5%
+        assert_eq!(U256::from(2_985_000_000u64), params.amount_out_minimum);
+        assert!(params.sqrt_price_limit_x96 > spot);
+        let price_move = (params.sqrt_price_limit_x96 * params.sqrt_price_limit_x96 - spot * spot)
+            * U256::from(10_000)
+            / (spot * spot);
+        // sqrt 向下取整，价格变化略小于 50bp
+        assert!(price_move <= U256::from(50) && price_move >= U256::from(49));
+    }
+
+    #[test]
+    fn test_into_call_sets_from() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let from = Address::from_str("0xa7B8b30A4fBEE8AfC8480c0c0D6f5E301C323C1f").unwrap();
+        let command = || {
+            UniswapV3RouterCommand::ExactOutputSingle(ExactOutputSingleParams {
+                token_in: usdc,
+                token_out: weth,
+                fee: UniswapPoolFee::Fee500,
+                recipient: from,
+                deadline: U256::from(1729779337),
+                amount_out: U256::from(10u128.pow(18)),
+                amount_in_maximum: U256::from(3_000_000_000u64),
+                sqrt_price_limit_x96: U256::ZERO,
+            })
+        };
+        let addresses = ContractAddresses::default();
+        let call = command().into_call(from, &addresses).unwrap();
+        let tx = command().into_transaction_request(&addresses).unwrap();
+        assert_eq!(Some(from), call.from);
+        assert_eq!(None, tx.from);
+        assert_eq!(tx.input, call.input);
+        assert_eq!(
+            Some(addresses.router),
+            call.to.and_then(|to| to.to().copied())
+        );
+
+        let json = serde_json::to_value(&call).unwrap();
+        assert_eq!(
+            from,
+            Address::from_str(json["from"].as_str().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encode_exact_output_two_hop() {
+        let usdt = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let ethc = Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap();
+        let params = ExactOutputParams {
+            tokens: vec![usdt, weth, ethc],
+            fees: vec![UniswapPoolFee::Fee500, UniswapPoolFee::Fee10000],
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_out: from_readable_amount(200.0, 18),
+            amount_in_maximum: U256::from(100_000_000),
+        };
+        let data = encode_exact_output(params).unwrap();
+        assert_eq!("f28c0498", hex::encode(&data[..4]));
+        let call = UNIV3_ROUTER::exactOutputCall::abi_decode(&data, true).unwrap();
+        // 从 token_out 开始：ETHC | 10000 | WETH | 500 | USDT
+        assert_eq!(
+            "35c8941c294e9d60e0742cb9f3d58c0d1ba2dec4002710\
+             c02aaa39b223fe8d0a0e5c4f27ead9083c756cc20001f4\
+             dac17f958d2ee523a2206206994597c13d831ec7",
+            hex::encode(&call.params.path)
+        );
+        assert_eq!(U256::from(100_000_000), call.params.amountInMaximum);
+
+        let invalid = ExactOutputParams {
+            tokens: vec![usdt, weth],
+            fees: vec![],
+            recipient: Address::ZERO,
+            deadline: U256::ZERO,
+            amount_out: U256::from(1),
+            amount_in_maximum: U256::from(1),
+        };
+        assert!(matches!(
+            encode_exact_output(invalid),
+            Err(UniswapV3RouterError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_swap_output() {
+        let amount = U256::from(47639961375419603i64);
+        let output = UNIV3_ROUTER::exactInputSingleCall::abi_encode_returns(&(amount,));
+        let swap = UNIV3_ROUTER::exactInputSingleCall {
+            params: ISwapRouter::ExactInputSingleParams {
+                tokenIn: Address::ZERO,
+                tokenOut: Address::ZERO,
+                fee: U24::from(3000),
+                recipient: Address::ZERO,
+                deadline: U256::ZERO,
+                amountIn: U256::from(1),
+                amountOutMinimum: U256::ZERO,
+                sqrtPriceLimitX96: U160::ZERO,
+            },
+        }
+        .abi_encode();
+        assert_eq!(
+            UniswapV3RouterResult::ExactInputSingle(amount),
+            decode_swap_output(&swap, &output).unwrap()
+        );
+
+        let unwrap = encode_unwrap_weth9(U256::ZERO, Address::ZERO);
+        let multicall = UNIV3_ROUTER::multicallCall {
+            data: vec![swap.into(), unwrap],
+        }
+        .abi_encode();
+        let multicall_output =
+            UNIV3_ROUTER::multicallCall::abi_encode_returns(&(vec![output.into(), Bytes::new()],));
+        assert_eq!(
+            UniswapV3RouterResult::ExactInputSingle(amount),
+            decode_swap_output(&multicall, &multicall_output).unwrap()
+        );
+        assert!(matches!(
+            decode_swap_output(&encode_refund_eth(), &[]),
+            Err(UniswapV3RouterError::InvalidCalldata(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_router_call_round_trip() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let ethc = Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap();
+        let recipient = Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap();
+        let deadline = U256::from(1729779337);
+        let exact_input_single = ExactInputSingleParams {
+            token_in: ethc,
+            token_out: weth,
+            fee: UniswapPoolFee::Fee10000,
+            recipient,
+            deadline,
+            amount_in: U256::from(10u128.pow(20)),
+            amount_out_minimum: U256::from(10u128.pow(16)),
+            sqrt_price_limit_x96: U256::from(1u128 << 96),
+            native_value: false,
+            allow_zero_min: false,
+        };
+        let commands = vec![
+            UniswapV3RouterCommand::ExactInputSingle(exact_input_single.clone()),
+            UniswapV3RouterCommand::ExactInputSingle(ExactInputSingleParams {
+                amount_out_minimum: U256::ZERO,
+                allow_zero_min: true,
+                ..exact_input_single.clone()
+            }),
+            UniswapV3RouterCommand::ExactOutputSingle(ExactOutputSingleParams {
+                token_in: weth,
+                token_out: usdc,
+                fee: UniswapPoolFee::Fee500,
+                recipient,
+                deadline,
+                amount_out: U256::from(3_000_000_000u64),
+                amount_in_maximum: U256::from(10u128.pow(18)),
+                sqrt_price_limit_x96: U256::ZERO,
+            }),
+            UniswapV3RouterCommand::ExactInput(ExactInputParams {
+                tokens: vec![ethc, weth, usdc],
+                fees: vec![UniswapPoolFee::Fee10000, UniswapPoolFee::Fee500],
+                recipient,
+                deadline,
+                amount_in: U256::from(10u128.pow(20)),
+                amount_out_minimum: U256::from(1_000_000u64),
+                allow_zero_min: false,
+            }),
+            UniswapV3RouterCommand::ExactOutput(ExactOutputParams {
+                tokens: vec![usdc, weth, ethc],
+                fees: vec![UniswapPoolFee::Fee500, UniswapPoolFee::Fee10000],
+                recipient,
+                deadline,
+                amount_out: U256::from(10u128.pow(20)),
+                amount_in_maximum: U256::from(1_000_000_000u64),
+            }),
+            exact_input_single_and_unwrap(exact_input_single, &ContractAddresses::default())
+                .unwrap(),
+        ];
+        for command in commands {
+            let data = encode_command(command.clone()).unwrap();
+            assert_eq!(command, decode_router_call(&data).unwrap());
+        }
+
+        // multicall 里的每个调用也能单独解码
+        let UniswapV3RouterCommand::Multicall(calls) = decode_router_call(
+            &encode_command(UniswapV3RouterCommand::Multicall(vec![encode_refund_eth()])).unwrap(),
+        )
+        .unwrap() else {
+            panic!("expected a multicall");
+        };
+        assert_eq!(vec![encode_refund_eth()], calls);
+        assert!(matches!(
+            decode_router_call(&calls[0]),
+            Err(UniswapV3RouterError::InvalidCalldata(_))
+        ));
+        assert!(matches!(
+            decode_router_call(&[0x12, 0x34]),
+            Err(UniswapV3RouterError::InvalidCalldata(_))
+        ));
+    }
+
+    #[test]
+    fn test_unwrap_through_configured_router() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let recipient = Address::repeat_byte(1);
+        let addresses = ContractAddresses {
+            router: Address::repeat_byte(7),
+            ..Default::default()
+        };
+        let exact_input = exact_input_single_and_unwrap(
+            ExactInputSingleParams {
+                token_in: usdc,
+                token_out: weth,
+                fee: UniswapPoolFee::Fee500,
+                recipient,
+                deadline: U256::from(1729779337),
+                amount_in: U256::from(3_000_000_000u64),
+                amount_out_minimum: U256::from(10u128.pow(18)),
+                sqrt_price_limit_x96: U256::ZERO,
+                native_value: false,
+                allow_zero_min: false,
+            },
+            &addresses,
+        )
+        .unwrap();
+        let exact_output = exact_output_single_and_unwrap(
+            ExactOutputSingleParams {
+                token_in: usdc,
+                token_out: weth,
+                fee: UniswapPoolFee::Fee500,
+                recipient,
+                deadline: U256::from(1729779337),
+                amount_out: U256::from(10u128.pow(18)),
+                amount_in_maximum: U256::from(3_000_000_000u64),
+                sqrt_price_limit_x96: U256::ZERO,
+            },
+            &addresses,
+        )
+        .unwrap();
+        for command in [exact_input, exact_output] {
+            let UniswapV3RouterCommand::Multicall(calls) = command.clone() else {
+                panic!("expected a multicall");
+            };
+            // WETH 留在执行 multicall 的 router 中，而不是主网 router
+            let swap_recipient = match decode_router_call(&calls[0]).unwrap() {
+                UniswapV3RouterCommand::ExactInputSingle(params) => params.recipient,
+                UniswapV3RouterCommand::ExactOutputSingle(params) => params.recipient,
+                other => panic!("unexpected call {:?}", other),
+            };
+            assert_eq!(addresses.router, swap_recipient);
+            let unwrap = UNIV3_ROUTER::unwrapWETH9Call::abi_decode(&calls[1], true).unwrap();
+            assert_eq!(recipient, unwrap.recipient);
+            let tx = build_tx(command, &addresses).unwrap();
+            assert_eq!(
+                Some(addresses.router),
+                tx.to.and_then(|to| to.to().copied())
+            );
+        }
+    }
+
+    #[test]
+    fn test_describe_tx() {
+        let weth = Token::new(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+            18,
+            "WETH".to_string(),
+        )
+        .unwrap();
+        let ethc = Token::new(
+            "0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4",
+            18,
+            "ETHC".to_string(),
+        )
+        .unwrap();
+        let receiver = Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap();
+        let params = |token_in: &Token, token_out: &Token, amount_in, amount_out_minimum| {
+            ExactInputSingleParams {
+                token_in: token_in.address,
+                token_out: token_out.address,
+                fee: UniswapPoolFee::Fee10000,
+                recipient: receiver,
+                deadline: U256::from(1729779337),
+                amount_in,
+                amount_out_minimum,
+                sqrt_price_limit_x96: U256::ZERO,
+                native_value: false,
+                allow_zero_min: false,
+            }
+        };
+        let addresses = ContractAddresses::default();
+        let tokens = [weth.clone(), ethc.clone()];
+
+        let tx = build_tx(
+            UniswapV3RouterCommand::ExactInputSingle(params(
+                &weth,
+                &ethc,
+                from_readable_amount(0.02, 18),
+                from_readable_str("1500.5", 18).unwrap(),
+            )),
+            &addresses,
+        )
+        .unwrap();
+        assert_eq!(
+            "Swap 0.02 WETH for at least 1500.5 ETHC via 1% pool",
+            describe_tx(&tx, &tokens)
+        );
+        // 不认识的 token 显示地址和原始数量
+        assert_eq!(
+            format!(
+                "Swap 20000000000000000 {} for at least 1500500000000000000000 {} via 1% pool",
+                weth.address, ethc.address
+            ),
+            describe_tx(&tx, &[])
+        );
+
+        let command = exact_input_single_and_unwrap(
+            params(
+                &ethc,
+                &weth,
+                from_readable_amount(200.0, 18),
+                from_readable_amount(0.01, 18),
+            ),
+            &addresses,
+        )
+        .unwrap();
+        let tx = build_tx(command, &addresses).unwrap();
+        assert_eq!(
+            format!(
+                "Swap 200 ETHC for at least 0.01 WETH via 1% pool, then Unwrap at least 0.01 WETH to ETH for {}",
+                receiver
+            ),
+            describe_tx(&tx, &tokens)
+        );
+
+        assert_eq!(
+            "No calldata",
+            describe_tx(&TransactionRequest::default(), &tokens)
+        );
+        let unknown = TransactionRequest::default().with_input(Bytes::from(vec![1, 2, 3, 4]));
+        assert_eq!("Unknown call 0x01020304", describe_tx(&unknown, &tokens));
+    }
+
+    #[test]
+    fn test_zero_min_requires_opt_in() {
+        let params = |amount_out_minimum, allow_zero_min| ExactInputSingleParams {
+            token_in: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            token_out: Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            fee: UniswapPoolFee::Fee500,
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(1.0, 18),
+            amount_out_minimum,
+            sqrt_price_limit_x96: U256::ZERO,
+            native_value: false,
+            allow_zero_min,
+        };
+        let addresses = ContractAddresses::default();
+        let build = |params| build_tx(UniswapV3RouterCommand::ExactInputSingle(params), &addresses);
+
+        let err = build(params(U256::ZERO, false)).unwrap_err();
+        assert!(matches!(
+            err,
+            UniswapV3RouterError::MissingSlippageProtection
+        ));
+        assert_eq!("MISSING_SLIPPAGE_PROTECTION", err.code());
+        // 包装成 multicall 时同样检查
+        assert!(matches!(
+            exact_input_single_and_unwrap(params(U256::ZERO, false), &addresses),
+            Err(UniswapV3RouterError::MissingSlippageProtection)
+        ));
+
+        // 显式允许时正常编码
+        let tx = build(params(U256::ZERO, true)).unwrap();
+        let call = UNIV3_ROUTER::exactInputSingleCall::abi_decode(tx.input.input().unwrap(), true)
+            .unwrap();
+        assert_eq!(U256::ZERO, call.params.amountOutMinimum);
+        // 设置了最小输出时不需要 allow_zero_min
+        assert!(build(params(U256::from(1), false)).is_ok());
+    }
+
+    #[test]
+    fn test_encode_price_limit_overflow() {
+        let params = ExactInputSingleParams {
+            token_in: Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap(),
+            token_out: Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap(),
+            fee: UniswapPoolFee::Fee10000,
+            recipient: Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap(),
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(200.0, 18),
+            amount_out_minimum: U256::ZERO,
+            sqrt_price_limit_x96: U256::from(1) << 160,
+            native_value: false,
+            allow_zero_min: true,
+        };
+        let err = encode_exact_input_single(params).unwrap_err();
+        assert!(matches!(err, UniswapV3RouterError::InvalidPriceLimit(_)));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_execute_logs_command() {
+        use std::{
+            io,
+            sync::{Arc, Mutex},
+        };
+
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+        impl io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let command = UniswapV3RouterCommand::Multicall(vec![encode_refund_eth()]);
+        #[allow(deprecated)]
+        execute(command, String::new()).await.unwrap();
+        let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("module=\"router\""));
+        assert!(logs.contains(UNIV3_ROUTER_CONTRACT_ADDR));
+        assert!(logs.contains("Multicall"));
+    }
+
+    #[tokio::test]
+    pub async fn test_multicall_swap_and_unwrap() {
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let ethc = Address::from_str("0x35c8941c294E9d60E0742CB9f3d58c0D1Ba2DEc4").unwrap();
+        let receiver = Address::from_str("0x1a2f6A0AC3646992E4864159C41bC38990424CA3").unwrap();
+        let params = ExactInputSingleParams {
+            token_in: ethc,
+            token_out: weth,
+            fee: UniswapPoolFee::Fee10000,
+            recipient: receiver,
+            deadline: U256::from(1729779337),
+            amount_in: from_readable_amount(200.0, 18),
+            amount_out_minimum: U256::from(47639961375419603i64),
+            sqrt_price_limit_x96: U256::ZERO,
+            native_value: false,
+            allow_zero_min: false,
+        };
+        let command = exact_input_single_and_unwrap(params, &ContractAddresses::default()).unwrap();
+        let res = build_tx(command, &ContractAddresses::default()).unwrap();
+        let data = hex::encode(res.input.input().unwrap());
+        // multicall(bytes[])
+        assert!(data.starts_with("ac9650d8"));
+        // exactInputSingle
+        assert!(data.contains("414bf389"));
+        // unwrapWETH9(uint256,address)
+        assert!(data.contains("49404b7c"));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use std::str::FromStr;
+
+    use alloy::hex;
+
+    use crate::{
+        client::{swap, SwapDirection, SwapParams, UniswapSupportChain, UniswapVersion},
+        test_utils::spawn_mainnet_fork,
+        utils::{from_readable_amount, Token},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_simulate_swap_on_fork() {
+        let fork = spawn_mainnet_fork();
+        // anvil 的默认账户有 10000 ETH
+        let from = fork.addresses()[0];
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let params = SwapParams::builder()
+            .token_in(Address::ZERO)
+            .token_out(usdc)
+            .amount_in(from_readable_amount(1.0, 18))
+            .pool_fee(UniswapPoolFee::Fee500)
+            .recipient(from)
+            .native_in(true)
+            .build()
+            .unwrap();
+        let tx = swap(
+            UniswapSupportChain::Ethereum,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            fork.endpoint(),
+        )
+        .await
+        .unwrap();
+        let UniswapV3RouterResult::ExactInputSingle(amount_out) =
+            simulate_swap(tx.clone(), from, fork.endpoint())
+                .await
+                .unwrap()
+        else {
+            panic!("unexpected result");
+        };
+        assert!(amount_out > U256::ZERO);
+
+        // 没有附带 ETH 时 router 无法支付，模拟应当返回回滚
+        let mut tx = tx;
+        tx.value = None;
+        let err = simulate_swap(tx, from, fork.endpoint()).await.unwrap_err();
+        assert!(matches!(
+            err,
+            UniswapV3RouterError::SimulationReverted(_) | UniswapV3RouterError::SimulationError(_)
+        ));
+    }
+
     #[tokio::test]
     pub async fn test_exact_input_single() {
         let rpc_url = "https://eth.llamarpc.com";
@@ -157,14 +1840,15 @@ mod tests {
             amount_in,
             amount_out_minimum: U256::ZERO,
             sqrt_price_limit_x96: U256::ZERO,
+            native_value: false,
+            allow_zero_min: true,
         };
-        let res = execute(
+        let res = build_tx(
             UniswapV3RouterCommand::ExactInputSingle(params),
-            rpc_url.to_string(),
+            &ContractAddresses::default(),
         )
-        .await
         .unwrap();
-        println!("{:?}", res);
+        println!("{}", describe_tx(&res, &[weth, ethc]));
     }
 
     /// https://etherscan.io/tx/0x9f130d339b1b444c86593603ce5ade9f4edb7dc2e181b068eb925a5bec1101b3
@@ -190,12 +1874,13 @@ mod tests {
             amount_in,
             amount_out_minimum: U256::from(47639961375419603i64),
             sqrt_price_limit_x96: U256::ZERO,
+            native_value: false,
+            allow_zero_min: false,
         };
-        let res = execute(
+        let res = build_tx(
             UniswapV3RouterCommand::ExactInputSingle(params),
-            rpc_url.to_string(),
+            &ContractAddresses::default(),
         )
-        .await
         .unwrap();
         let input = res.input;
         let data = input.input.unwrap();
@@ -226,11 +1911,10 @@ mod tests {
             amount_in_maximum: U256::from(5201336670800000000i64),
             sqrt_price_limit_x96: U256::ZERO,
         };
-        let res = execute(
+        let res = build_tx(
             UniswapV3RouterCommand::ExactOutputSingle(params),
-            rpc_url.to_string(),
+            &ContractAddresses::default(),
         )
-        .await
         .unwrap();
         let input = res.input;
         let data = input.input.unwrap();
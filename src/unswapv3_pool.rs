@@ -1,3 +1,733 @@
+use std::{fmt, future::IntoFuture, str::FromStr, sync::Arc};
+
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{aliases::I24, Address, TxHash, I256, U256},
+    providers::{Provider, ProviderBuilder, WsConnect},
+    rpc::types::{Filter, Log, TransactionRequest},
+    sol,
+    sol_types::{SolCall, SolEvent},
+    transports::Transport,
+};
+use futures::{Stream, StreamExt};
+
+use crate::tick_math::{MAX_TICK, MIN_TICK};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    UNIV3_POOL,
+    "src/abi/uniswapv3_pool.json"
+);
+
+/// Global fee growth and accrued protocol fees of a pool, used for LP accounting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolFeeGrowth {
+    pub fee_growth_global0_x128: U256,
+    pub fee_growth_global1_x128: U256,
+    /// 尚未被提取的协议手续费
+    pub protocol_fees_token0: u128,
+    pub protocol_fees_token1: u128,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UniswapV3PoolError {
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 无法连接 {0}"))]
+    #[cfg_attr(feature = "locale-en", error("cannot connect to RPC {0}"))]
+    InvalidRpcUrl(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法读取池子状态{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to read pool state: {0}"))]
+    GetPoolStateError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("tick spacing 必须大于 0，当前{0}"))]
+    #[cfg_attr(
+        feature = "locale-en",
+        error("tick spacing must be greater than 0, got {0}")
+    )]
+    InvalidTickSpacing(i32),
+    #[cfg_attr(not(feature = "locale-en"), error("tick 超出 int24 范围{0}"))]
+    #[cfg_attr(feature = "locale-en", error("tick out of int24 range: {0}"))]
+    InvalidTick(i32),
+    #[cfg_attr(
+        not(feature = "locale-en"),
+        error("目标 observation cardinality {target} 必须大于当前的 {current}")
+    )]
+    #[cfg_attr(
+        feature = "locale-en",
+        error(
+            "target observation cardinality {target} must be greater than the current {current}"
+        )
+    )]
+    CardinalityNotIncreased { target: u16, current: u16 },
+    #[cfg_attr(not(feature = "locale-en"), error("订阅事件失败{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to subscribe to events: {0}"))]
+    SubscriptionError(String),
+    #[cfg_attr(not(feature = "locale-en"), error("无法解析池子事件{0}"))]
+    #[cfg_attr(feature = "locale-en", error("failed to decode pool event: {0}"))]
+    InvalidEvent(String),
+}
+
+impl UniswapV3PoolError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            UniswapV3PoolError::InvalidRpcUrl(_) => "INVALID_RPC_URL",
+            UniswapV3PoolError::GetPoolStateError(_) => "GET_POOL_STATE_ERROR",
+            UniswapV3PoolError::InvalidTickSpacing(_) => "INVALID_TICK_SPACING",
+            UniswapV3PoolError::InvalidTick(_) => "INVALID_TICK",
+            UniswapV3PoolError::CardinalityNotIncreased { .. } => "CARDINALITY_NOT_INCREASED",
+            UniswapV3PoolError::SubscriptionError(_) => "SUBSCRIPTION_ERROR",
+            UniswapV3PoolError::InvalidEvent(_) => "INVALID_EVENT",
+        }
+    }
+}
+
+/// A decoded `Swap` event of a pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapEvent {
+    pub sender: Address,
+    pub recipient: Address,
+    /// 正数是转入池子的数量，负数是池子转出的数量
+    pub amount0: I256,
+    pub amount1: I256,
+    /// 兑换之后的价格
+    pub sqrt_price_x96: U256,
+    pub liquidity: u128,
+    pub tick: i32,
+    /// 待确认的日志没有区块信息
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<TxHash>,
+}
+
+impl SwapEvent {
+    pub fn from_log(log: &Log) -> Result<Self, UniswapV3PoolError> {
+        let swap = log
+            .log_decode::<UNIV3_POOL::Swap>()
+            .map_err(|e| UniswapV3PoolError::InvalidEvent(e.to_string()))?
+            .inner
+            .data;
+        Ok(SwapEvent {
+            sender: swap.sender,
+            recipient: swap.recipient,
+            amount0: swap.amount0,
+            amount1: swap.amount1,
+            sqrt_price_x96: U256::from(swap.sqrtPriceX96),
+            liquidity: swap.liquidity,
+            tick: swap.tick.as_i32(),
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash,
+        })
+    }
+}
+
+/// The `Swap` events of `pool` emitted in `from_block..=to_block`, in log order
+pub async fn get_swaps(
+    pool: Address,
+    from_block: u64,
+    to_block: u64,
+    rpc_url: String,
+) -> Result<Vec<SwapEvent>, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    get_swaps_with_provider(pool, from_block, to_block, Arc::new(provider)).await
+}
+
+/// Same as `get_swaps` but reuses an already constructed provider
+pub async fn get_swaps_with_provider<T, P>(
+    pool: Address,
+    from_block: u64,
+    to_block: u64,
+    client: P,
+) -> Result<Vec<SwapEvent>, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    get_pool_logs::<UNIV3_POOL::Swap, _, _>(pool, from_block, to_block, client)
+        .await?
+        .iter()
+        .map(SwapEvent::from_log)
+        .collect()
+}
+
+/// A decoded `Mint` event: liquidity added to `tick_lower..tick_upper`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintEvent {
+    /// 调用 mint 的合约，通常是 NonfungiblePositionManager
+    pub sender: Address,
+    pub owner: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub amount0: U256,
+    pub amount1: U256,
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<TxHash>,
+}
+
+impl MintEvent {
+    pub fn from_log(log: &Log) -> Result<Self, UniswapV3PoolError> {
+        let mint = log
+            .log_decode::<UNIV3_POOL::Mint>()
+            .map_err(|e| UniswapV3PoolError::InvalidEvent(e.to_string()))?
+            .inner
+            .data;
+        Ok(MintEvent {
+            sender: mint.sender,
+            owner: mint.owner,
+            tick_lower: mint.tickLower.as_i32(),
+            tick_upper: mint.tickUpper.as_i32(),
+            liquidity: mint.amount,
+            amount0: mint.amount0,
+            amount1: mint.amount1,
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash,
+        })
+    }
+}
+
+/// A decoded `Burn` event: liquidity removed from `tick_lower..tick_upper`. The
+/// amounts are only credited to the position, they leave the pool on `collect`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BurnEvent {
+    pub owner: Address,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub amount0: U256,
+    pub amount1: U256,
+    pub block_number: Option<u64>,
+    pub transaction_hash: Option<TxHash>,
+}
+
+impl BurnEvent {
+    pub fn from_log(log: &Log) -> Result<Self, UniswapV3PoolError> {
+        let burn = log
+            .log_decode::<UNIV3_POOL::Burn>()
+            .map_err(|e| UniswapV3PoolError::InvalidEvent(e.to_string()))?
+            .inner
+            .data;
+        Ok(BurnEvent {
+            owner: burn.owner,
+            tick_lower: burn.tickLower.as_i32(),
+            tick_upper: burn.tickUpper.as_i32(),
+            liquidity: burn.amount,
+            amount0: burn.amount0,
+            amount1: burn.amount1,
+            block_number: log.block_number,
+            transaction_hash: log.transaction_hash,
+        })
+    }
+}
+
+/// The `Mint` events of `pool` emitted in `from_block..=to_block`, in log order
+pub async fn get_mints(
+    pool: Address,
+    from_block: u64,
+    to_block: u64,
+    rpc_url: String,
+) -> Result<Vec<MintEvent>, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    get_mints_with_provider(pool, from_block, to_block, Arc::new(provider)).await
+}
+
+/// Same as `get_mints` but reuses an already constructed provider
+pub async fn get_mints_with_provider<T, P>(
+    pool: Address,
+    from_block: u64,
+    to_block: u64,
+    client: P,
+) -> Result<Vec<MintEvent>, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    get_pool_logs::<UNIV3_POOL::Mint, _, _>(pool, from_block, to_block, client)
+        .await?
+        .iter()
+        .map(MintEvent::from_log)
+        .collect()
+}
+
+/// The `Burn` events of `pool` emitted in `from_block..=to_block`, in log order
+pub async fn get_burns(
+    pool: Address,
+    from_block: u64,
+    to_block: u64,
+    rpc_url: String,
+) -> Result<Vec<BurnEvent>, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    get_burns_with_provider(pool, from_block, to_block, Arc::new(provider)).await
+}
+
+/// Same as `get_burns` but reuses an already constructed provider
+pub async fn get_burns_with_provider<T, P>(
+    pool: Address,
+    from_block: u64,
+    to_block: u64,
+    client: P,
+) -> Result<Vec<BurnEvent>, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    get_pool_logs::<UNIV3_POOL::Burn, _, _>(pool, from_block, to_block, client)
+        .await?
+        .iter()
+        .map(BurnEvent::from_log)
+        .collect()
+}
+
+/// Raw logs of event `E` emitted by `pool` in `from_block..=to_block`
+async fn get_pool_logs<E, T, P>(
+    pool: Address,
+    from_block: u64,
+    to_block: u64,
+    client: P,
+) -> Result<Vec<Log>, UniswapV3PoolError>
+where
+    E: SolEvent,
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let filter = Filter::new()
+        .address(pool)
+        .event_signature(E::SIGNATURE_HASH)
+        .from_block(from_block)
+        .to_block(to_block);
+    client
+        .get_logs(&filter)
+        .await
+        .map_err(|e| UniswapV3PoolError::GetPoolStateError(e.to_string()))
+}
+
+/// Stream the `Swap` events of `pool` as they are mined, over a WebSocket
+/// connection to `ws_url` (`ws://` or `wss://`)
+pub async fn subscribe_swaps(
+    pool: Address,
+    ws_url: String,
+) -> Result<impl Stream<Item = Result<SwapEvent, UniswapV3PoolError>>, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_ws(WsConnect::new(ws_url))
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    subscribe_swaps_with_provider(pool, provider).await
+}
+
+/// Same as `subscribe_swaps` but reuses an already constructed provider, which
+/// must be backed by a pubsub transport (WebSocket or IPC)
+pub async fn subscribe_swaps_with_provider<T, P>(
+    pool: Address,
+    client: P,
+) -> Result<impl Stream<Item = Result<SwapEvent, UniswapV3PoolError>>, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let filter = Filter::new()
+        .address(pool)
+        .event_signature(UNIV3_POOL::Swap::SIGNATURE_HASH);
+    let subscription = client
+        .subscribe_logs(&filter)
+        .await
+        .map_err(|e| UniswapV3PoolError::SubscriptionError(e.to_string()))?;
+    // provider 被 drop 后连接会关闭、订阅随之结束，所以让 stream 持有它
+    Ok(subscription.into_stream().map(move |log| {
+        let _ = &client;
+        SwapEvent::from_log(&log)
+    }))
+}
+
+pub async fn get_fee_growth(
+    pool: Address,
+    rpc_url: String,
+) -> Result<PoolFeeGrowth, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    get_fee_growth_with_provider(pool, Arc::new(provider)).await
+}
+
+/// Same as `get_fee_growth` but reuses an already constructed provider
+pub async fn get_fee_growth_with_provider<T, P>(
+    pool: Address,
+    client: P,
+) -> Result<PoolFeeGrowth, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let pool = UNIV3_POOL::new(pool, client);
+    let fee_growth0 = pool.feeGrowthGlobal0X128();
+    let fee_growth1 = pool.feeGrowthGlobal1X128();
+    let protocol_fees = pool.protocolFees();
+    let (fee_growth0, fee_growth1, protocol_fees) = futures::try_join!(
+        fee_growth0.call().into_future(),
+        fee_growth1.call().into_future(),
+        protocol_fees.call().into_future()
+    )
+    .map_err(|e| UniswapV3PoolError::GetPoolStateError(e.to_string()))?;
+    Ok(PoolFeeGrowth {
+        fee_growth_global0_x128: fee_growth0._0,
+        fee_growth_global1_x128: fee_growth1._0,
+        protocol_fees_token0: protocol_fees.token0,
+        protocol_fees_token1: protocol_fees.token1,
+    })
+}
+
+/// Build `increaseObservationCardinalityNext(target)` for `pool` after checking
+/// against `slot0` that it actually grows the oracle buffer; the pool silently
+/// does nothing otherwise
+pub async fn increase_observation_cardinality_tx(
+    pool: Address,
+    target: u16,
+    rpc_url: String,
+) -> Result<TransactionRequest, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    increase_observation_cardinality_tx_with_provider(pool, target, Arc::new(provider)).await
+}
+
+/// Same as `increase_observation_cardinality_tx` but reuses an already constructed provider
+pub async fn increase_observation_cardinality_tx_with_provider<T, P>(
+    pool: Address,
+    target: u16,
+    client: P,
+) -> Result<TransactionRequest, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let slot0 = UNIV3_POOL::new(pool, client)
+        .slot0()
+        .call()
+        .await
+        .map_err(|e| UniswapV3PoolError::GetPoolStateError(e.to_string()))?;
+    // 已经申请过扩容时 observationCardinalityNext 大于 observationCardinality
+    let current = slot0.observationCardinalityNext;
+    if target <= current {
+        return Err(UniswapV3PoolError::CardinalityNotIncreased { target, current });
+    }
+    Ok(TransactionRequest::default().with_to(pool).with_input(
+        UNIV3_POOL::increaseObservationCardinalityNextCall {
+            observationCardinalityNext: target,
+        }
+        .abi_encode(),
+    ))
+}
+
+/// Read the pool's `maxLiquidityPerTick`
+pub async fn get_max_liquidity_per_tick(
+    pool: Address,
+    rpc_url: String,
+) -> Result<u128, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    get_max_liquidity_per_tick_with_provider(pool, Arc::new(provider)).await
+}
+
+/// Same as `get_max_liquidity_per_tick` but reuses an already constructed provider
+pub async fn get_max_liquidity_per_tick_with_provider<T, P>(
+    pool: Address,
+    client: P,
+) -> Result<u128, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    UNIV3_POOL::new(pool, client)
+        .maxLiquidityPerTick()
+        .call()
+        .await
+        .map(|max| max._0)
+        .map_err(|e| UniswapV3PoolError::GetPoolStateError(e.to_string()))
+}
+
+/// `Tick.tickSpacingToMaxLiquidityPerTick`: the liquidity cap of a single tick, so
+/// that every usable tick fully used still fits in a `uint128`
+pub fn max_liquidity_per_tick(tick_spacing: i32) -> Result<u128, UniswapV3PoolError> {
+    if tick_spacing <= 0 {
+        return Err(UniswapV3PoolError::InvalidTickSpacing(tick_spacing));
+    }
+    // 与 Solidity 一样向零取整
+    let min_tick = (MIN_TICK / tick_spacing) * tick_spacing;
+    let max_tick = (MAX_TICK / tick_spacing) * tick_spacing;
+    let num_ticks = ((max_tick - min_tick) / tick_spacing) as u128 + 1;
+    Ok(u128::MAX / num_ticks)
+}
+
+/// Liquidity stored at one tick, what a cross-tick swap needs when crossing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickInfo {
+    /// 引用该 tick 的所有头寸的流动性总和
+    pub liquidity_gross: u128,
+    /// 从左往右穿过该 tick 时需要加上的流动性
+    pub liquidity_net: i128,
+    pub initialized: bool,
+}
+
+/// Read `ticks(tick)` of the pool
+pub async fn get_tick_info(
+    pool: Address,
+    tick: i32,
+    rpc_url: String,
+) -> Result<TickInfo, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    get_tick_info_with_provider(pool, tick, Arc::new(provider)).await
+}
+
+/// Same as `get_tick_info` but reuses an already constructed provider
+pub async fn get_tick_info_with_provider<T, P>(
+    pool: Address,
+    tick: i32,
+    client: P,
+) -> Result<TickInfo, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    let tick = I24::try_from(tick).map_err(|_| UniswapV3PoolError::InvalidTick(tick))?;
+    let info = UNIV3_POOL::new(pool, client)
+        .ticks(tick)
+        .call()
+        .await
+        .map_err(|e| UniswapV3PoolError::GetPoolStateError(e.to_string()))?;
+    Ok(TickInfo {
+        liquidity_gross: info.liquidityGross,
+        liquidity_net: info.liquidityNet,
+        initialized: info.initialized,
+    })
+}
+
+/// Read one 256 bit word of the pool's `tickBitmap`; bit `i` of word `w` is set
+/// when the tick `(w * 256 + i) * tick_spacing` is initialized
+pub async fn get_tick_bitmap(
+    pool: Address,
+    word_pos: i16,
+    rpc_url: String,
+) -> Result<U256, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    get_tick_bitmap_with_provider(pool, word_pos, Arc::new(provider)).await
+}
+
+/// Same as `get_tick_bitmap` but reuses an already constructed provider
+pub async fn get_tick_bitmap_with_provider<T, P>(
+    pool: Address,
+    word_pos: i16,
+    client: P,
+) -> Result<U256, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    UNIV3_POOL::new(pool, client)
+        .tickBitmap(word_pos)
+        .call()
+        .await
+        .map(|word| word._0)
+        .map_err(|e| UniswapV3PoolError::GetPoolStateError(e.to_string()))
+}
+
+/// `TickBitmap.position`: the word and bit of a compressed tick
+fn bitmap_position(compressed: i32) -> (i16, u8) {
+    ((compressed >> 8) as i16, (compressed & 0xff) as u8)
+}
+
+/// `tick / tick_spacing`, rounded towards negative infinity
+fn compress(tick: i32, tick_spacing: i32) -> i32 {
+    let compressed = tick / tick_spacing;
+    if tick < 0 && tick % tick_spacing != 0 {
+        compressed - 1
+    } else {
+        compressed
+    }
+}
+
+/// The bitmap word `next_initialized_tick_within_one_word` has to be given
+pub fn bitmap_word_pos(tick: i32, tick_spacing: i32, lte: bool) -> i16 {
+    let compressed = compress(tick, tick_spacing);
+    bitmap_position(if lte { compressed } else { compressed + 1 }).0
+}
+
+/// `TickBitmap.nextInitializedTickWithinOneWord`: the next initialized tick at
+/// or below `tick` when `lte` (price going down), or above `tick` otherwise,
+/// looking only at the bitmap word `bitmap_word_pos(..)`. When nothing is
+/// initialized the last tick of the word is returned with `false`.
+pub fn next_initialized_tick_within_one_word(
+    bitmap: U256,
+    tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+) -> (i32, bool) {
+    let compressed = compress(tick, tick_spacing);
+    if lte {
+        let (_, bit_pos) = bitmap_position(compressed);
+        // bit_pos 及其右边的所有位
+        let mask = (U256::from(1) << bit_pos) - U256::from(1) + (U256::from(1) << bit_pos);
+        let masked = bitmap & mask;
+        if masked.is_zero() {
+            ((compressed - bit_pos as i32) * tick_spacing, false)
+        } else {
+            let msb = 255 - masked.leading_zeros() as i32;
+            ((compressed - (bit_pos as i32 - msb)) * tick_spacing, true)
+        }
+    } else {
+        let (_, bit_pos) = bitmap_position(compressed + 1);
+        // bit_pos 及其左边的所有位
+        let mask = !((U256::from(1) << bit_pos) - U256::from(1));
+        let masked = bitmap & mask;
+        if masked.is_zero() {
+            (
+                (compressed + 1 + (255 - bit_pos as i32)) * tick_spacing,
+                false,
+            )
+        } else {
+            let lsb = masked.trailing_zeros() as i32;
+            (
+                (compressed + 1 + (lsb - bit_pos as i32)) * tick_spacing,
+                true,
+            )
+        }
+    }
+}
+
+/// At most this many bitmap words are read by `next_initialized_tick`
+pub const MAX_BITMAP_WORDS: usize = 64;
+
+/// Walk the pool's `tickBitmap` from `tick` to the next initialized tick at or
+/// below it when `lte`, or above it otherwise. Returns `None` when no tick is
+/// initialized within `MAX_BITMAP_WORDS` words or before `MIN_TICK`/`MAX_TICK`.
+pub async fn next_initialized_tick(
+    pool: Address,
+    tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+    rpc_url: String,
+) -> Result<Option<i32>, UniswapV3PoolError> {
+    let provider = ProviderBuilder::new()
+        .on_builtin(&rpc_url)
+        .await
+        .map_err(|e| UniswapV3PoolError::InvalidRpcUrl(e.to_string()))?;
+
+    next_initialized_tick_with_provider(pool, tick, tick_spacing, lte, Arc::new(provider)).await
+}
+
+/// Same as `next_initialized_tick` but reuses an already constructed provider
+pub async fn next_initialized_tick_with_provider<T, P>(
+    pool: Address,
+    mut tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+    client: P,
+) -> Result<Option<i32>, UniswapV3PoolError>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    if tick_spacing <= 0 {
+        return Err(UniswapV3PoolError::InvalidTickSpacing(tick_spacing));
+    }
+    for _ in 0..MAX_BITMAP_WORDS {
+        let word_pos = bitmap_word_pos(tick, tick_spacing, lte);
+        let bitmap = get_tick_bitmap_with_provider(pool, word_pos, &client).await?;
+        let (next, initialized) =
+            next_initialized_tick_within_one_word(bitmap, tick, tick_spacing, lte);
+        if initialized {
+            return Ok(Some(next));
+        }
+        // 继续查找相邻的 word
+        tick = if lte { next - 1 } else { next };
+        if tick <= MIN_TICK || tick >= MAX_TICK {
+            return Ok(None);
+        }
+    }
+    Ok(None)
+}
+
+/// A fee tier of a Uniswap V3 fork whose factory enables other fee amounts than
+/// Uniswap, e.g. PancakeSwap V3. `UniswapPoolFee` only covers the Uniswap tiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeeTier {
+    /// 原始手续费，单位为百万分之一（2500 即 0.25%）
+    pub fee: u32,
+    pub tick_spacing: i32,
+}
+
+impl FeeTier {
+    /// The tier of the raw fee amount `fee` in `tiers`, e.g. `PANCAKE_V3_FEE_TIERS`
+    pub fn find(tiers: &[FeeTier], fee: u32) -> Option<FeeTier> {
+        tiers.iter().find(|tier| tier.fee == fee).copied()
+    }
+
+    /// Fee in basis points (2500 -> 25)
+    pub fn as_bps(&self) -> u32 {
+        self.fee / 100
+    }
+
+    /// Fee as a percentage for display (2500 -> 0.25)
+    pub fn as_percent(&self) -> f64 {
+        self.fee as f64 / 10000.0
+    }
+}
+
+impl From<UniswapPoolFee> for FeeTier {
+    fn from(fee: UniswapPoolFee) -> Self {
+        FeeTier {
+            fee: fee.as_u32(),
+            tick_spacing: fee.tick_spacing(),
+        }
+    }
+}
+
+/// Fee tiers enabled by the PancakeSwap V3 factory, which has no 0.3% tier.
+/// Uniswap V3 itself has no 0.25% tier.
+pub const PANCAKE_V3_FEE_TIERS: [FeeTier; 4] = [
+    FeeTier {
+        fee: 10000,
+        tick_spacing: 200,
+    },
+    FeeTier {
+        fee: 2500,
+        tick_spacing: 50,
+    },
+    FeeTier {
+        fee: 500,
+        tick_spacing: 10,
+    },
+    FeeTier {
+        fee: 100,
+        tick_spacing: 1,
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UniswapPoolFee {
     Fee10000, // 1%
     Fee3000,  // 0.3%
@@ -5,6 +735,16 @@ pub enum UniswapPoolFee {
     Fee100,   // 0.01%
 }
 impl UniswapPoolFee {
+    /// The four Uniswap V3 fee tiers, highest fee first
+    pub fn all() -> [UniswapPoolFee; 4] {
+        [
+            UniswapPoolFee::Fee10000,
+            UniswapPoolFee::Fee3000,
+            UniswapPoolFee::Fee500,
+            UniswapPoolFee::Fee100,
+        ]
+    }
+
     pub fn as_u32(&self) -> u32 {
         match self {
             UniswapPoolFee::Fee10000 => 10000,
@@ -13,4 +753,864 @@ impl UniswapPoolFee {
             UniswapPoolFee::Fee100 => 100,
         }
     }
+
+    pub fn from_u32(fee: u32) -> Option<Self> {
+        match fee {
+            10000 => Some(UniswapPoolFee::Fee10000),
+            3000 => Some(UniswapPoolFee::Fee3000),
+            500 => Some(UniswapPoolFee::Fee500),
+            100 => Some(UniswapPoolFee::Fee100),
+            _ => None,
+        }
+    }
+
+    /// Tick spacing the factory enables for this fee tier
+    pub fn tick_spacing(&self) -> i32 {
+        match self {
+            UniswapPoolFee::Fee10000 => 200,
+            UniswapPoolFee::Fee3000 => 60,
+            UniswapPoolFee::Fee500 => 10,
+            UniswapPoolFee::Fee100 => 1,
+        }
+    }
+
+    /// Fee in basis points, the fee amount is in hundredths of a bip (3000 -> 30)
+    pub fn as_bps(&self) -> u32 {
+        self.as_u32() / 100
+    }
+
+    /// Fee as a percentage for display (3000 -> 0.3)
+    pub fn as_percent(&self) -> f64 {
+        self.as_u32() as f64 / 10000.0
+    }
+}
+
+/// Formats as the percentage followed by the raw fee amount, e.g. `0.3% (3000)`
+impl fmt::Display for UniswapPoolFee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}% ({})", self.as_percent(), self.as_u32())
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[cfg_attr(not(feature = "locale-en"), error("不支持的池子手续费{0}"))]
+#[cfg_attr(feature = "locale-en", error("unsupported pool fee: {0}"))]
+pub struct ParsePoolFeeError(pub String);
+
+/// Parses a percentage (`"0.3%"`), a raw fee amount (`"3000"`) or the `Display`
+/// form (`"0.3% (3000)"`)
+impl FromStr for UniswapPoolFee {
+    type Err = ParsePoolFeeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParsePoolFeeError(s.to_string());
+        let s = s.trim();
+        match s.split_once(" (") {
+            // 百分比和原始值都要解析，两者不一致时报错
+            Some((percent, raw)) => {
+                let raw = raw.strip_suffix(')').ok_or_else(err)?;
+                let fee = parse_raw_fee(raw).ok_or_else(err)?;
+                match percent.strip_suffix('%').and_then(parse_percent_fee) {
+                    Some(from_percent) if from_percent == fee => Ok(fee),
+                    _ => Err(err()),
+                }
+            }
+            None => match s.strip_suffix('%') {
+                Some(percent) => parse_percent_fee(percent),
+                None => parse_raw_fee(s),
+            }
+            .ok_or_else(err),
+        }
+    }
+}
+
+fn parse_percent_fee(percent: &str) -> Option<UniswapPoolFee> {
+    percent
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|percent| UniswapPoolFee::from_u32((percent * 10000.0).round() as u32))
+}
+
+fn parse_raw_fee(raw: &str) -> Option<UniswapPoolFee> {
+    raw.trim()
+        .parse::<u32>()
+        .ok()
+        .and_then(UniswapPoolFee::from_u32)
+}
+
+/// Serialized as the raw fee amount (10000, 3000, 500, 100)
+#[cfg(feature = "serde")]
+impl serde::Serialize for UniswapPoolFee {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.as_u32())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for UniswapPoolFee {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fee = u32::deserialize(deserializer)?;
+        UniswapPoolFee::from_u32(fee)
+            .ok_or_else(|| serde::de::Error::custom(ParsePoolFeeError(fee.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{aliases::I56, Bytes, U160};
+
+    use crate::test_utils::MockTransport;
+
+    use super::*;
+
+    #[test]
+    fn test_swap_event_from_log() {
+        let swap = UNIV3_POOL::Swap {
+            sender: Address::repeat_byte(1),
+            recipient: Address::repeat_byte(2),
+            amount0: I256::try_from(-3_000_000_000i64).unwrap(),
+            amount1: I256::try_from(10i128.pow(18)).unwrap(),
+            sqrtPriceX96: U160::from(1u64 << 32),
+            liquidity: 1000,
+            tick: I24::try_from(-42).unwrap(),
+        };
+        let log = Log {
+            inner: alloy::primitives::Log {
+                address: Address::repeat_byte(3),
+                data: swap.encode_log_data(),
+            },
+            block_number: Some(7),
+            transaction_hash: Some(TxHash::repeat_byte(4)),
+            ..Default::default()
+        };
+        assert_eq!(
+            SwapEvent {
+                sender: Address::repeat_byte(1),
+                recipient: Address::repeat_byte(2),
+                amount0: I256::try_from(-3_000_000_000i64).unwrap(),
+                amount1: I256::try_from(10i128.pow(18)).unwrap(),
+                sqrt_price_x96: U256::from(1u64 << 32),
+                liquidity: 1000,
+                tick: -42,
+                block_number: Some(7),
+                transaction_hash: Some(TxHash::repeat_byte(4)),
+            },
+            SwapEvent::from_log(&log).unwrap()
+        );
+
+        // 其他事件无法解析为 Swap
+        let initialize = UNIV3_POOL::Initialize {
+            sqrtPriceX96: U160::from(1u64 << 32),
+            tick: I24::ZERO,
+        };
+        let log = Log {
+            inner: alloy::primitives::Log {
+                address: Address::repeat_byte(3),
+                data: initialize.encode_log_data(),
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            "INVALID_EVENT",
+            SwapEvent::from_log(&log).unwrap_err().code()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_swaps() {
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let swap = |amount0: i64, amount1: i128| UNIV3_POOL::Swap {
+            sender: Address::repeat_byte(1),
+            recipient: Address::repeat_byte(1),
+            amount0: I256::try_from(amount0).unwrap(),
+            amount1: I256::try_from(amount1).unwrap(),
+            sqrtPriceX96: U160::from(1u64 << 32),
+            liquidity: 1000,
+            tick: I24::try_from(195_000).unwrap(),
+        };
+        let logs: Vec<Log> = [
+            (swap(-3_000_000_000, 10i128.pow(18)), 20_999_999),
+            (swap(6_000_000_000, -2 * 10i128.pow(18)), 21_000_000),
+        ]
+        .into_iter()
+        .map(|(swap, block_number)| Log {
+            inner: alloy::primitives::Log {
+                address: pool,
+                data: swap.encode_log_data(),
+            },
+            block_number: Some(block_number),
+            ..Default::default()
+        })
+        .collect();
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::to_value(&logs).unwrap());
+
+        let swaps = get_swaps_with_provider(pool, 20_999_999, 21_000_000, transport.provider())
+            .await
+            .unwrap();
+        let requests = transport.requests();
+        assert_eq!("eth_getLogs", requests[0].method);
+        assert_eq!("0x1406f3f", requests[0].params[0]["fromBlock"]);
+        assert_eq!("0x1406f40", requests[0].params[0]["toBlock"]);
+        assert_eq!(
+            serde_json::json!(UNIV3_POOL::Swap::SIGNATURE_HASH),
+            requests[0].params[0]["topics"][0]
+        );
+        assert_eq!(2, swaps.len());
+        assert_eq!(I256::try_from(-3_000_000_000i64).unwrap(), swaps[0].amount0);
+        assert!(swaps[1].amount1.is_negative());
+        assert_eq!(Some(21_000_000), swaps[1].block_number);
+        assert_eq!(195_000, swaps[1].tick);
+    }
+
+    fn pool_log(data: alloy::primitives::LogData) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap(),
+                data,
+            },
+            block_number: Some(21_000_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mint_and_burn_from_log() {
+        let npm = Address::from_str("0xC36442b4a4522E871399CD717aBDD847Ab11FE88").unwrap();
+        // 负 tick 编码在 indexed topic 中，需要按 int24 符号扩展
+        let mint = UNIV3_POOL::Mint {
+            sender: npm,
+            owner: npm,
+            tickLower: I24::try_from(-887_270).unwrap(),
+            tickUpper: I24::try_from(195_000).unwrap(),
+            amount: 123_456_789,
+            amount0: U256::from(1_000_000_000u64),
+            amount1: U256::from(10u128.pow(18)),
+        };
+        let decoded = MintEvent::from_log(&pool_log(mint.encode_log_data())).unwrap();
+        assert_eq!(
+            MintEvent {
+                sender: npm,
+                owner: npm,
+                tick_lower: -887_270,
+                tick_upper: 195_000,
+                liquidity: 123_456_789,
+                amount0: U256::from(1_000_000_000u64),
+                amount1: U256::from(10u128.pow(18)),
+                block_number: Some(21_000_000),
+                transaction_hash: None,
+            },
+            decoded
+        );
+
+        let burn = UNIV3_POOL::Burn {
+            owner: npm,
+            tickLower: I24::try_from(-200_010).unwrap(),
+            tickUpper: I24::try_from(-199_990).unwrap(),
+            amount: 42,
+            amount0: U256::ZERO,
+            amount1: U256::from(7),
+        };
+        let burn_log = pool_log(burn.encode_log_data());
+        let decoded = BurnEvent::from_log(&burn_log).unwrap();
+        assert_eq!(-200_010, decoded.tick_lower);
+        assert_eq!(-199_990, decoded.tick_upper);
+        assert_eq!(42, decoded.liquidity);
+        assert_eq!(U256::from(7), decoded.amount1);
+        assert_eq!(
+            "INVALID_EVENT",
+            MintEvent::from_log(&burn_log).unwrap_err().code()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_mints_and_burns() {
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let burn = UNIV3_POOL::Burn {
+            owner: Address::repeat_byte(1),
+            tickLower: I24::try_from(-10).unwrap(),
+            tickUpper: I24::try_from(10).unwrap(),
+            amount: 1,
+            amount0: U256::from(1),
+            amount1: U256::from(1),
+        };
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!([]));
+        transport.push_response(serde_json::json!([pool_log(burn.encode_log_data())]));
+
+        let mints = get_mints_with_provider(pool, 1, 2, transport.provider())
+            .await
+            .unwrap();
+        let burns = get_burns_with_provider(pool, 1, 2, transport.provider())
+            .await
+            .unwrap();
+        assert!(mints.is_empty());
+        assert_eq!(1, burns.len());
+        assert_eq!(-10, burns[0].tick_lower);
+        let requests = transport.requests();
+        assert_eq!(
+            serde_json::json!(UNIV3_POOL::Mint::SIGNATURE_HASH),
+            requests[0].params[0]["topics"][0]
+        );
+        assert_eq!(
+            serde_json::json!(UNIV3_POOL::Burn::SIGNATURE_HASH),
+            requests[1].params[0]["topics"][0]
+        );
+    }
+
+    fn slot0_response(cardinality: u16, cardinality_next: u16) -> serde_json::Value {
+        let slot0 = UNIV3_POOL::slot0Call::abi_encode_returns(&(
+            U160::from(1u64 << 32),
+            I24::ZERO,
+            0u16,
+            cardinality,
+            cardinality_next,
+            0u8,
+            true,
+        ));
+        serde_json::json!(Bytes::from(slot0))
+    }
+
+    #[tokio::test]
+    async fn test_increase_observation_cardinality_tx() {
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let transport = MockTransport::default();
+        transport.push_response(slot0_response(1, 1));
+        let tx = increase_observation_cardinality_tx_with_provider(pool, 100, transport.provider())
+            .await
+            .unwrap();
+        assert_eq!(Some(pool), tx.to.and_then(|to| to.to().copied()));
+        let input = tx.input.input().unwrap();
+        assert_eq!(
+            UNIV3_POOL::increaseObservationCardinalityNextCall::SELECTOR,
+            input[..4]
+        );
+        let call =
+            UNIV3_POOL::increaseObservationCardinalityNextCall::abi_decode(input, true).unwrap();
+        assert_eq!(100, call.observationCardinalityNext);
+    }
+
+    #[tokio::test]
+    async fn test_increase_observation_cardinality_not_increased() {
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let transport = MockTransport::default();
+        // 已经有人把 next 扩容到 200
+        transport.push_response(slot0_response(100, 200));
+        let err =
+            increase_observation_cardinality_tx_with_provider(pool, 150, transport.provider())
+                .await
+                .unwrap_err();
+        assert!(matches!(
+            err,
+            UniswapV3PoolError::CardinalityNotIncreased {
+                target: 150,
+                current: 200
+            }
+        ));
+    }
+
+    #[test]
+    fn test_max_liquidity_per_tick() {
+        // Tick.spec.ts 中的数值
+        assert_eq!(
+            1917569901783203986719870431555990,
+            max_liquidity_per_tick(UniswapPoolFee::Fee500.tick_spacing()).unwrap()
+        );
+        assert_eq!(
+            11505743598341114571880798222544994,
+            max_liquidity_per_tick(UniswapPoolFee::Fee3000.tick_spacing()).unwrap()
+        );
+        assert_eq!(
+            38350317471085141830651933667504588,
+            max_liquidity_per_tick(UniswapPoolFee::Fee10000.tick_spacing()).unwrap()
+        );
+        // tick spacing 为 MAX_TICK 时只有 -MAX_TICK、0、MAX_TICK 三个 tick
+        assert_eq!(u128::MAX / 3, max_liquidity_per_tick(MAX_TICK).unwrap());
+        assert!(matches!(
+            max_liquidity_per_tick(0),
+            Err(UniswapV3PoolError::InvalidTickSpacing(0))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_max_liquidity_per_tick() {
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let expected = max_liquidity_per_tick(UniswapPoolFee::Fee500.tick_spacing()).unwrap();
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(Bytes::from(
+            UNIV3_POOL::maxLiquidityPerTickCall::abi_encode_returns(&(expected,))
+        )));
+        let max = get_max_liquidity_per_tick_with_provider(pool, transport.provider())
+            .await
+            .unwrap();
+        assert_eq!(expected, max);
+        assert_eq!("eth_call", transport.requests()[0].method);
+    }
+
+    /// TickBitmap.spec.ts 中初始化的 tick，tick spacing 为 1
+    fn word_zero() -> U256 {
+        [70, 78, 84, 139, 240]
+            .iter()
+            .fold(U256::ZERO, |bitmap, &tick| bitmap | (U256::from(1) << tick))
+    }
+
+    #[test]
+    fn test_next_initialized_tick_within_one_word() {
+        let bitmap = word_zero();
+        // 向右查找（不含当前 tick）
+        assert_eq!(
+            (84, true),
+            next_initialized_tick_within_one_word(bitmap, 78, 1, false)
+        );
+        assert_eq!(
+            (78, true),
+            next_initialized_tick_within_one_word(bitmap, 77, 1, false)
+        );
+        assert_eq!(
+            (255, false),
+            next_initialized_tick_within_one_word(bitmap, 240, 1, false)
+        );
+        // 向左查找（包含当前 tick）
+        assert_eq!(
+            (78, true),
+            next_initialized_tick_within_one_word(bitmap, 78, 1, true)
+        );
+        assert_eq!(
+            (78, true),
+            next_initialized_tick_within_one_word(bitmap, 79, 1, true)
+        );
+        assert_eq!(
+            (0, false),
+            next_initialized_tick_within_one_word(bitmap, 69, 1, true)
+        );
+        // tick spacing 为 10 时 tick 先被压缩
+        assert_eq!(
+            (780, true),
+            next_initialized_tick_within_one_word(bitmap, 775, 10, false)
+        );
+        assert_eq!(
+            (780, true),
+            next_initialized_tick_within_one_word(bitmap, 789, 10, true)
+        );
+    }
+
+    #[test]
+    fn test_bitmap_word_pos() {
+        assert_eq!(0, bitmap_word_pos(255, 1, true));
+        assert_eq!(1, bitmap_word_pos(255, 1, false));
+        assert_eq!(-1, bitmap_word_pos(-1, 1, true));
+        // -5 / 10 向负无穷取整为 -1
+        assert_eq!(-1, bitmap_word_pos(-5, 10, true));
+        assert_eq!(0, bitmap_word_pos(-5, 10, false));
+        assert_eq!(-1, bitmap_word_pos(-2561, 10, false));
+    }
+
+    #[tokio::test]
+    async fn test_get_tick_info() {
+        let output = UNIV3_POOL::ticksCall::abi_encode_returns(&(
+            5000u128,
+            -3000i128,
+            U256::ZERO,
+            U256::ZERO,
+            I56::ZERO,
+            U160::ZERO,
+            0u32,
+            true,
+        ));
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(Bytes::from(output)));
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let info = get_tick_info_with_provider(pool, -195_000, transport.provider())
+            .await
+            .unwrap();
+        assert_eq!(
+            TickInfo {
+                liquidity_gross: 5000,
+                liquidity_net: -3000,
+                initialized: true,
+            },
+            info
+        );
+        assert!(matches!(
+            get_tick_info_with_provider(pool, 1 << 23, transport.provider()).await,
+            Err(UniswapV3PoolError::InvalidTick(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_next_initialized_tick_across_words() {
+        let transport = MockTransport::default();
+        // word 0 中 240 右边没有初始化的 tick，word 1 为空，word 2 中初始化了 535
+        for bitmap in [word_zero(), U256::ZERO, U256::from(1) << (535 - 512)] {
+            transport.push_response(serde_json::json!(bitmap.to_be_bytes::<32>()));
+        }
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let next = next_initialized_tick_with_provider(pool, 240, 1, false, transport.provider())
+            .await
+            .unwrap();
+        assert_eq!(Some(535), next);
+        assert_eq!(3, transport.requests().len());
+    }
+
+    #[test]
+    fn test_all_fee_tiers() {
+        let fees = UniswapPoolFee::all();
+        assert_eq!(
+            [
+                UniswapPoolFee::Fee10000,
+                UniswapPoolFee::Fee3000,
+                UniswapPoolFee::Fee500,
+                UniswapPoolFee::Fee100,
+            ],
+            fees
+        );
+        assert!(fees.windows(2).all(|w| w[0].as_u32() > w[1].as_u32()));
+    }
+
+    #[test]
+    fn test_from_u32() {
+        for fee in [10000, 3000, 500, 100] {
+            assert_eq!(fee, UniswapPoolFee::from_u32(fee).unwrap().as_u32());
+        }
+        assert!(UniswapPoolFee::from_u32(2000).is_none());
+        // 2500 只在 PancakeSwap V3 上存在
+        assert!(UniswapPoolFee::from_u32(2500).is_none());
+    }
+
+    #[test]
+    fn test_pancake_v3_tiers() {
+        let tier = FeeTier::find(&PANCAKE_V3_FEE_TIERS, 2500).unwrap();
+        assert_eq!(50, tier.tick_spacing);
+        assert_eq!(25, tier.as_bps());
+        assert_eq!(0.25, tier.as_percent());
+        let spacings: Vec<i32> = PANCAKE_V3_FEE_TIERS
+            .iter()
+            .map(|tier| tier.tick_spacing)
+            .collect();
+        assert_eq!(vec![200, 50, 10, 1], spacings);
+        assert!(FeeTier::find(&PANCAKE_V3_FEE_TIERS, 3000).is_none());
+
+        // Uniswap 的档位可以直接转换
+        let uniswap_tiers: Vec<FeeTier> = UniswapPoolFee::all()
+            .into_iter()
+            .map(FeeTier::from)
+            .collect();
+        assert_eq!(
+            Some(FeeTier {
+                fee: 3000,
+                tick_spacing: 60
+            }),
+            FeeTier::find(&uniswap_tiers, 3000)
+        );
+        assert!(FeeTier::find(&uniswap_tiers, 2500).is_none());
+    }
+
+    #[test]
+    fn test_as_bps_and_percent() {
+        assert_eq!(100, UniswapPoolFee::Fee10000.as_bps());
+        assert_eq!(30, UniswapPoolFee::Fee3000.as_bps());
+        assert_eq!(5, UniswapPoolFee::Fee500.as_bps());
+        assert_eq!(1, UniswapPoolFee::Fee100.as_bps());
+
+        assert_eq!(1.0, UniswapPoolFee::Fee10000.as_percent());
+        assert_eq!(0.3, UniswapPoolFee::Fee3000.as_percent());
+        assert_eq!(0.05, UniswapPoolFee::Fee500.as_percent());
+        assert_eq!(0.01, UniswapPoolFee::Fee100.as_percent());
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        assert_eq!("1% (10000)", UniswapPoolFee::Fee10000.to_string());
+        assert_eq!("0.3% (3000)", UniswapPoolFee::Fee3000.to_string());
+        assert_eq!("0.05% (500)", UniswapPoolFee::Fee500.to_string());
+        assert_eq!("0.01% (100)", UniswapPoolFee::Fee100.to_string());
+
+        for fee in UniswapPoolFee::all() {
+            assert_eq!(fee, fee.to_string().parse().unwrap());
+            assert_eq!(fee, format!("{}%", fee.as_percent()).parse().unwrap());
+            assert_eq!(fee, fee.as_u32().to_string().parse().unwrap());
+        }
+        assert_eq!(
+            UniswapPoolFee::Fee3000,
+            "0.30%".parse::<UniswapPoolFee>().unwrap()
+        );
+        assert!("0.25%".parse::<UniswapPoolFee>().is_err());
+        assert!("2500".parse::<UniswapPoolFee>().is_err());
+        assert!("0.25% (2500)".parse::<UniswapPoolFee>().is_err());
+        assert!("0.2%".parse::<UniswapPoolFee>().is_err());
+        assert!("2000".parse::<UniswapPoolFee>().is_err());
+        assert!("fee".parse::<UniswapPoolFee>().is_err());
+
+        // 百分比和原始值不一致
+        assert_eq!(
+            Err(ParsePoolFeeError("1% (3000)".to_string())),
+            "1% (3000)".parse::<UniswapPoolFee>()
+        );
+        assert!("0.05% (100)".parse::<UniswapPoolFee>().is_err());
+        assert!("0.2% (3000)".parse::<UniswapPoolFee>().is_err());
+        assert!("3000 (3000)".parse::<UniswapPoolFee>().is_err());
+        assert!("0.3% (3000".parse::<UniswapPoolFee>().is_err());
+    }
+
+    #[test]
+    fn test_fee_eq_and_hash() {
+        use std::collections::HashMap;
+
+        let fee = UniswapPoolFee::Fee500;
+        let copied = fee;
+        assert_eq!(fee, copied);
+        assert_ne!(UniswapPoolFee::Fee500, UniswapPoolFee::Fee3000);
+
+        let mut liquidity = HashMap::new();
+        liquidity.insert(UniswapPoolFee::Fee500, 1u128);
+        liquidity.insert(copied, 2u128);
+        liquidity.insert(UniswapPoolFee::Fee3000, 3u128);
+        assert_eq!(2, liquidity.len());
+        assert_eq!(Some(&2), liquidity.get(&UniswapPoolFee::Fee500));
+        assert_eq!("Fee500", format!("{:?}", fee));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let json = serde_json::to_string(&UniswapPoolFee::Fee3000).unwrap();
+        assert_eq!("3000", json);
+        let fee: UniswapPoolFee = serde_json::from_str(&json).unwrap();
+        assert_eq!(UniswapPoolFee::Fee3000, fee);
+
+        let fees: Vec<UniswapPoolFee> = serde_json::from_str("[10000, 500, 100]").unwrap();
+        assert_eq!(
+            vec![
+                UniswapPoolFee::Fee10000,
+                UniswapPoolFee::Fee500,
+                UniswapPoolFee::Fee100
+            ],
+            fees
+        );
+
+        assert!(serde_json::from_str::<UniswapPoolFee>("2500").is_err());
+        let err = serde_json::from_str::<UniswapPoolFee>("2000").unwrap_err();
+        assert_eq!(
+            ParsePoolFeeError("2000".to_string()).to_string(),
+            err.to_string()
+        );
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use crate::test_utils::spawn_mainnet_fork;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_next_initialized_tick_above_current() {
+        let fork = spawn_mainnet_fork();
+        // USDC/WETH 0.05%
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let provider = ProviderBuilder::new()
+            .on_builtin(&fork.endpoint())
+            .await
+            .unwrap();
+        let tick = UNIV3_POOL::new(pool, &provider)
+            .slot0()
+            .call()
+            .await
+            .unwrap()
+            .tick
+            .as_i32();
+        let spacing = UniswapPoolFee::Fee500.tick_spacing();
+        let next = next_initialized_tick_with_provider(pool, tick, spacing, false, &provider)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(next > tick);
+        assert_eq!(0, next % spacing);
+        let below = next_initialized_tick_with_provider(pool, tick, spacing, true, &provider)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(below <= tick);
+    }
+
+    #[tokio::test]
+    async fn test_get_tick_info_initialized() {
+        let fork = spawn_mainnet_fork();
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let provider = ProviderBuilder::new()
+            .on_builtin(&fork.endpoint())
+            .await
+            .unwrap();
+        let tick = UNIV3_POOL::new(pool, &provider)
+            .slot0()
+            .call()
+            .await
+            .unwrap()
+            .tick
+            .as_i32();
+        let spacing = UniswapPoolFee::Fee500.tick_spacing();
+        let next = next_initialized_tick_with_provider(pool, tick, spacing, false, &provider)
+            .await
+            .unwrap()
+            .unwrap();
+        let info = get_tick_info_with_provider(pool, next, &provider)
+            .await
+            .unwrap();
+        assert!(info.initialized);
+        assert!(info.liquidity_gross > 0);
+        assert!(info.liquidity_net.unsigned_abs() <= info.liquidity_gross);
+        // 相邻的非初始化 tick
+        let empty = get_tick_info_with_provider(pool, next + 1, &provider)
+            .await
+            .unwrap();
+        assert!(!empty.initialized);
+    }
+
+    #[tokio::test]
+    async fn test_max_liquidity_per_tick_matches_pool() {
+        let fork = spawn_mainnet_fork();
+        let provider = ProviderBuilder::new()
+            .on_builtin(&fork.endpoint())
+            .await
+            .unwrap();
+        for pool in [
+            // USDC/WETH 0.05%
+            "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640",
+            // ETHC/WETH 1%
+            "0xFbDbaC2d456A3CC2754A626C2fB83C1af25A3a6F",
+        ] {
+            let pool = Address::from_str(pool).unwrap();
+            let spacing = UNIV3_POOL::new(pool, &provider)
+                .tickSpacing()
+                .call()
+                .await
+                .unwrap()
+                ._0
+                .as_i32();
+            let max = get_max_liquidity_per_tick_with_provider(pool, &provider)
+                .await
+                .unwrap();
+            assert_eq!(max_liquidity_per_tick(spacing).unwrap(), max);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_fee_growth() {
+        // USDC/WETH 0.05%
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let fork = spawn_mainnet_fork();
+        let fees = get_fee_growth(pool, fork.endpoint()).await.unwrap();
+        // 该池子已运行多年，累计的 fee growth 是一个很大的 Q128 数
+        assert!(fees.fee_growth_global0_x128 > U256::from(1) << 128);
+        assert!(fees.fee_growth_global1_x128 > U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_swaps_on_fork() {
+        use std::time::Duration;
+
+        use alloy::{network::EthereumWallet, signers::local::PrivateKeySigner};
+
+        use crate::{
+            client::{swap_and_send, SwapAndSendParams, UniswapSupportChain},
+            utils::{from_readable_amount, Token},
+            weth::{weth_address, wrap_tx},
+        };
+
+        let fork = spawn_mainnet_fork();
+        let chain = UniswapSupportChain::Ethereum;
+        // USDC/WETH 0.05%
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let mut swaps = Box::pin(subscribe_swaps(pool, fork.ws_endpoint()).await.unwrap());
+
+        let signer = PrivateKeySigner::from(fork.keys()[0].clone());
+        let from = signer.address();
+        let amount_in = from_readable_amount(1.0, 18);
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .on_http(fork.endpoint_url());
+        provider
+            .send_transaction(wrap_tx(&chain, amount_in).with_from(from))
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+        let params = SwapAndSendParams {
+            token_in: Token::new(&weth_address(&chain).to_string(), 18, "WETH".to_string())
+                .unwrap(),
+            token_out: Token::new(
+                "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                6,
+                "USDC".to_string(),
+            )
+            .unwrap(),
+            amount_in,
+            pool_fee: UniswapPoolFee::Fee500,
+            slippage_bps: 100,
+        };
+        let receipt = swap_and_send(chain, EthereumWallet::from(signer), params, fork.endpoint())
+            .await
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(10), swaps.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some(receipt.transaction_hash), event.transaction_hash);
+        // USDC 是 token0：池子转出 USDC、收到 1 WETH
+        assert!(event.amount0.is_negative());
+        assert_eq!(I256::try_from(amount_in).unwrap(), event.amount1);
+        assert!(event.sqrt_price_x96 > U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_get_swaps_historical() {
+        use crate::test_utils::FORK_BLOCK_NUMBER;
+
+        let fork = spawn_mainnet_fork();
+        // USDC/WETH 0.05%，几乎每个区块都有兑换
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let from_block = FORK_BLOCK_NUMBER - 20;
+        let swaps = get_swaps(pool, from_block, FORK_BLOCK_NUMBER, fork.endpoint())
+            .await
+            .unwrap();
+        assert!(!swaps.is_empty());
+        for swap in &swaps {
+            let block_number = swap.block_number.unwrap();
+            assert!((from_block..=FORK_BLOCK_NUMBER).contains(&block_number));
+            // 一边转入池子，另一边转出
+            assert!(swap.amount0.is_negative() != swap.amount1.is_negative());
+            assert!(swap.sqrt_price_x96 > U256::ZERO);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_mints_and_burns_historical() {
+        use crate::test_utils::FORK_BLOCK_NUMBER;
+
+        let fork = spawn_mainnet_fork();
+        let pool = Address::from_str("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640").unwrap();
+        let from_block = FORK_BLOCK_NUMBER - 500;
+        let spacing = UniswapPoolFee::Fee500.tick_spacing();
+        let mints = get_mints(pool, from_block, FORK_BLOCK_NUMBER, fork.endpoint())
+            .await
+            .unwrap();
+        let burns = get_burns(pool, from_block, FORK_BLOCK_NUMBER, fork.endpoint())
+            .await
+            .unwrap();
+        assert!(!mints.is_empty() || !burns.is_empty());
+        let ranges = mints
+            .iter()
+            .map(|mint| (mint.tick_lower, mint.tick_upper))
+            .chain(burns.iter().map(|burn| (burn.tick_lower, burn.tick_upper)));
+        for (tick_lower, tick_upper) in ranges {
+            assert!(tick_lower < tick_upper);
+            assert!((MIN_TICK..=MAX_TICK).contains(&tick_lower));
+            assert_eq!(0, tick_lower % spacing);
+            assert_eq!(0, tick_upper % spacing);
+        }
+    }
 }
@@ -1,26 +1,320 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    future::{Future, IntoFuture},
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Result;
 
-use alloy::{primitives::utils::format_units, sol};
+use alloy::{network::TransactionBuilder, primitives::utils::format_units, sol};
 use alloy::{
-    primitives::{Address, U256},
-    providers::ProviderBuilder,
+    primitives::{keccak256, Address, Signature, B256, I256, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    sol_types::{decode_revert_reason, Revert, SolCall, SolError, SolStruct},
+    transports::{
+        layers::{RateLimitRetryPolicy, RetryPolicy},
+        Transport, TransportError,
+    },
 };
 use std::str::FromStr;
 
+use crate::{client::UniswapSupportChain, tokens};
+
 sol!(
     #[allow(missing_docs)]
     #[sol(rpc)]
     ERC20,
     "src/abi/erc20.json"
 );
+
+sol!(
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[sol(rpc)]
+    ERC20_PERMIT,
+    "src/abi/erc20_permit.json"
+);
+
+sol!(
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[sol(rpc)]
+    DAI_PERMIT,
+    "src/abi/dai_permit.json"
+);
+
+sol! {
+    /// The EIP-2612 `Permit` struct the owner signs
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
+
+mod dai {
+    alloy::sol! {
+        /// DAI's `Permit` struct, which predates EIP-2612 and only approves all or nothing
+        struct Permit {
+            address holder;
+            address spender;
+            uint256 nonce;
+            uint256 expiry;
+            bool allowed;
+        }
+    }
+}
+/// Default time each RPC request of `Token::new_from_online` may take
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default wait before the first retry of `retry_rpc`, doubled on every further retry
+pub const DEFAULT_RPC_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Timeout and retries of the RPC requests made by `retry_rpc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcRetryConfig {
+    /// 每次请求的超时时间
+    pub timeout: Duration,
+    /// 超时或可重试的错误（例如限流）之后再尝试的次数
+    pub retries: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍；节点返回了等待时间时以节点为准
+    pub backoff: Duration,
+}
+
+impl Default for RpcRetryConfig {
+    fn default() -> Self {
+        RpcRetryConfig {
+            timeout: DEFAULT_RPC_TIMEOUT,
+            retries: 2,
+            backoff: DEFAULT_RPC_BACKOFF,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RpcRetryError {
+    #[cfg_attr(not(feature = "locale-en"), error("RPC 请求超时 {0:?}"))]
+    #[cfg_attr(feature = "locale-en", error("RPC request timed out after {0:?}"))]
+    Timeout(Duration),
+}
+
+impl RpcRetryError {
+    /// Stable identifier of the variant, independent of the message language
+    pub fn code(&self) -> &'static str {
+        match self {
+            RpcRetryError::Timeout(_) => "TIMEOUT",
+        }
+    }
+}
+
+/// Run the request created by `request`, giving each attempt `config.timeout`.
+/// Timeouts and retryable transport errors such as rate limiting are retried up
+/// to `config.retries` times, waiting the backoff the node asks for or else
+/// `config.backoff` doubled per retry; other errors, e.g. a revert, are returned
+/// at once. Fails with `RpcRetryError::Timeout` when the last attempt times out.
+pub async fn retry_rpc<T, F, Fut>(config: &RpcRetryConfig, mut request: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        let err = match tokio::time::timeout(config.timeout, request()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(e)) if !is_retryable(&e) => return Err(e),
+            Ok(Err(e)) => e,
+            Err(_) => RpcRetryError::Timeout(config.timeout).into(),
+        };
+        if attempt >= config.retries {
+            return Err(err);
+        }
+        let backoff = as_transport_error(&err)
+            .and_then(|e| RateLimitRetryPolicy::default().backoff_hint(e))
+            .unwrap_or_else(|| config.backoff.saturating_mul(2u32.saturating_pow(attempt)));
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+/// Same policy as alloy's `RetryBackoffLayer`: rate limits and temporarily
+/// unavailable nodes
+fn is_retryable(e: &anyhow::Error) -> bool {
+    as_transport_error(e).is_some_and(|e| RateLimitRetryPolicy::default().should_retry(e))
+}
+
+fn as_transport_error(e: &anyhow::Error) -> Option<&TransportError> {
+    match e.downcast_ref::<alloy::contract::Error>() {
+        Some(alloy::contract::Error::TransportError(e)) => Some(e),
+        Some(_) => None,
+        None => e.downcast_ref::<TransportError>(),
+    }
+}
+
+fn revert_reason(data: &[u8]) -> Option<String> {
+    Revert::abi_decode(data, true)
+        .ok()
+        .map(|revert| revert.reason)
+        .or_else(|| decode_revert_reason(data))
+        .filter(|reason| !reason.is_empty())
+}
+
+/// The revert reason of a failed `eth_call`, from the revert data when the node
+/// returns it, otherwise from an `execution reverted: ...` message
+pub(crate) fn transport_revert_reason(e: &TransportError) -> Option<String> {
+    let payload = e.as_error_resp()?;
+    payload
+        .as_revert_data()
+        .and_then(|data| revert_reason(&data))
+        .or_else(|| {
+            payload
+                .message
+                .strip_prefix("execution reverted: ")
+                .map(|reason| reason.to_string())
+        })
+}
+
 pub fn from_readable_amount(amount_in: f64, decimals: u8) -> U256 {
     U256::from((amount_in * 10_f64.powi(decimals as i32)) as u128)
 }
+/// Parse a decimal string such as "1.5" into base units without going through `f64`.
+/// Trailing zeros in the fraction are ignored, any other digit beyond `decimals` is an error.
+pub fn from_readable_str(amount_in: &str, decimals: u8) -> Result<U256> {
+    let (whole, fractional) = amount_in.split_once('.').unwrap_or((amount_in, ""));
+    let fractional = fractional.trim_end_matches('0');
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fractional.is_empty()) || !is_digits(whole) || !is_digits(fractional) {
+        anyhow::bail!("数量格式不正确{}", amount_in);
+    }
+    if fractional.len() > decimals as usize {
+        anyhow::bail!("小数位数超过 token 精度{}: {}", decimals, amount_in);
+    }
+    let digits = format!(
+        "{}{:0<width$}",
+        whole,
+        fractional,
+        width = decimals as usize
+    );
+    U256::from_str_radix(&digits, 10)
+        .map_err(|e| anyhow::anyhow!("数量超出范围{}: {}", amount_in, e))
+}
+
+/// `amount_in` scaled down by `decimals`, e.g. `1.5` for 1.5e18 wei. Fails when
+/// `decimals` is above 77, the largest unit `format_units` supports.
+pub fn try_to_readable_amount(amount_in: U256, decimals: u8) -> Result<f64> {
+    let num = format_units(amount_in, decimals)
+        .map_err(|e| anyhow::anyhow!("无法按 {} 位精度格式化数量{}: {}", decimals, amount_in, e))?;
+    num.parse::<f64>()
+        .map_err(|e| anyhow::anyhow!("数量无法转换为 f64{}: {}", num, e))
+}
+
+/// Panicking version of `try_to_readable_amount`, for decimals read from a
+/// token which are always in range
 pub fn to_readable_amount(amount_in: U256, decimals: u8) -> f64 {
-    let num: String = format_units(amount_in, decimals).unwrap();
-    num.parse::<f64>().unwrap()
+    try_to_readable_amount(amount_in, decimals).unwrap()
+}
+
+/// Like `to_readable_amount` but exact: the decimal value as a string without
+/// trailing zeros, e.g. `"123456.789012"` for that many USDC, never through `f64`
+pub fn to_readable_string(amount_in: U256, decimals: u8) -> String {
+    let digits = amount_in.to_string();
+    let decimals = decimals as usize;
+    let (whole, fractional) = if digits.len() > decimals {
+        let (whole, fractional) = digits.split_at(digits.len() - decimals);
+        (whole.to_string(), fractional.to_string())
+    } else {
+        (
+            "0".to_string(),
+            format!("{:0>width$}", digits, width = decimals),
+        )
+    };
+    let fractional = fractional.trim_end_matches('0');
+    if fractional.is_empty() {
+        whole
+    } else {
+        format!("{}.{}", whole, fractional)
+    }
+}
+
+/// `amount * bps / 10_000`, failing instead of wrapping when the product overflows
+pub fn mul_bps(amount: U256, bps: u32) -> Result<U256> {
+    amount
+        .checked_mul(U256::from(bps))
+        .map(|product| product / U256::from(10_000))
+        .ok_or_else(|| anyhow::anyhow!("数量乘以 {} bps 溢出: {}", bps, amount))
+}
+
+/// `amount` less `slippage_bps`, e.g. the minimum output of a swap
+pub fn sub_slippage(amount: U256, slippage_bps: u32) -> Result<U256> {
+    amount
+        .checked_sub(mul_bps(amount, slippage_bps)?)
+        .ok_or_else(|| anyhow::anyhow!("滑点 {} bps 超过数量本身: {}", slippage_bps, amount))
+}
+
+/// `amount` plus `buffer_bps`, e.g. a gas limit with some headroom
+pub fn add_buffer(amount: U256, buffer_bps: u32) -> Result<U256> {
+    amount
+        .checked_add(mul_bps(amount, buffer_bps)?)
+        .ok_or_else(|| anyhow::anyhow!("数量加上 {} bps 溢出: {}", buffer_bps, amount))
+}
+
+/// Format `amount` for display, e.g. `1,234.56 WETH`: the exact decimal value with
+/// thousands separators and without trailing zeros, labelled with `token.symbol`
+pub fn format_token_amount(amount: U256, token: &Token) -> String {
+    // to_readable_string 按精度精确转换，不会出现科学计数法，任意精度都不会 panic
+    let num = to_readable_string(amount, token.decimals);
+    let (whole, fractional) = num.split_once('.').unwrap_or((&num, ""));
+    let mut grouped = String::with_capacity(whole.len() + whole.len() / 3);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if !fractional.is_empty() {
+        grouped.push('.');
+        grouped.push_str(fractional);
+    }
+    format!("{} {}", grouped, token.symbol)
+}
+
+/// Like `to_readable_amount` but without going through `f64`, so no precision is lost.
+/// Fails when the amount does not fit the 96-bit mantissa of `Decimal` or `decimals > 28`.
+#[cfg(feature = "decimal")]
+pub fn to_readable_decimal(amount_in: U256, decimals: u8) -> Result<rust_decimal::Decimal> {
+    let mantissa: i128 = amount_in
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("数量超出 Decimal 的表示范围{}", amount_in))?;
+    rust_decimal::Decimal::try_from_i128_with_scale(mantissa, decimals as u32)
+        .map_err(|e| anyhow::anyhow!("数量超出 Decimal 的表示范围{}", e))
+}
+
+/// How much `Token::approve_tx` lets the spender take
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalStrategy {
+    /// 只授权本次需要的数量，更安全
+    Exact(U256),
+    /// Approve `U256::MAX` once so later swaps need no approval, at the cost of
+    /// trusting the spender with the whole balance
+    Infinite,
+}
+
+impl ApprovalStrategy {
+    pub fn amount(&self) -> U256 {
+        match self {
+            ApprovalStrategy::Exact(amount) => *amount,
+            ApprovalStrategy::Infinite => U256::MAX,
+        }
+    }
+}
+
+/// A bare amount is an exact approval
+impl From<U256> for ApprovalStrategy {
+    fn from(amount: U256) -> Self {
+        ApprovalStrategy::Exact(amount)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,51 +322,1176 @@ pub struct Token {
     pub address: Address,
     pub decimals: u8,
     pub token_name: String,
+    /// ERC20 `symbol()`, e.g. `WETH`, used to label amounts
+    pub symbol: String,
+}
+
+/// Tokens are identified by address only, the name and decimals may differ
+/// depending on where they were fetched from
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl Eq for Token {}
+
+impl Hash for Token {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.address.hash(state);
+    }
 }
 
 impl Token {
-    pub fn new(address: &str, decimals: u8, token_name: String) -> Self {
-        let address = Address::from_str(address).unwrap();
-        Token {
+    /// Fails instead of panicking when `address` is not a valid address, as it
+    /// often comes from user input. The symbol defaults to `token_name`, see `with_symbol`.
+    pub fn new(address: &str, decimals: u8, token_name: String) -> Result<Self> {
+        let address = Address::from_str(address)
+            .map_err(|e| anyhow::anyhow!("地址格式不正确{}: {}", address, e))?;
+        Ok(Token {
             address,
             decimals,
+            symbol: token_name.clone(),
             token_name,
-        }
+        })
     }
+
+    /// Replace the symbol used to label amounts
+    pub fn with_symbol(mut self, symbol: String) -> Self {
+        self.symbol = symbol;
+        self
+    }
+
+    /// Read the name, symbol and decimals of `address`, with the default `RpcRetryConfig`
     pub async fn new_from_online(address: &str, rpc_url: &str) -> Result<Self> {
-        let address = Address::from_str(address).unwrap();
-        let provider = ProviderBuilder::new().on_builtin(&rpc_url).await?;
-        let client = Arc::new(provider);
-        let contract = ERC20::new(address, client);
-        let name: String = contract.name().call().await?._0;
-        let decimals: u8 = contract.decimals().call().await?._0;
+        Self::new_from_online_with_config(address, rpc_url, &RpcRetryConfig::default()).await
+    }
+
+    /// Same as `new_from_online` with a custom timeout and number of retries,
+    /// applied to connecting to `rpc_url` and to every ERC20 call
+    pub async fn new_from_online_with_config(
+        address: &str,
+        rpc_url: &str,
+        config: &RpcRetryConfig,
+    ) -> Result<Self> {
+        // 先校验地址，避免无效输入还要访问 RPC
+        let address = Address::from_str(address)
+            .map_err(|e| anyhow::anyhow!("地址格式不正确{}: {}", address, e))?;
+        let provider = retry_rpc(config, || async {
+            Ok(ProviderBuilder::new().on_builtin(rpc_url).await?)
+        })
+        .await?;
+        Self::new_from_online_with_provider(address, Arc::new(provider), config).await
+    }
+
+    /// Same as `new_from_online_with_config` but reuses an already constructed provider
+    pub async fn new_from_online_with_provider<T, P>(
+        address: Address,
+        client: P,
+        config: &RpcRetryConfig,
+    ) -> Result<Self>
+    where
+        T: Transport + Clone,
+        P: Provider<T>,
+    {
+        let contract = ERC20::new(address, &client);
+        let name = retry_rpc(config, || async {
+            let res = contract.name().call().await.map(|name| name._0);
+            string_or_bytes32(res, address, ERC20::nameCall {}.abi_encode(), &client).await
+        })
+        .await?;
+        let decimals = retry_rpc(config, || async {
+            Ok(contract.decimals().call().await?._0)
+        })
+        .await?;
+        let symbol = retry_rpc(config, || async {
+            let res = contract.symbol().call().await.map(|symbol| symbol._0);
+            string_or_bytes32(res, address, ERC20::symbolCall {}.abi_encode(), &client).await
+        })
+        .await?;
 
         Ok(Self {
             address,
             decimals,
             token_name: name,
+            symbol,
         })
     }
+
+    /// Load the token from `store`, falling back to `new_from_online` and saving
+    /// the result to `store` on a miss
+    pub async fn load<S: TokenStore>(address: &str, store: &mut S, rpc_url: &str) -> Result<Self> {
+        let key = Address::from_str(address)?;
+        if let Some(token) = store.get(&key) {
+            return Ok(token);
+        }
+        let token = Self::new_from_online(address, rpc_url).await?;
+        store.put(token.clone());
+        Ok(token)
+    }
+
+    /// Read `totalSupply()` in the token's smallest unit
+    pub async fn total_supply(&self, rpc_url: &str) -> Result<U256> {
+        let provider = ProviderBuilder::new().on_builtin(rpc_url).await?;
+        let contract = ERC20::new(self.address, provider);
+        Ok(contract.totalSupply().call().await?._0)
+    }
+
+    /// `total_supply` scaled by the stored `decimals`, failing instead of panicking
+    /// when the decimals are out of range
+    pub async fn readable_total_supply(&self, rpc_url: &str) -> Result<f64> {
+        let total_supply = self.total_supply(rpc_url).await?;
+        try_to_readable_amount(total_supply, self.decimals)
+    }
+
+    /// Re-read `decimals()` and update the cached value, for tokens behind an
+    /// upgradeable proxy whose decimals may change. Returns the new decimals.
+    pub async fn refresh_decimals(&mut self, rpc_url: &str) -> Result<u8> {
+        let provider = ProviderBuilder::new().on_builtin(rpc_url).await?;
+        let contract = ERC20::new(self.address, provider);
+        self.decimals = contract.decimals().call().await?._0;
+        Ok(self.decimals)
+    }
+
+    /// `allowance(owner, spender)` of this token
+    pub async fn allowance<T, P>(&self, owner: Address, spender: Address, client: P) -> Result<U256>
+    where
+        T: Transport + Clone,
+        P: Provider<T>,
+    {
+        let contract = ERC20::new(self.address, client);
+        Ok(contract.allowance(owner, spender).call().await?._0)
+    }
+
+    /// Whether this is one of the known USD stablecoins of `chain`, see `tokens::stablecoins`
+    pub fn is_stablecoin(&self, chain: &UniswapSupportChain) -> bool {
+        tokens::stablecoins(chain).contains(&self.address)
+    }
+
+    /// Build an ERC20 `approve(spender, amount)` transaction for this token.
+    /// `strategy` is either an exact amount or `ApprovalStrategy::Infinite`.
+    pub fn approve_tx(
+        &self,
+        spender: Address,
+        strategy: impl Into<ApprovalStrategy>,
+    ) -> TransactionRequest {
+        TransactionRequest::default()
+            .with_to(self.address)
+            .with_input(
+                ERC20::approveCall {
+                    _spender: spender,
+                    _value: strategy.into().amount(),
+                }
+                .abi_encode(),
+            )
+    }
+
+    /// Read `DOMAIN_SEPARATOR()` and `nonces(owner)` of a token with a native
+    /// `permit`, the two values the permit digest depends on. Works for both
+    /// EIP-2612 tokens and DAI.
+    pub async fn permit_domain_and_nonce<T, P>(
+        &self,
+        owner: Address,
+        client: P,
+    ) -> Result<(B256, U256)>
+    where
+        T: Transport + Clone,
+        P: Provider<T>,
+    {
+        let contract = ERC20_PERMIT::new(self.address, &client);
+        let domain_separator = contract.DOMAIN_SEPARATOR();
+        let nonce = contract.nonces(owner);
+        let (domain_separator, nonce) = futures::try_join!(
+            domain_separator.call().into_future(),
+            nonce.call().into_future()
+        )?;
+        Ok((domain_separator._0, nonce._0))
+    }
+
+    /// Build the EIP-2612 `permit(owner, spender, value, deadline, v, r, s)`
+    /// transaction from the owner's signature of `params.digest(..)`
+    pub fn permit_tx(&self, params: &PermitParams, signature: &Signature) -> TransactionRequest {
+        let (v, r, s) = split_signature(signature);
+        TransactionRequest::default()
+            .with_to(self.address)
+            .with_input(
+                ERC20_PERMIT::permitCall {
+                    owner: params.owner,
+                    spender: params.spender,
+                    value: params.value,
+                    deadline: params.deadline,
+                    v,
+                    r,
+                    s,
+                }
+                .abi_encode(),
+            )
+    }
+
+    /// Build DAI's `permit(holder, spender, nonce, expiry, allowed, v, r, s)`
+    /// transaction from the holder's signature of `params.digest(..)`
+    pub fn dai_permit_tx(
+        &self,
+        params: &DaiPermitParams,
+        signature: &Signature,
+    ) -> TransactionRequest {
+        let (v, r, s) = split_signature(signature);
+        TransactionRequest::default()
+            .with_to(self.address)
+            .with_input(
+                DAI_PERMIT::permitCall {
+                    holder: params.holder,
+                    spender: params.spender,
+                    nonce: params.nonce,
+                    expiry: params.expiry,
+                    allowed: params.allowed,
+                    v,
+                    r,
+                    s,
+                }
+                .abi_encode(),
+            )
+    }
+}
+
+/// An EIP-2612 permit letting `spender` spend `value` of the owner's tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermitParams {
+    pub owner: Address,
+    pub spender: Address,
+    pub value: U256,
+    /// 当前的 `nonces(owner)`
+    pub nonce: U256,
+    pub deadline: U256,
+}
+
+impl PermitParams {
+    /// The digest the owner has to sign, given the token's `DOMAIN_SEPARATOR()`
+    pub fn digest(&self, domain_separator: B256) -> B256 {
+        let permit = Permit {
+            owner: self.owner,
+            spender: self.spender,
+            value: self.value,
+            nonce: self.nonce,
+            deadline: self.deadline,
+        };
+        eip712_digest(domain_separator, permit.eip712_hash_struct())
+    }
+}
+
+/// DAI's permit, which sets the allowance to `U256::MAX` when `allowed` is true
+/// and to zero otherwise; `expiry` zero means it never expires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DaiPermitParams {
+    pub holder: Address,
+    pub spender: Address,
+    pub nonce: U256,
+    pub expiry: U256,
+    pub allowed: bool,
+}
+
+impl DaiPermitParams {
+    /// The digest the holder has to sign, given DAI's `DOMAIN_SEPARATOR()`
+    pub fn digest(&self, domain_separator: B256) -> B256 {
+        let permit = dai::Permit {
+            holder: self.holder,
+            spender: self.spender,
+            nonce: self.nonce,
+            expiry: self.expiry,
+            allowed: self.allowed,
+        };
+        eip712_digest(domain_separator, permit.eip712_hash_struct())
+    }
+}
+
+fn eip712_digest(domain_separator: B256, struct_hash: B256) -> B256 {
+    // keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))
+    let mut buf = [0u8; 66];
+    buf[..2].copy_from_slice(&[0x19, 0x01]);
+    buf[2..34].copy_from_slice(domain_separator.as_slice());
+    buf[34..].copy_from_slice(struct_hash.as_slice());
+    keccak256(buf)
+}
+
+/// `permit` 需要 v 为 27/28
+fn split_signature(signature: &Signature) -> (u8, B256, B256) {
+    (
+        signature.v().y_parity_byte() + 27,
+        signature.r().into(),
+        signature.s().into(),
+    )
+}
+
+/// The string returned by `name()` or `symbol()`, also accepting tokens that
+/// return `bytes32` instead
+async fn string_or_bytes32<T, P>(
+    res: Result<String, alloy::contract::Error>,
+    address: Address,
+    input: Vec<u8>,
+    client: &P,
+) -> Result<String>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+{
+    match res {
+        Ok(value) => Ok(value),
+        // MKR、SAI 等早期 token 返回 bytes32 而不是 string
+        Err(alloy::contract::Error::AbiError(e)) => {
+            let tx = TransactionRequest::default()
+                .with_to(address)
+                .with_input(input);
+            let data = client.call(&tx).await?;
+            Ok(decode_bytes32_string(&data).ok_or(e)?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Decode a zero padded `bytes32` return value as a UTF-8 string
+fn decode_bytes32_string(data: &[u8]) -> Option<String> {
+    if data.len() != 32 {
+        return None;
+    }
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8(data[..end].to_vec()).ok()
+}
+
+/// Persistent token metadata used by `Token::load`, e.g. backed by a token list
+/// file or a database
+pub trait TokenStore {
+    fn get(&self, address: &Address) -> Option<Token>;
+    fn put(&mut self, token: Token);
+}
+
+/// In-memory store, lost when the process exits
+impl TokenStore for HashMap<Address, Token> {
+    fn get(&self, address: &Address) -> Option<Token> {
+        HashMap::get(self, address).cloned()
+    }
+
+    fn put(&mut self, token: Token) {
+        self.insert(token.address, token);
+    }
+}
+
+/// Read the `token_in` and `token_out` balances of `account` before and after
+/// running `action` (e.g. sending a swap) and return the signed
+/// `(delta_in, delta_out)`; `delta_in` is negative when tokens were spent
+pub async fn balance_changes<T, P, F, Fut>(
+    client: P,
+    account: Address,
+    token_in: Address,
+    token_out: Address,
+    action: F,
+) -> Result<(I256, I256)>
+where
+    T: Transport + Clone,
+    P: Provider<T>,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let token_in = ERC20::new(token_in, &client);
+    let token_out = ERC20::new(token_out, &client);
+    let balance_in = token_in.balanceOf(account);
+    let balance_out = token_out.balanceOf(account);
+    let read_balances = || async {
+        futures::try_join!(
+            balance_in.call().into_future(),
+            balance_out.call().into_future()
+        )
+        .map(|(balance_in, balance_out)| (balance_in.balance, balance_out.balance))
+    };
+    let (in_before, out_before) = read_balances().await?;
+    action().await?;
+    let (in_after, out_after) = read_balances().await?;
+    Ok((
+        signed_delta(in_before, in_after)?,
+        signed_delta(out_before, out_after)?,
+    ))
+}
+
+fn signed_delta(before: U256, after: U256) -> Result<I256> {
+    let to_signed =
+        |v: U256| I256::try_from(v).map_err(|e| anyhow::anyhow!("余额超出 int256 范围{}", e));
+    Ok(to_signed(after)? - to_signed(before)?)
 }
 
 #[cfg(test)]
 mod tests {
-    use alloy::primitives::address;
+    use std::collections::HashSet;
+
+    use alloy::{
+        hex,
+        primitives::{b256, Bytes},
+        signers::{local::PrivateKeySigner, SignerSync},
+        sol_types::Eip712Domain,
+    };
+
+    use crate::test_utils::MockTransport;
 
     use super::*;
 
+    #[test]
+    fn test_try_to_readable_amount() {
+        assert_eq!(
+            1.5,
+            try_to_readable_amount(U256::from(15u128 * 10u128.pow(17)), 18).unwrap()
+        );
+        assert_eq!(0.0, try_to_readable_amount(U256::ZERO, 77).unwrap());
+        // format_units 最多支持 77 位小数，超过时返回错误而不是 panic
+        assert!(try_to_readable_amount(U256::from(1), 78).is_err());
+        assert!(try_to_readable_amount(U256::MAX, u8::MAX).is_err());
+    }
+
+    #[test]
+    fn test_to_readable_string_round_trip() {
+        for amount in ["123456.789012", "0.000001", "1", "99999999999999.5"] {
+            let raw = from_readable_str(amount, 6).unwrap();
+            assert_eq!(amount, to_readable_string(raw, 6));
+        }
+        assert_eq!(
+            U256::from(123_456_789_012u64),
+            from_readable_str(&to_readable_string(U256::from(123_456_789_012u64), 6), 6).unwrap()
+        );
+        assert_eq!("0", to_readable_string(U256::ZERO, 6));
+        assert_eq!(
+            "1.5",
+            to_readable_string(U256::from(15u64 * 10u64.pow(17)), 18)
+        );
+        assert_eq!("42", to_readable_string(U256::from(42), 0));
+        // f64 会丢失的精度
+        assert_eq!(
+            "115792089237316195423570985008687907853269984665640564039457.584007913129639935",
+            to_readable_string(U256::MAX, 18)
+        );
+    }
+
+    #[test]
+    fn test_checked_amount_helpers() {
+        let amount = U256::from(1_000_000u64);
+        assert_eq!(U256::from(5_000u64), mul_bps(amount, 50).unwrap());
+        assert_eq!(U256::from(995_000u64), sub_slippage(amount, 50).unwrap());
+        assert_eq!(U256::from(1_200_000u64), add_buffer(amount, 2_000).unwrap());
+        assert_eq!(U256::ZERO, sub_slippage(amount, 10_000).unwrap());
+
+        // U256::MAX 处溢出
+        assert_eq!(
+            U256::MAX / U256::from(10_000),
+            mul_bps(U256::MAX, 1).unwrap()
+        );
+        assert!(mul_bps(U256::MAX, 2).is_err());
+        assert!(add_buffer(U256::MAX, 0).is_ok());
+        assert!(add_buffer(U256::MAX - U256::from(1), 1).is_err());
+        // 滑点超过 100% 时低于零
+        assert!(sub_slippage(amount, 10_001).is_err());
+        assert!(sub_slippage(U256::from(1), 20_000).is_err());
+    }
+
+    #[test]
+    fn test_token_eq_by_address() {
+        let address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+        let weth = Token::new(address, 18, "WETH".to_string()).unwrap();
+        let wrapped_ether = Token::new(address, 18, "Wrapped Ether".to_string()).unwrap();
+        assert_eq!(weth, wrapped_ether);
+
+        let tokens: HashSet<Token> = [weth, wrapped_ether].into_iter().collect();
+        assert_eq!(1, tokens.len());
+        assert!(!tokens.contains(
+            &Token::new(
+                "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                6,
+                "USDC".to_string()
+            )
+            .unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_token_new_invalid_address() {
+        for address in ["", "0x1234", "C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2zz"] {
+            assert!(Token::new(address, 18, "WETH".to_string()).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_from_online_invalid_address() {
+        // 地址校验在连接 RPC 之前
+        let err = Token::new_from_online("0xZZZ", "http://127.0.0.1:1")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("0xZZZ"));
+    }
+
+    #[tokio::test]
+    async fn test_new_from_online_timeout() {
+        let address = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let config = RpcRetryConfig {
+            timeout: Duration::from_millis(20),
+            retries: 1,
+            backoff: Duration::from_millis(10),
+        };
+        let transport = MockTransport::default().with_delay(Duration::from_secs(5));
+        for _ in 0..2 {
+            transport.push_response(serde_json::json!(Bytes::from(
+                ERC20::nameCall::abi_encode_returns(&("Wrapped Ether".to_string(),))
+            )));
+        }
+
+        let started = std::time::Instant::now();
+        let err = Token::new_from_online_with_provider(address, transport.provider(), &config)
+            .await
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let err = err.downcast_ref::<RpcRetryError>().unwrap();
+        assert_eq!("TIMEOUT", err.code());
+        assert!(matches!(err, RpcRetryError::Timeout(timeout) if *timeout == config.timeout));
+        // 超时后重试了一次 name()，没有继续请求 decimals()
+        let requests = transport.requests();
+        assert_eq!(2, requests.len());
+        assert!(requests.iter().all(|r| r.method == "eth_call"));
+    }
+
+    #[tokio::test]
+    async fn test_new_from_online_within_timeout() {
+        let address = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let transport = MockTransport::default().with_delay(Duration::from_millis(10));
+        transport.push_response(serde_json::json!(Bytes::from(
+            ERC20::nameCall::abi_encode_returns(&("Wrapped Ether".to_string(),))
+        )));
+        transport.push_response(serde_json::json!(B256::from(U256::from(18))));
+        transport.push_response(serde_json::json!(Bytes::from(
+            ERC20::symbolCall::abi_encode_returns(&("WETH".to_string(),))
+        )));
+
+        let token = Token::new_from_online_with_provider(
+            address,
+            transport.provider(),
+            &RpcRetryConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!("Wrapped Ether", token.token_name);
+        assert_eq!("WETH", token.symbol);
+        assert_eq!(18, token.decimals);
+    }
+
+    #[tokio::test]
+    async fn test_retry_rpc_stops_on_other_errors() {
+        let config = RpcRetryConfig {
+            timeout: Duration::from_millis(20),
+            retries: 3,
+            backoff: Duration::from_millis(10),
+        };
+        let mut attempts = 0;
+        let res: Result<()> = retry_rpc(&config, || {
+            attempts += 1;
+            async { Err(anyhow::anyhow!("execution reverted")) }
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(1, attempts);
+
+        let mut attempts = 0;
+        let res = retry_rpc(&config, || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt < 3 {
+                    // 前两次模拟节点卡住
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+                Ok(attempt)
+            }
+        })
+        .await;
+        assert_eq!(3, res.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_retry_rpc_backoff() {
+        use alloy::rpc::json_rpc::ErrorPayload;
+
+        let rate_limited = |payload: &str| -> anyhow::Error {
+            TransportError::ErrorResp(serde_json::from_str::<ErrorPayload>(payload).unwrap()).into()
+        };
+        let config = RpcRetryConfig {
+            timeout: Duration::from_secs(1),
+            retries: 2,
+            backoff: Duration::from_millis(50),
+        };
+        // 没有等待时间提示时按 50ms、100ms 指数退避
+        let mut attempts = Vec::new();
+        let res: Result<()> = retry_rpc(&config, || {
+            attempts.push(std::time::Instant::now());
+            async {
+                Err(rate_limited(
+                    r#"{"code":429,"message":"Too Many Requests"}"#,
+                ))
+            }
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(3, attempts.len());
+        assert!(attempts[1] - attempts[0] >= Duration::from_millis(50));
+        assert!(attempts[2] - attempts[1] >= Duration::from_millis(100));
+
+        // 节点返回的 backoff_seconds 优先于配置的退避时间
+        let config = RpcRetryConfig {
+            backoff: Duration::from_secs(30),
+            ..config
+        };
+        let mut attempts = Vec::new();
+        let res = retry_rpc(&config, || {
+            attempts.push(std::time::Instant::now());
+            let attempt = attempts.len();
+            async move {
+                if attempt == 1 {
+                    return Err(rate_limited(
+                        r#"{"code":-32005,"message":"daily request count exceeded","data":{"rate":{"backoff_seconds":1}}}"#,
+                    ));
+                }
+                Ok(attempt)
+            }
+        })
+        .await;
+        assert_eq!(2, res.unwrap());
+        let waited = attempts[1] - attempts[0];
+        assert!(waited >= Duration::from_secs(1) && waited < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_format_token_amount() {
+        let weth = Token::new(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+            18,
+            "WETH".to_string(),
+        )
+        .unwrap();
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USDC".to_string(),
+        )
+        .unwrap();
+        let amount = from_readable_str("1234.56", 18).unwrap();
+        assert_eq!("1,234.56 WETH", format_token_amount(amount, &weth));
+        assert_eq!("0 WETH", format_token_amount(U256::ZERO, &weth));
+        assert_eq!(
+            "1,000,000 USDC",
+            format_token_amount(U256::from(1_000_000_000_000u64), &usdc)
+        );
+        assert_eq!(
+            "999.5 USDC",
+            format_token_amount(U256::from(999_500_000), &usdc)
+        );
+        // 1 wei 不使用科学计数法
+        assert_eq!(
+            "0.000000000000000001 WETH",
+            format_token_amount(U256::from(1), &weth)
+        );
+        assert_eq!(
+            "12,345,678.000001 USDC",
+            format_token_amount(U256::from(12_345_678_000_001u64), &usdc)
+        );
+
+        // 标注使用 symbol 而不是 name
+        let weth = Token::new(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+            18,
+            "Wrapped Ether".to_string(),
+        )
+        .unwrap()
+        .with_symbol("WETH".to_string());
+        assert_eq!(
+            "1 WETH",
+            format_token_amount(U256::from(10).pow(U256::from(18)), &weth)
+        );
+        // 超过 77 位精度时 format_units 会失败，这里不能 panic
+        let odd = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            80,
+            "ODD".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            format!("0.{}1 ODD", "0".repeat(79)),
+            format_token_amount(U256::from(1), &odd)
+        );
+        let odd = Token {
+            decimals: 78,
+            ..odd
+        };
+        assert_eq!(
+            "0.115792089237316195423570985008687907853269984665640564039457584007913129639935 ODD",
+            format_token_amount(U256::MAX, &odd)
+        );
+    }
+
+    #[test]
+    fn test_decode_bytes32_string() {
+        let mut maker = [0u8; 32];
+        maker[..5].copy_from_slice(b"Maker");
+        assert_eq!(Some("Maker".to_string()), decode_bytes32_string(&maker));
+        assert_eq!(Some(String::new()), decode_bytes32_string(&[0u8; 32]));
+        // string 编码的返回值不是 32 字节
+        let encoded = ERC20::nameCall::abi_encode_returns(&("Maker".to_string(),));
+        assert_eq!(None, decode_bytes32_string(&encoded));
+        assert_eq!(None, decode_bytes32_string(&[0xff; 32]));
+    }
+
+    #[tokio::test]
+    async fn test_load_from_store() {
+        let address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+        let mut store: HashMap<Address, Token> = HashMap::new();
+        // 没有可用的 RPC，未命中时只能失败
+        let unreachable_rpc = "http://127.0.0.1:1";
+        assert!(Token::load(address, &mut store, unreachable_rpc)
+            .await
+            .is_err());
+
+        store.put(Token::new(address, 18, "Wrapped Ether".to_string()).unwrap());
+        let token = Token::load(address, &mut store, unreachable_rpc)
+            .await
+            .unwrap();
+        assert_eq!(18, token.decimals);
+        assert_eq!("Wrapped Ether", token.token_name);
+    }
+
+    #[tokio::test]
+    async fn test_balance_changes() {
+        let transport = MockTransport::default();
+        // 兑换前后 token_in 和 token_out 的 balanceOf
+        for balance in [1000u64, 5, 400, 905] {
+            transport.push_response(serde_json::json!(U256::from(balance).to_be_bytes::<32>()));
+        }
+        let account = Address::from_str("0xCa017e24f449Ec454E94C843bbbF2cE61b7F6B69").unwrap();
+        let weth = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let (delta_in, delta_out) =
+            balance_changes(transport.provider(), account, weth, usdc, || async {
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(I256::try_from(-600).unwrap(), delta_in);
+        assert_eq!(I256::try_from(900).unwrap(), delta_out);
+        assert_eq!(4, transport.requests().len());
+    }
+
+    #[test]
+    fn test_revert_reason() {
+        let data = Revert::from("SPL").abi_encode();
+        assert_eq!(Some("SPL".to_string()), revert_reason(&data));
+        assert_eq!(None, revert_reason(&[]));
+    }
+
     #[test]
     fn test_from_readable_amount() {
         let amount_in = from_readable_amount(1.0, 18);
         assert_eq!(U256::from(1000000000000000000u128), amount_in);
     }
 
+    #[test]
+    fn test_from_readable_str() {
+        assert_eq!(
+            U256::from(1500000000000000000u128),
+            from_readable_str("1.5", 18).unwrap()
+        );
+        // 18 位精度时 f64 会丢失精度
+        assert_eq!(
+            U256::from(123456789123456789123456789u128),
+            from_readable_str("123456789.123456789123456789", 18).unwrap()
+        );
+        assert_eq!(U256::from(2000000u64), from_readable_str("2", 6).unwrap());
+        assert_eq!(U256::from(500000u64), from_readable_str(".5", 6).unwrap());
+        assert_eq!(U256::from(1000000u64), from_readable_str("1.", 6).unwrap());
+    }
+
+    #[test]
+    fn test_from_readable_str_trailing_zeros() {
+        assert_eq!(
+            U256::from(1500000u64),
+            from_readable_str("1.500000000000", 6).unwrap()
+        );
+        assert_eq!(U256::from(1u64), from_readable_str("1.000", 0).unwrap());
+    }
+
+    #[test]
+    fn test_from_readable_str_invalid() {
+        // 小数位数超过精度
+        assert!(from_readable_str("1.0000001", 6).is_err());
+        assert!(from_readable_str("0.5", 0).is_err());
+        assert!(from_readable_str("", 18).is_err());
+        assert!(from_readable_str(".", 18).is_err());
+        assert!(from_readable_str("-1", 18).is_err());
+        assert!(from_readable_str("1.2.3", 18).is_err());
+        assert!(from_readable_str("1e18", 18).is_err());
+        assert!(from_readable_str(&"9".repeat(80), 18).is_err());
+    }
+
     #[test]
     fn test_to_readable_amount() {
         let human_readable_num = to_readable_amount(U256::from(1000000000000000000u128), 18);
         assert_eq!(1.0, human_readable_num)
     }
 
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_to_readable_decimal() {
+        let amount = U256::from(123456789123456789123456789u128);
+        let decimal = to_readable_decimal(amount, 18).unwrap();
+        assert_eq!("123456789.123456789123456789", decimal.to_string());
+        // f64 只能保留 15~17 位有效数字
+        let float = to_readable_amount(amount, 18);
+        assert_ne!(decimal.to_string(), float.to_string());
+
+        assert_eq!(
+            "1.000000",
+            to_readable_decimal(U256::from(1000000u64), 6)
+                .unwrap()
+                .to_string()
+        );
+        assert!(to_readable_decimal(U256::MAX, 18).is_err());
+    }
+
+    #[test]
+    fn test_is_stablecoin() {
+        let chain = UniswapSupportChain::Ethereum;
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USDC".to_string(),
+        )
+        .unwrap();
+        let weth = Token::new(
+            "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2",
+            18,
+            "WETH".to_string(),
+        )
+        .unwrap();
+        assert!(usdc.is_stablecoin(&chain));
+        assert!(!weth.is_stablecoin(&chain));
+        // 主网 USDC 的地址在 Base 上不是 USDC
+        assert!(!usdc.is_stablecoin(&UniswapSupportChain::Base));
+    }
+
+    #[test]
+    fn test_approve_tx() {
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USD Coin".to_string(),
+        )
+        .unwrap();
+        let spender = Address::from_str("0x000000000022D473030F116dDEE9F6B43aC78BA3").unwrap();
+        let tx = usdc.approve_tx(spender, U256::from(100));
+        let call = ERC20::approveCall::abi_decode(tx.input.input().unwrap(), true).unwrap();
+        assert_eq!(spender, call._spender);
+        assert_eq!(U256::from(100), call._value);
+    }
+
+    #[test]
+    fn test_approve_tx_infinite() {
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USD Coin".to_string(),
+        )
+        .unwrap();
+        let spender = Address::from_str("0xE592427A0AEce92De3Edee1F18E0157C05861564").unwrap();
+        let tx = usdc.approve_tx(spender, ApprovalStrategy::Infinite);
+        let input = tx.input.input().unwrap();
+        // calldata 最后 32 字节是 value，全部为 0xff
+        assert_eq!([0xff; 32], input[input.len() - 32..]);
+        let call = ERC20::approveCall::abi_decode(input, true).unwrap();
+        assert_eq!(U256::MAX, call._value);
+
+        let exact = usdc.approve_tx(spender, ApprovalStrategy::Exact(U256::from(100)));
+        assert_eq!(usdc.approve_tx(spender, U256::from(100)).input, exact.input);
+    }
+
+    fn usdc_permit() -> PermitParams {
+        PermitParams {
+            owner: Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap(),
+            spender: Address::from_str("0xE592427A0AEce92De3Edee1F18E0157C05861564").unwrap(),
+            value: U256::from(1000000000u64),
+            nonce: U256::ZERO,
+            deadline: U256::from(1735689600u64),
+        }
+    }
+
+    #[test]
+    fn test_permit_digest() {
+        // USDC.DOMAIN_SEPARATOR() on mainnet
+        let domain_separator =
+            b256!("06c37168a7db5138defc7866392bb87a741f9b3d104deb5094588ce041cae335");
+        let domain = Eip712Domain {
+            name: Some("USD Coin".into()),
+            version: Some("2".into()),
+            chain_id: Some(U256::from(1)),
+            verifying_contract: Some(
+                Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap(),
+            ),
+            salt: None,
+        };
+        assert_eq!(domain_separator, domain.separator());
+        assert_eq!(
+            b256!("969a9dbd99fdf33d605e211738b42b9261fea8f3ab9b6bf222efaf619a51298f"),
+            usdc_permit().digest(domain_separator)
+        );
+    }
+
+    #[test]
+    fn test_dai_permit_digest() {
+        // DAI.DOMAIN_SEPARATOR() on mainnet
+        let domain_separator =
+            b256!("dbb8cf42e1ecb028be3f3dbc922e1d878b963f411dc388ced501601c60f7c6f7");
+        let params = DaiPermitParams {
+            holder: Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap(),
+            spender: Address::from_str("0xE592427A0AEce92De3Edee1F18E0157C05861564").unwrap(),
+            nonce: U256::from(3),
+            expiry: U256::from(1735689600u64),
+            allowed: true,
+        };
+        assert_eq!(
+            b256!("160b1789e3ead785eb33f90cac106d3b9d4d1b683d73973c96bed9e1b824f429"),
+            params.digest(domain_separator)
+        );
+        // 同样的字段，EIP-2612 的类型哈希不同
+        assert_ne!(
+            usdc_permit().digest(domain_separator),
+            params.digest(domain_separator)
+        );
+    }
+
+    #[test]
+    fn test_permit_tx() {
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USD Coin".to_string(),
+        )
+        .unwrap();
+        // anvil 的第二个默认账户
+        let signer: PrivateKeySigner =
+            "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+                .parse()
+                .unwrap();
+        let params = usdc_permit();
+        assert_eq!(signer.address(), params.owner);
+        let digest = params.digest(b256!(
+            "06c37168a7db5138defc7866392bb87a741f9b3d104deb5094588ce041cae335"
+        ));
+        let signature = signer.sign_hash_sync(&digest).unwrap();
+
+        let tx = usdc.permit_tx(&params, &signature);
+        assert_eq!(Some(usdc.address), tx.to.and_then(|to| to.to().copied()));
+        let input = tx.input.input().unwrap();
+        assert_eq!(hex!("d505accf"), input[..4]);
+        let call = ERC20_PERMIT::permitCall::abi_decode(input, true).unwrap();
+        assert_eq!(params.owner, call.owner);
+        assert_eq!(params.value, call.value);
+        assert_eq!(params.deadline, call.deadline);
+        assert!(call.v == 27 || call.v == 28);
+        // 合约里 ecrecover 的结果必须是 owner
+        let recovered = Signature::from_rs_and_parity(call.r.into(), call.s.into(), call.v == 28)
+            .unwrap()
+            .recover_address_from_prehash(&digest)
+            .unwrap();
+        assert_eq!(params.owner, recovered);
+
+        let dai = Token::new(
+            "0x6B175474E89094C44Da98b954EedeAC495271d0F",
+            18,
+            "Dai Stablecoin".to_string(),
+        )
+        .unwrap();
+        let dai_params = DaiPermitParams {
+            holder: params.owner,
+            spender: params.spender,
+            nonce: U256::ZERO,
+            expiry: U256::ZERO,
+            allowed: true,
+        };
+        let tx = dai.dai_permit_tx(&dai_params, &signature);
+        let input = tx.input.input().unwrap();
+        assert_eq!(hex!("8fcbaf0c"), input[..4]);
+        let call = DAI_PERMIT::permitCall::abi_decode(input, true).unwrap();
+        assert!(call.allowed);
+        assert_eq!(dai_params.holder, call.holder);
+    }
+
+    #[tokio::test]
+    async fn test_permit_domain_and_nonce() {
+        let domain_separator =
+            b256!("06c37168a7db5138defc7866392bb87a741f9b3d104deb5094588ce041cae335");
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(domain_separator));
+        transport.push_response(serde_json::json!(U256::from(7).to_be_bytes::<32>()));
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USD Coin".to_string(),
+        )
+        .unwrap();
+        let owner = Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap();
+        let (separator, nonce) = usdc
+            .permit_domain_and_nonce(owner, transport.provider())
+            .await
+            .unwrap();
+        assert_eq!(domain_separator, separator);
+        assert_eq!(U256::from(7), nonce);
+    }
+
+    #[tokio::test]
+    async fn test_allowance() {
+        let transport = MockTransport::default();
+        transport.push_response(serde_json::json!(U256::from(100).to_be_bytes::<32>()));
+        let usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            6,
+            "USD Coin".to_string(),
+        )
+        .unwrap();
+        let owner = Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap();
+        let spender = Address::from_str("0xE592427A0AEce92De3Edee1F18E0157C05861564").unwrap();
+        assert_eq!(
+            U256::from(100),
+            usdc.allowance(owner, spender, transport.provider())
+                .await
+                .unwrap()
+        );
+        let call = ERC20::allowanceCall::abi_decode(
+            &alloy::hex::decode(transport.requests()[0].params[0]["input"].as_str().unwrap())
+                .unwrap(),
+            true,
+        )
+        .unwrap();
+        assert_eq!((owner, spender), (call._owner, call._spender));
+    }
+}
+
+#[cfg(all(test, feature = "live-tests"))]
+mod live_tests {
+    use crate::{
+        client::{swap, SwapDirection, SwapParams, UniswapSupportChain, UniswapVersion},
+        test_utils::spawn_mainnet_fork,
+        uniswapv3_quoter::{self, QuoteExactInputSingleParams, UniswapV3QuoterCommand},
+        uniswapv3_router::UNIV3_ROUTER_CONTRACT_ADDR,
+        unswapv3_pool::UniswapPoolFee,
+        weth::{weth_address, wrap_tx},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_balance_changes_on_fork() {
+        let fork = spawn_mainnet_fork();
+        let chain = UniswapSupportChain::Ethereum;
+        // anvil 的默认账户已解锁，可以直接 eth_sendTransaction
+        let from = fork.addresses()[0];
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .on_http(fork.endpoint_url());
+        let weth = Token::new(&weth_address(&chain).to_string(), 18, "WETH".to_string()).unwrap();
+        let usdc = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let amount_in = from_readable_amount(1.0, 18);
+
+        let router = Address::from_str(UNIV3_ROUTER_CONTRACT_ADDR).unwrap();
+        for tx in [
+            wrap_tx(&chain, amount_in),
+            weth.approve_tx(router, amount_in),
+        ] {
+            provider
+                .send_transaction(tx.with_from(from))
+                .await
+                .unwrap()
+                .get_receipt()
+                .await
+                .unwrap();
+        }
+
+        let command = UniswapV3QuoterCommand::QuoteExactInputSingle(QuoteExactInputSingleParams {
+            token_in: weth.address,
+            token_out: usdc,
+            fee: UniswapPoolFee::Fee500,
+            amount_in,
+            sqrt_price_limit_x96: U256::ZERO,
+        });
+        let uniswapv3_quoter::UniswapV3QuoterResult::QuoteExactInputSingle(quote) =
+            uniswapv3_quoter::execute(command, fork.endpoint())
+                .await
+                .unwrap()
+        else {
+            panic!("unexpected result");
+        };
+        // 1% 滑点
+        let amount_out_min = quote * U256::from(99) / U256::from(100);
+        let params = SwapParams::builder()
+            .token_in(weth.address)
+            .token_out(usdc)
+            .amount_in(amount_in)
+            .amount_out_min(amount_out_min)
+            .pool_fee(UniswapPoolFee::Fee500)
+            .recipient(from)
+            .build()
+            .unwrap();
+        let tx = swap(
+            chain,
+            SwapDirection::ExactInput,
+            UniswapVersion::V3,
+            params,
+            fork.endpoint(),
+        )
+        .await
+        .unwrap();
+
+        let (delta_in, delta_out) =
+            balance_changes(&provider, from, weth.address, usdc, || async {
+                provider
+                    .send_transaction(tx.with_from(from))
+                    .await?
+                    .get_receipt()
+                    .await?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(-I256::try_from(amount_in).unwrap(), delta_in);
+        let delta_out = delta_out.into_raw();
+        assert!(delta_out >= amount_out_min && delta_out <= quote);
+    }
+
+    #[tokio::test]
+    async fn test_load_writes_back_to_store() {
+        let address = "0xA35923162C49cF95e6BF26623385eb431ad920D3";
+        let mut store: HashMap<Address, Token> = HashMap::new();
+        let token = Token::load(address, &mut store, "https://eth.llamarpc.com")
+            .await
+            .unwrap();
+        assert_eq!(1, store.len());
+        // 第二次从 store 读取，RPC 不可用也能成功
+        let cached = Token::load(address, &mut store, "http://127.0.0.1:1")
+            .await
+            .unwrap();
+        assert_eq!(token, cached);
+        assert_eq!(token.token_name, cached.token_name);
+    }
+
+    #[tokio::test]
+    async fn test_get_bytes32_token_info() {
+        let token = Token::new_from_online(
+            "0x9f8F72aA9304c8B593d555F12eF6589cC3A579A2",
+            "https://eth.llamarpc.com",
+        )
+        .await
+        .unwrap();
+        assert_eq!("Maker", token.token_name);
+        assert_eq!("MKR", token.symbol);
+        assert_eq!(18, token.decimals);
+    }
+
     #[tokio::test]
     pub async fn test_get_token_info() {
         let mainet_rpc = "https://eth.llamarpc.com";
@@ -85,5 +1504,24 @@ mod tests {
         assert_eq!(token.address, expected_addr);
         assert_eq!(token.decimals, 18);
         assert_eq!(token.token_name, "Turbo");
+        assert_eq!(token.symbol, "TURBO");
+    }
+
+    #[tokio::test]
+    async fn test_usdc_total_supply() {
+        let mainet_rpc = "https://eth.llamarpc.com";
+        let mut usdc = Token::new(
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+            0,
+            "USD Coin".to_string(),
+        )
+        .unwrap();
+        // 故意设置错误的精度，refresh 后恢复为 6
+        assert_eq!(6, usdc.refresh_decimals(mainet_rpc).await.unwrap());
+        assert_eq!(6, usdc.decimals);
+        let total_supply = usdc.total_supply(mainet_rpc).await.unwrap();
+        assert!(total_supply > U256::ZERO);
+        let readable = usdc.readable_total_supply(mainet_rpc).await.unwrap();
+        assert!(readable > 1_000_000.0);
     }
 }
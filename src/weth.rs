@@ -0,0 +1,77 @@
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, U256},
+    rpc::types::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+
+use crate::{client::UniswapSupportChain, tokens};
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    WETH9,
+    "src/abi/weth9.json"
+);
+
+/// WETH contract address on the given chain
+pub fn weth_address(chain: &UniswapSupportChain) -> Address {
+    tokens::weth(chain)
+}
+
+/// Build a `deposit()` transaction that wraps `amount` of ETH into WETH
+pub fn wrap_tx(chain: &UniswapSupportChain, amount: U256) -> TransactionRequest {
+    TransactionRequest::default()
+        .with_to(weth_address(chain))
+        .with_input(WETH9::depositCall {}.abi_encode())
+        .with_value(amount)
+}
+
+/// Build a `withdraw(wad)` transaction that unwraps `amount` of WETH back into ETH
+pub fn unwrap_tx(chain: &UniswapSupportChain, amount: U256) -> TransactionRequest {
+    TransactionRequest::default()
+        .with_to(weth_address(chain))
+        .with_input(WETH9::withdrawCall { wad: amount }.abi_encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        hex,
+        primitives::{address, TxKind},
+    };
+
+    use crate::utils::from_readable_amount;
+
+    use super::*;
+
+    #[test]
+    fn test_wrap_tx() {
+        let amount = from_readable_amount(0.5, 18);
+        let tx = wrap_tx(&UniswapSupportChain::Ethereum, amount);
+        let data = tx.input.input().unwrap();
+        assert_eq!("d0e30db0", hex::encode(data));
+        assert_eq!(Some(amount), tx.value);
+        assert_eq!(
+            Some(TxKind::Call(weth_address(&UniswapSupportChain::Ethereum))),
+            tx.to
+        );
+    }
+
+    #[test]
+    fn test_unwrap_tx() {
+        let amount = from_readable_amount(0.5, 18);
+        let tx = unwrap_tx(&UniswapSupportChain::Base, amount);
+        let data = tx.input.input().unwrap();
+        assert_eq!("2e1a7d4d", hex::encode(&data[..4]));
+        assert_eq!(amount, U256::from_be_slice(&data[4..]));
+        assert_eq!(None, tx.value);
+        assert_eq!(
+            Some(TxKind::Call(address!(
+                "4200000000000000000000000000000000000006"
+            ))),
+            tx.to
+        );
+    }
+}